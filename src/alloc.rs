@@ -0,0 +1,53 @@
+// src/alloc.rs
+// Voice allocation: which voice to steal once polyphony is used up. Released
+// voices always go first; the policy picks among the rest.
+use crate::voice::Voice;
+use crate::MAX_VOICES;
+
+/// Pool capacity: polyphony plus room for stolen voices still fading out.
+pub const VOICE_POOL: usize = MAX_VOICES + 16;
+/// Seconds a stolen voice takes to fade to silence.
+pub const STEAL_FADE: f32 = 0.005;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StealPolicy {
+    Oldest,
+    /// Lowest amp-envelope level times velocity.
+    Quietest,
+    Lowest,
+    Highest,
+}
+impl StealPolicy {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            1 => Self::Quietest,
+            2 => Self::Lowest,
+            3 => Self::Highest,
+            _ => Self::Oldest,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Oldest => 0,
+            Self::Quietest => 1,
+            Self::Lowest => 2,
+            Self::Highest => 3,
+        }
+    }
+}
+
+/// Index of the voice to steal among those not already being stolen.
+pub fn pick_victim(voices: &[Voice], policy: StealPolicy) -> Option<usize> {
+    let by_policy = |a: &Voice, b: &Voice| match policy {
+        StealPolicy::Oldest => a.serial.cmp(&b.serial),
+        StealPolicy::Quietest => (a.env.level * a.vel).total_cmp(&(b.env.level * b.vel)),
+        StealPolicy::Lowest => a.midi_note.cmp(&b.midi_note),
+        StealPolicy::Highest => b.midi_note.cmp(&a.midi_note),
+    };
+    voices
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| !v.is_stolen())
+        .min_by(|(_, a), (_, b)| a.key_down.cmp(&b.key_down).then_with(|| by_policy(a, b)))
+        .map(|(i, _)| i)
+}
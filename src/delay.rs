@@ -0,0 +1,136 @@
+// src/delay.rs
+use crate::tempo::{SyncDivision, DEFAULT_BPM};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DelayMode {
+    Mono,     // L+R summed into one line, echoes centred
+    Stereo,   // independent L/R lines
+    PingPong, // summed input bounces L -> R -> L
+}
+impl DelayMode {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            0 => Self::Mono,
+            2 => Self::PingPong,
+            _ => Self::Stereo,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Mono => 0,
+            Self::Stereo => 1,
+            Self::PingPong => 2,
+        }
+    }
+}
+
+// --- Stereo feedback delay with fractional read + damping --------------
+pub struct SimpleDelay {
+    sample_rate: f32,
+    buffers: [Vec<f32>; 2],
+    write_pos: usize,
+    length: usize,
+    time_seconds: f32,
+    /// Use `division` at `tempo` for the delay time instead of `time_seconds`.
+    pub sync: bool,
+    pub division: SyncDivision,
+    tempo: f32,
+    pub feedback: f32,
+    pub wet: f32,
+    pub mode: DelayMode,
+
+    // feedback path lowpass (warmer repeats)
+    fb_lp_state: [f32; 2],
+    fb_lp_coef: f32,
+}
+impl SimpleDelay {
+    pub fn new(sr: f32, time: f32, fb: f32) -> Self {
+        let length = ((sr * 5.0) as usize).max(1);
+        let mut d = Self {
+            sample_rate: sr,
+            buffers: [vec![0.0; length], vec![0.0; length]],
+            write_pos: 0,
+            length,
+            time_seconds: time.clamp(0.0, 5.0),
+            sync: false,
+            division: SyncDivision::default(),
+            tempo: DEFAULT_BPM,
+            feedback: fb.clamp(0.0, 0.99),
+            wet: 0.35,
+            mode: DelayMode::Stereo,
+            fb_lp_state: [0.0; 2],
+            fb_lp_coef: 0.0,
+        };
+        d.set_feedback_tone(6000.0);
+        d
+    }
+    pub fn set_time(&mut self, t: f32) {
+        self.time_seconds = t.clamp(0.0, 5.0);
+    }
+    /// The free (unsynced) delay time in seconds.
+    pub fn time(&self) -> f32 {
+        self.time_seconds
+    }
+    pub fn set_tempo(&mut self, bpm: f32) {
+        self.tempo = bpm;
+    }
+    /// Delay time in use, synced or not.
+    pub fn effective_time(&self) -> f32 {
+        if self.sync { self.division.seconds(self.tempo) } else { self.time_seconds }
+    }
+    pub fn set_feedback_tone(&mut self, hz: f32) {
+        let hz = hz.clamp(500.0, 12000.0);
+        let x = (-2.0 * std::f32::consts::PI * hz / self.sample_rate).exp();
+        self.fb_lp_coef = 1.0 - x;
+    }
+    #[inline]
+    fn read_frac(&self, ch: usize, delay_samples: f32) -> f32 {
+        let mut rp = self.write_pos as i32 - delay_samples as i32;
+        while rp < 0 { rp += self.length as i32; }
+        let rp0 = rp as usize % self.length;
+        let rp1 = (rp0 + 1) % self.length;
+        let frac = delay_samples.fract();
+        let a = self.buffers[ch][rp0];
+        let b = self.buffers[ch][rp1];
+        a + (b - a) * frac
+    }
+    pub fn process_stereo(&mut self, l: f32, r: f32) -> (f32, f32) {
+        let d_samp = (self.effective_time() * self.sample_rate).clamp(0.0, (self.length - 2) as f32);
+
+        // read delayed signal
+        let delayed = [self.read_frac(0, d_samp), self.read_frac(1, d_samp)];
+
+        // feedback path damping (darkens repeats)
+        for (ch, &d) in delayed.iter().enumerate() {
+            self.fb_lp_state[ch] += self.fb_lp_coef * (d - self.fb_lp_state[ch]);
+        }
+        let fb = [self.fb_lp_state[0] * self.feedback, self.fb_lp_state[1] * self.feedback];
+
+        // write input + feedback
+        let w = self.write_pos;
+        let (wet_l, wet_r) = match self.mode {
+            DelayMode::Mono => {
+                self.buffers[0][w] = 0.5 * (l + r) + fb[0];
+                (delayed[0], delayed[0])
+            }
+            DelayMode::Stereo => {
+                self.buffers[0][w] = l + fb[0];
+                self.buffers[1][w] = r + fb[1];
+                (delayed[0], delayed[1])
+            }
+            DelayMode::PingPong => {
+                // left line takes the input and the right line's repeats, right takes left's
+                self.buffers[0][w] = 0.5 * (l + r) + fb[1];
+                self.buffers[1][w] = fb[0];
+                (delayed[0], delayed[1])
+            }
+        };
+        self.write_pos = (self.write_pos + 1) % self.length;
+
+        // wet/dry
+        (
+            l * (1.0 - self.wet) + wet_l * self.wet,
+            r * (1.0 - self.wet) + wet_r * self.wet,
+        )
+    }
+}
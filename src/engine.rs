@@ -0,0 +1,1039 @@
+// src/engine.rs
+// Host-independent synth engine. Everything here is plain Rust so it can be
+// rendered and tested natively; `Synthesizer` in lib.rs only forwards to it.
+use crate::alloc::{pick_victim, StealPolicy, VOICE_POOL};
+use crate::delay::{DelayMode, SimpleDelay};
+use crate::envelope::ADSRParams;
+use crate::events::{EventKind, EventQueue};
+use crate::filter::{FilterModel, FilterSettings, FilterType, VoiceFilter};
+use crate::lfo::{CurvePoint, LfoCurve, LFO};
+use crate::midi::{
+    MidiMessage, MidiParser, CC_ALL_NOTES_OFF, CC_ALL_SOUND_OFF, CC_DATA_ENTRY, CC_MOD_WHEEL, CC_RPN_LSB, CC_RPN_MSB,
+    CC_SOSTENUTO, CC_SUSTAIN, RPN_BEND_RANGE, RPN_MPE_CONFIG, RPN_NULL,
+};
+use crate::modulation::{ModDest, ModError, ModMatrix, ModSlot, ModSource, SourceValues, MACROS};
+use crate::mono::{MonoSettings, NoteStack, VoiceMode};
+use crate::mpe::{Expression, MpeConfig, MpeZone, CC_TIMBRE};
+use crate::params;
+use crate::patch::Patch;
+use crate::preset::{Preset, PresetError};
+use crate::reverb::SimpleReverb;
+use crate::rng::{rand_phase, Rng};
+use crate::smooth::{Smoothed, Smoother};
+use crate::tempo::{SyncDivision, Transport};
+use crate::unison::{UnisonLayout, MAX_UNISON};
+use crate::tuning::{Keymap, Scale, Tuning, TuningError};
+use crate::util::{midi_to_freq, soft_clip};
+use crate::voice::{CrossMod, OscSettings, RenderCtx, Voice, Waveform};
+use crate::wav::{parse_wavetable, WavError};
+use crate::wavetable::{resample_cycle, Wavetable, MAX_WT_FRAMES};
+use crate::MAX_VOICES;
+
+pub struct Engine {
+    pub(crate) sample_rate: f32,
+    pub(crate) osc_settings: [OscSettings; 2],
+    pub(crate) cross_mod: CrossMod,
+    pub(crate) wavetables: [Wavetable; 2],
+    /// Never grows past `VOICE_POOL` and new voices take their filter from
+    /// `spare_filters`, so note-on doesn't allocate (see tests/voices.rs).
+    pub(crate) voices: Vec<Voice>,
+    /// Filters of the free pool slots; a new voice takes one (comb buffers and
+    /// all) and gives it back when it finishes.
+    spare_filters: Vec<VoiceFilter>,
+    pub(crate) polyphony: usize,
+    pub(crate) steal_policy: StealPolicy,
+    /// Re-striking a sounding note steals its voice instead of stacking another.
+    pub(crate) retrigger_same_note: bool,
+    next_serial: u64,
+    pub(crate) mono: MonoSettings,
+    /// Keys down in every mode, for mono/legato note priority.
+    held: NoteStack,
+    pub(crate) env_defaults: ADSRParams,
+    /// Mod envelope 2; mod envelope 1 is `filter.env`.
+    pub(crate) env2: ADSRParams,
+    pub(crate) filter: FilterSettings,
+    pub(crate) lfos: [LFO; 2],
+    pub(crate) mod_matrix: ModMatrix,
+    pub(crate) mod_wheel: f32,
+    pub(crate) aftertouch: f32,
+    /// Last pitch bend, -1..1.
+    pub(crate) pitch_bend: f32,
+    /// Semitones at full bend (per patch).
+    pub(crate) bend_range: f32,
+    pub(crate) sustain: bool,
+    pub(crate) sostenuto: bool,
+    pub(crate) program: u8,
+    pub(crate) mpe: MpeConfig,
+    /// Latest expression per channel; a note starting on a member channel picks it up.
+    channel_expr: [Expression; 16],
+    /// Selected RPN per channel, `(msb << 7) | lsb`.
+    rpn: [u16; 16],
+    /// Key -> Hz for every note; 12-TET at A4 = 440 until changed.
+    pub(crate) tuning: Tuning,
+    midi: MidiParser,
+    events: EventQueue,
+    pub(crate) macros: [f32; MACROS],
+    pub(crate) delay: SimpleDelay,
+    pub(crate) reverb: SimpleReverb,
+    pub(crate) master_gain: f32,
+    pub(crate) voice_pan_spread: f32,
+    pub(crate) transport: Transport,
+    /// Ramps the continuous controls above towards their (field) values.
+    smooth: Smoother,
+    /// Unison tables of both oscillators; rebuilt when an osc setting changes or a pan ramps.
+    unison: [UnisonLayout; 2],
+    unison_stale: bool,
+    rng: Rng,
+}
+
+impl Engine {
+    pub fn new(sample_rate: f32) -> Engine {
+        Self::with_seed(sample_rate, 0)
+    }
+
+    pub fn with_seed(sample_rate: f32, seed: u64) -> Engine {
+        let patch = Patch::default();
+        let mut delay = SimpleDelay::new(sample_rate, patch.delay_time, patch.delay_feedback);
+        (delay.wet, delay.mode, delay.sync, delay.division) = (patch.delay_wet, patch.delay_mode, patch.delay_sync, patch.delay_division);
+        let mut reverb = SimpleReverb::new(sample_rate);
+        (reverb.wet, reverb.width) = (patch.reverb_wet, patch.reverb_width);
+        let mut engine = Engine {
+            sample_rate,
+            osc_settings: patch.osc,
+            cross_mod: patch.cross_mod,
+            wavetables: [Wavetable::sine(), Wavetable::sine()],
+            voices: Vec::with_capacity(VOICE_POOL),
+            spare_filters: (0..VOICE_POOL).map(|_| VoiceFilter::new(&FilterSettings::default(), sample_rate)).collect(),
+            polyphony: patch.polyphony,
+            steal_policy: patch.steal_policy,
+            retrigger_same_note: patch.retrigger_same_note,
+            next_serial: 0,
+            mono: patch.mono,
+            held: NoteStack::default(),
+            env_defaults: patch.env,
+            env2: patch.env2,
+            filter: patch.filter,
+            lfos: patch.lfos,
+            mod_matrix: patch.mod_matrix,
+            mod_wheel: patch.mod_wheel,
+            aftertouch: patch.aftertouch,
+            pitch_bend: patch.pitch_bend,
+            bend_range: patch.bend_range,
+            sustain: patch.sustain,
+            sostenuto: patch.sostenuto,
+            program: 0,
+            mpe: patch.mpe,
+            channel_expr: [Expression::default(); 16],
+            rpn: [RPN_NULL; 16],
+            tuning: Tuning::default(),
+            midi: MidiParser::new(),
+            events: EventQueue::default(),
+            macros: patch.macros,
+            delay,
+            reverb,
+            master_gain: patch.master_gain,
+            voice_pan_spread: patch.voice_pan_spread,
+            transport: Transport::default(),
+            smooth: Smoother::new(sample_rate, [0.0; Smoothed::COUNT]),
+            unison: patch.osc.map(|os| UnisonLayout::new(&os)),
+            unison_stale: true,
+            rng: Rng::new(seed),
+        };
+        engine.smooth.jump(engine.smoothed_targets());
+        engine.set_seed(seed);
+        // build the registry now, so lookups on the audio thread never do
+        params::all();
+        engine
+    }
+
+    /// Reseed the internal RNG (noise, random start phases) for reproducible renders.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng.reseed(seed);
+        for (i, l) in self.lfos.iter_mut().enumerate() {
+            l.reseed(seed ^ (0xA5A5 + i as u64));
+        }
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    // ---------- tempo / transport ----------
+    pub fn set_tempo(&mut self, bpm: f32) {
+        let bpm = bpm.clamp(20.0, 999.0);
+        self.transport.bpm = bpm;
+        for l in &mut self.lfos {
+            l.tempo = bpm;
+        }
+        self.delay.set_tempo(bpm);
+    }
+
+    pub fn tempo(&self) -> f32 {
+        self.transport.bpm
+    }
+
+    /// Start/stop the song clock and jump it to `position` (in beats). Synced global
+    /// LFOs follow the position while playing and free-run at tempo when stopped.
+    pub fn set_transport(&mut self, playing: bool, position: f64) {
+        self.transport.playing = playing;
+        self.transport.position = position.max(0.0);
+    }
+
+    /// Song position in beats.
+    pub fn song_position(&self) -> f64 {
+        self.transport.position
+    }
+
+    // ---------- tuning ----------
+    /// Retune from Scala text: a .scl scale and optionally a .kbm key map
+    /// (`None` puts degree 0 on middle C with A4 = 440 Hz). Nothing changes on error.
+    pub fn load_scala(&mut self, scl: &str, kbm: Option<&str>) -> Result<(), TuningError> {
+        let scale = Scale::parse(scl)?;
+        let keymap = kbm.map(Keymap::parse).transpose()?.unwrap_or_default();
+        self.tuning = Tuning::from_scala(&scale, &keymap);
+        Ok(())
+    }
+
+    /// Move the reference key (A4 by default) to `hz`, 200..1000, keeping the tuning's shape.
+    pub fn set_reference_pitch(&mut self, hz: f32) {
+        self.tuning.set_reference_pitch(hz);
+    }
+
+    /// Back to 12-TET at A4 = 440 Hz.
+    pub fn reset_tuning(&mut self) {
+        self.tuning = Tuning::default();
+    }
+
+    /// Replace the whole key -> Hz table (e.g. from a preset); `reference` is the
+    /// reference key's pitch it was made with. Extra entries are ignored.
+    pub fn set_tuning_table(&mut self, table: &[f32], reference: f32) {
+        self.tuning.set_table(table, reference);
+    }
+
+    pub fn tuning(&self) -> &Tuning {
+        &self.tuning
+    }
+
+    // ---------- notes ----------
+    pub fn note_on(&mut self, midi_note: u8, velocity: f32) {
+        self.note_on_channel(0, midi_note, velocity);
+    }
+
+    /// Start a note from MIDI `channel`; on an MPE member channel the voice takes
+    /// that channel's current expression.
+    pub fn note_on_channel(&mut self, channel: u8, midi_note: u8, velocity: f32) {
+        // keys the tuning leaves unmapped don't sound
+        if self.tuning.freq(midi_note) <= 0.0 {
+            return;
+        }
+        let overlapping = !self.held.is_empty();
+        self.held.push(midi_note);
+        if self.mono.mode != VoiceMode::Poly {
+            if let Some(note) = self.held.pick(self.mono.priority) {
+                self.mono_play(channel, note, velocity, overlapping);
+            }
+            return;
+        }
+        self.make_room(channel, midi_note);
+        self.start_voice(channel, midi_note, velocity);
+    }
+
+    fn start_voice(&mut self, channel: u8, midi_note: u8, velocity: f32) -> &mut Voice {
+        if self.voices.is_empty() {
+            // nothing sounding: the first note starts at the current settings, no ramp
+            let targets = self.smoothed_targets();
+            for p in Smoothed::ALL.into_iter().filter(|p| p.per_voice()) {
+                self.smooth.settle(p, targets[p as usize]);
+            }
+            self.unison_stale = true;
+        }
+        let freq = self.tuning.freq(midi_note);
+        let voice_rng = Rng::new(self.rng.next_u64());
+        let mut filter = self.spare_filters.pop().unwrap_or_else(|| VoiceFilter::new(&self.filter, self.sample_rate));
+        filter.restart(&self.filter);
+        let mut v = Voice::new(midi_note, freq, velocity, [&self.env_defaults, &self.filter.env, &self.env2], filter, voice_rng);
+        v.channel = channel;
+        v.serial = self.next_serial;
+        self.next_serial += 1;
+        if self.mpe.is_member(channel) {
+            v.expr = self.channel_expr[channel as usize];
+        }
+        for (phases, os) in v.phases.iter_mut().zip(&self.osc_settings) {
+            for ph in phases.iter_mut().take(MAX_UNISON) {
+                *ph = rand_phase(&mut self.rng) * os.unison.phase_rand;
+            }
+        }
+        v.random = self.rng.next_f32();
+        if self.voice_pan_spread > 0.0 {
+            v.pan = self.rng.range(-self.voice_pan_spread, self.voice_pan_spread);
+        }
+        for (st, lfo) in v.lfos.iter_mut().zip(&mut self.lfos) {
+            *st = lfo.voice_state(self.rng.next_u64());
+            if (lfo.retrigger || lfo.one_shot) && !lfo.per_voice {
+                lfo.retrigger();
+            }
+        }
+        self.voices.push(v);
+        self.voices.last_mut().unwrap()
+    }
+
+    /// Make `midi_note` the one mono/legato note, gliding from whatever was sounding.
+    /// Legato keeps a held voice going and only moves its pitch.
+    fn mono_play(&mut self, channel: u8, midi_note: u8, velocity: f32, overlapping: bool) {
+        let current = self.voices.iter().rposition(|v| !v.is_stolen());
+        if let Some(v) = current.map(|i| &self.voices[i]) {
+            if v.midi_note == midi_note && v.key_down {
+                return;
+            }
+        }
+        let freq = self.tuning.freq(midi_note);
+        let glide = current.and_then(|i| self.mono.glide(12.0 * (self.voices[i].current_freq() / freq).log2(), overlapping));
+        match current {
+            Some(i) if self.mono.mode == VoiceMode::Legato && self.voices[i].key_down => {
+                let v = &mut self.voices[i];
+                v.set_note(midi_note, freq);
+                v.glide = glide;
+            }
+            _ => {
+                for v in &mut self.voices {
+                    v.steal();
+                }
+                self.make_room(channel, midi_note);
+                self.start_voice(channel, midi_note, velocity).glide = glide;
+            }
+        }
+    }
+
+    /// After a voice mode or priority change the keys stay held; mono/legato
+    /// only re-picks which of them sounds.
+    fn repick_mono(&mut self) {
+        if self.mono.mode == VoiceMode::Poly {
+            return;
+        }
+        let Some(note) = self.held.pick(self.mono.priority) else { return };
+        match self.voices.iter().rposition(|v| !v.is_stolen() && v.key_down && v.midi_note == note) {
+            Some(keep) => {
+                for (i, v) in self.voices.iter_mut().enumerate() {
+                    if i != keep {
+                        v.steal();
+                    }
+                }
+            }
+            None => {
+                let (channel, vel) = self.voices.iter().rev().find(|v| !v.is_stolen()).map_or((0, 1.0), |v| (v.channel, v.vel));
+                for v in &mut self.voices {
+                    v.steal();
+                }
+                self.mono_play(channel, note, vel, false);
+            }
+        }
+    }
+
+    /// Mono/legato key release: fall back to the next held note, or release.
+    fn mono_note_off(&mut self, channel: u8, midi_note: u8) {
+        let sounding = self.held.pick(self.mono.priority);
+        if !self.held.remove(midi_note) {
+            return;
+        }
+        match self.held.pick(self.mono.priority) {
+            Some(next) if Some(next) != sounding => {
+                let vel = self.voices.iter().rev().find(|v| !v.is_stolen()).map_or(1.0, |v| v.vel);
+                self.mono_play(channel, next, vel, true);
+            }
+            Some(_) => {}
+            None => self.release_where(|_| true),
+        }
+    }
+
+    /// Steal (with a declick fade) whatever must go for a new note: the same note
+    /// when retriggering, then a victim once polyphony is used up. Stolen voices
+    /// still fading are cut outright only if the pool itself is full.
+    fn make_room(&mut self, channel: u8, midi_note: u8) {
+        if self.retrigger_same_note {
+            for v in &mut self.voices {
+                if v.midi_note == midi_note && v.channel == channel {
+                    v.steal();
+                }
+            }
+        }
+        if self.active_voices() >= self.polyphony {
+            if let Some(i) = pick_victim(&self.voices, self.steal_policy) {
+                self.voices[i].steal();
+            }
+        }
+        if self.voices.len() >= VOICE_POOL {
+            if let Some(i) = self.voices.iter().position(Voice::is_stolen) {
+                let v = self.voices.swap_remove(i);
+                self.spare_filters.push(v.into_filter());
+            }
+        }
+    }
+
+    /// Release the key; its voices keep sounding while a pedal holds them.
+    pub fn note_off(&mut self, midi_note: u8) {
+        if self.mono.mode != VoiceMode::Poly {
+            self.mono_note_off(0, midi_note);
+            return;
+        }
+        self.held.remove(midi_note);
+        self.release_where(|v| v.midi_note == midi_note);
+    }
+
+    /// As `note_off`, but with an MPE zone on only `channel`'s note is released: the
+    /// same key held on the master and a member channel is two notes.
+    pub fn note_off_channel(&mut self, channel: u8, midi_note: u8) {
+        if self.mono.mode != VoiceMode::Poly {
+            self.mono_note_off(channel, midi_note);
+        } else if self.mpe.zone != MpeZone::Off {
+            self.held.remove(midi_note);
+            self.release_where(|v| v.midi_note == midi_note && v.channel == channel);
+        } else {
+            self.note_off(midi_note);
+        }
+    }
+
+    fn release_where(&mut self, matches: impl Fn(&Voice) -> bool) {
+        for v in &mut self.voices {
+            if matches(v) && v.key_down {
+                v.key_down = false;
+                if !self.sustain && !v.sostenuto {
+                    v.note_off();
+                }
+            }
+        }
+    }
+
+    /// Release every voice, pedals or not.
+    pub fn all_notes_off(&mut self) {
+        self.held.clear();
+        for v in &mut self.voices {
+            v.key_down = false;
+            v.sostenuto = false;
+            v.note_off();
+        }
+    }
+
+    // ---------- controllers ----------
+    pub fn set_sustain(&mut self, down: bool) {
+        self.sustain = down;
+        if !down {
+            for v in &mut self.voices {
+                if !v.key_down && !v.sostenuto {
+                    v.note_off();
+                }
+            }
+        }
+    }
+
+    /// Pressing latches the keys held right now; only those are sustained until release.
+    pub fn set_sostenuto(&mut self, down: bool) {
+        if down == self.sostenuto {
+            return;
+        }
+        self.sostenuto = down;
+        for v in &mut self.voices {
+            if down {
+                v.sostenuto = v.key_down;
+            } else if v.sostenuto {
+                v.sostenuto = false;
+                if !v.key_down && !self.sustain {
+                    v.note_off();
+                }
+            }
+        }
+    }
+
+    /// Poly aftertouch for the held voices of `midi_note`, 0..1.
+    pub fn set_key_pressure(&mut self, midi_note: u8, pressure: f32) {
+        for v in &mut self.voices {
+            if v.midi_note == midi_note && v.key_down {
+                v.expr.pressure = pressure.clamp(0.0, 1.0);
+            }
+        }
+    }
+
+    // ---------- MIDI ----------
+    /// Parse raw MIDI bytes (any number of messages, running status allowed, and a
+    /// message may continue in the next call) and act on them. All channels are heard.
+    pub fn process_midi(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if let Some(msg) = self.midi.push(byte) {
+                self.handle_midi(msg);
+            }
+            if let Some(sysex) = self.midi.take_sysex() {
+                self.tuning.apply_mts(sysex);
+            }
+        }
+    }
+
+    pub fn handle_midi(&mut self, msg: MidiMessage) {
+        match msg {
+            MidiMessage::NoteOn { channel, note, velocity } => self.note_on_channel(channel, note, velocity as f32 / 127.0),
+            MidiMessage::NoteOff { channel, note, .. } => self.note_off_channel(channel, note),
+            MidiMessage::ControlChange { channel, controller: c @ (CC_RPN_MSB | CC_RPN_LSB | CC_DATA_ENTRY), value } => {
+                self.handle_rpn(channel, c, value)
+            }
+
+            // MPE member channels: per-note expression. Pedals there hold the whole zone
+            // (as on the master channel); all-notes/sound-off only reach that channel's notes.
+            MidiMessage::PitchBend { channel, value } if self.mpe.is_member(channel) => {
+                self.set_channel_expression(channel, |e| e.bend = value as f32 / 8192.0)
+            }
+            MidiMessage::ChannelPressure { channel, pressure } if self.mpe.is_member(channel) => {
+                self.set_channel_expression(channel, |e| e.pressure = pressure as f32 / 127.0)
+            }
+            MidiMessage::ControlChange { channel, controller: CC_TIMBRE, value } if self.mpe.is_member(channel) => {
+                self.set_channel_expression(channel, |e| e.timbre = value as f32 / 127.0)
+            }
+            MidiMessage::ControlChange { channel, controller: c @ (CC_ALL_SOUND_OFF | CC_ALL_NOTES_OFF), .. }
+                if self.mpe.is_member(channel) =>
+            {
+                self.channel_notes_off(channel, c == CC_ALL_SOUND_OFF)
+            }
+            MidiMessage::ControlChange { channel, controller, .. }
+                if self.mpe.is_member(channel) && !matches!(controller, CC_SUSTAIN | CC_SOSTENUTO) => {}
+
+            MidiMessage::ControlChange { controller, value, .. } => match controller {
+                CC_MOD_WHEEL => self.mod_wheel = value as f32 / 127.0,
+                CC_SUSTAIN => self.set_sustain(value >= 64),
+                CC_SOSTENUTO => self.set_sostenuto(value >= 64),
+                CC_ALL_SOUND_OFF => {
+                    self.held.clear();
+                    self.spare_filters.extend(self.voices.drain(..).map(Voice::into_filter));
+                }
+                CC_ALL_NOTES_OFF => self.all_notes_off(),
+                _ => {}
+            },
+            MidiMessage::ChannelPressure { pressure, .. } => self.aftertouch = pressure as f32 / 127.0,
+            MidiMessage::PitchBend { value, .. } => self.pitch_bend = value as f32 / 8192.0,
+            MidiMessage::ProgramChange { program, .. } => self.program = program,
+            MidiMessage::PolyPressure { note, pressure, .. } => self.set_key_pressure(note, pressure as f32 / 127.0),
+        }
+    }
+
+    /// Release `channel`'s voices regardless of pedals, or with `cut` drop them at once.
+    fn channel_notes_off(&mut self, channel: u8, cut: bool) {
+        let mut i = 0;
+        while i < self.voices.len() {
+            let v = &mut self.voices[i];
+            if v.channel != channel {
+                i += 1;
+                continue;
+            }
+            self.held.remove(v.midi_note);
+            if cut {
+                self.spare_filters.push(self.voices.remove(i).into_filter());
+            } else {
+                v.key_down = false;
+                v.sostenuto = false;
+                v.note_off();
+                i += 1;
+            }
+        }
+    }
+
+    /// Update `channel`'s expression and the held notes playing on it.
+    fn set_channel_expression(&mut self, channel: u8, f: impl Fn(&mut Expression)) {
+        f(&mut self.channel_expr[channel as usize]);
+        for v in &mut self.voices {
+            if v.channel == channel && v.key_down {
+                f(&mut v.expr);
+            }
+        }
+    }
+
+    /// RPN 0 (bend range) on a member channel sets the per-note range, elsewhere the
+    /// patch range; RPN 6 on channel 1 or 16 is the MPE configuration message.
+    fn handle_rpn(&mut self, channel: u8, controller: u8, value: u8) {
+        let rpn = &mut self.rpn[channel as usize];
+        match controller {
+            CC_RPN_MSB => *rpn = (*rpn & 0x7F) | (value as u16) << 7,
+            CC_RPN_LSB => *rpn = (*rpn & !0x7F) | value as u16,
+            _ => match *rpn {
+                RPN_BEND_RANGE if self.mpe.is_member(channel) => self.mpe.set_by_name("bend_range", value as f32),
+                RPN_BEND_RANGE => self.bend_range = (value as f32).min(48.0),
+                RPN_MPE_CONFIG if channel == 0 || channel == 15 => {
+                    self.mpe.zone = match (value, channel) {
+                        (0, _) => MpeZone::Off,
+                        (_, 0) => MpeZone::Lower,
+                        _ => MpeZone::Upper,
+                    };
+                    self.mpe.members = value.clamp(1, 15);
+                    self.mpe.bend_range = 48.0;
+                    self.bend_range = 2.0;
+                    self.channel_expr = [Expression::default(); 16];
+                }
+                _ => {}
+            },
+        }
+    }
+
+    pub fn pitch_bend(&self) -> f32 {
+        self.pitch_bend
+    }
+
+    /// Last program change received; the host decides what to load for it.
+    pub fn program(&self) -> u8 {
+        self.program
+    }
+
+    // ---------- scheduled events ----------
+    /// Queue `kind` to happen `frame` samples into the next rendered block (or a
+    /// later one, if the block is shorter).
+    pub fn schedule(&mut self, frame: usize, kind: EventKind) {
+        self.events.push(frame, kind);
+    }
+
+    pub fn clear_scheduled(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn scheduled_count(&self) -> usize {
+        self.events.len()
+    }
+
+    fn apply_event(&mut self, kind: EventKind) {
+        match kind {
+            EventKind::NoteOn { note, velocity } => self.note_on(note, velocity),
+            EventKind::NoteOff { note } => self.note_off(note),
+            EventKind::Param { id, value } => {
+                if let Some(p) = params::by_id(id) {
+                    self.set_parameter(&p.name, value);
+                }
+            }
+            EventKind::Midi { bytes, len } => self.process_midi(&bytes[..len as usize]),
+        }
+    }
+
+    #[inline]
+    fn apply_due_events(&mut self, frame: usize) {
+        while let Some(kind) = self.events.pop_due(frame) {
+            self.apply_event(kind);
+        }
+    }
+
+    pub fn mpe(&self) -> &MpeConfig {
+        &self.mpe
+    }
+
+    // ---------- params ----------
+    /// Set a registered parameter (see `params::all`), clamped to its range.
+    /// False, changing nothing, if `name` isn't one.
+    pub fn set_parameter(&mut self, name: &str, value: f32) -> bool {
+        let Some(info) = params::find(name) else { return false };
+        let value = info.clamp(value);
+        match name {
+            // osc
+            "osc0_waveform" => self.osc_settings[0].waveform = Waveform::from_f32(value),
+            "osc1_waveform" => self.osc_settings[1].waveform = Waveform::from_f32(value),
+            "osc0_gain" | "osc0_volume" => self.osc_settings[0].gain = value,
+            "osc1_gain" | "osc1_volume" => self.osc_settings[1].gain = value,
+            "osc0_coarse" => self.osc_settings[0].coarse = value.clamp(-48.0, 48.0),
+            "osc1_coarse" => self.osc_settings[1].coarse = value.clamp(-48.0, 48.0),
+            "osc0_detune" => self.osc_settings[0].detune_cents = value,
+            "osc1_detune" => self.osc_settings[1].detune_cents = value,
+            "osc0_wt_position" => self.osc_settings[0].wt_position = value.clamp(0.0, 1.0),
+            "osc1_wt_position" => self.osc_settings[1].wt_position = value.clamp(0.0, 1.0),
+            "osc0_pan" => self.osc_settings[0].pan = value.clamp(-1.0, 1.0),
+            "osc1_pan" => self.osc_settings[1].pan = value.clamp(-1.0, 1.0),
+            name if name.starts_with("osc0_unison") => self.osc_settings[0].unison.set_by_name(&name[5..], value),
+            name if name.starts_with("osc1_unison") => self.osc_settings[1].unison.set_by_name(&name[5..], value),
+            "polyphony"        => self.polyphony = (value.round() as usize).clamp(1, MAX_VOICES),
+            "voice_steal"      => self.steal_policy = StealPolicy::from_f32(value),
+            "voice_retrigger"  => self.retrigger_same_note = value > 0.5,
+            "voice_mode" | "note_priority" => {
+                self.mono.set_by_name(name, value);
+                self.repick_mono();
+            }
+            name if name.starts_with("glide_") => self.mono.set_by_name(name, value),
+            "voice_pan_spread" => self.voice_pan_spread = value.clamp(0.0, 1.0),
+            "osc0_sync" | "osc1_sync" | "osc1_fm" | "osc1_pm" | "osc1_am" | "osc1_ring" => {
+                self.cross_mod.set_by_name(name, value)
+            }
+
+            // envelopes: amp (env_*), mod 1 (filter_env_* / env1_*), mod 2 (env2_*)
+            "filter_env_amount" | "env1_amount" => self.filter.env_amount = value.clamp(-8.0, 8.0),
+            name if name.starts_with("env_")  => self.env_defaults.set_by_name(&name[4..], value),
+            name if name.starts_with("env1_") => self.filter.env.set_by_name(&name[5..], value),
+            name if name.starts_with("env2_") => self.env2.set_by_name(&name[5..], value),
+
+            // filter
+            "filter_model"     => self.filter.model = FilterModel::from_f32(value),
+            "filter_drive"     => self.filter.drive = value.clamp(0.0, 1.0),
+            "filter_type"      => self.filter.filter_type = FilterType::from_f32(value),
+            "filter_morph"     => self.filter.morph = value.clamp(0.0, 1.0),
+            // kept as set, so patches read back the same at any rate; voices stop short of Nyquist
+            "filter_cutoff"    => self.filter.cutoff = value,
+            "filter_resonance" => self.filter.resonance = value.max(0.0),
+            "filter_keytrack"  => self.filter.key_track = value.clamp(0.0, 1.0),
+            "filter_env"       => self.filter.env_enabled = value > 0.5,
+            name if name.starts_with("filter_env_") => self.filter.env.set_by_name(&name[11..], value),
+
+            // LFOs
+            name if name.starts_with("lfo0_") => self.lfos[0].set_by_name(&name[5..], value),
+            name if name.starts_with("lfo1_") => self.lfos[1].set_by_name(&name[5..], value),
+
+            // FX
+            "fx_delay_time"     => self.delay.set_time(value.max(0.0)),
+            "fx_delay_feedback" => self.delay.feedback = value.clamp(0.0, 0.99),
+            "fx_delay_wet"      => self.delay.wet = value.clamp(0.0, 1.0),
+            "fx_delay_mode"     => self.delay.mode = DelayMode::from_f32(value),
+            "fx_delay_sync"     => self.delay.sync = value > 0.5,
+            "fx_delay_division" => self.delay.division = SyncDivision::from_f32(value),
+            "fx_reverb_wet"     => self.reverb.wet = value.clamp(0.0, 1.0),
+            "fx_reverb_width"   => self.reverb.width = value.clamp(0.0, 1.0),
+
+            // master
+            "master_gain" => self.master_gain = value,
+            "tempo"       => self.set_tempo(value),
+
+            // mod matrix sources and routes
+            "mod_wheel"  => self.mod_wheel = value.clamp(0.0, 1.0),
+            "aftertouch" => self.aftertouch = value.clamp(0.0, 1.0),
+            "pitch_bend" => self.pitch_bend = value.clamp(-1.0, 1.0),
+            "pitch_bend_range" => self.bend_range = value.clamp(0.0, 48.0),
+            "reference_pitch" => self.set_reference_pitch(value),
+            "sustain_pedal"    => self.set_sustain(value > 0.5),
+            "sostenuto_pedal"  => self.set_sostenuto(value > 0.5),
+            name if name.starts_with("mpe_") => self.mpe.set_by_name(&name[4..], value),
+            "macro0" => self.macros[0] = value.clamp(0.0, 1.0),
+            "macro1" => self.macros[1] = value.clamp(0.0, 1.0),
+            "macro2" => self.macros[2] = value.clamp(0.0, 1.0),
+            "macro3" => self.macros[3] = value.clamp(0.0, 1.0),
+            name if name.starts_with("mod_") => self.mod_matrix.set_by_name(name, value),
+
+            _ => {}
+        }
+        if info.name.starts_with("osc") {
+            self.unison_stale = true;
+        }
+        true
+    }
+
+    /// Set a parameter from its 0..1 knob position, following its taper.
+    pub fn set_parameter_normalized(&mut self, name: &str, normalized: f32) -> bool {
+        match params::find(name) {
+            Some(info) => self.set_parameter(name, info.denormalize(normalized)),
+            None => false,
+        }
+    }
+
+    /// Current value of a registered parameter; `None` for unknown names.
+    pub fn get_parameter(&self, name: &str) -> Option<f32> {
+        self.read_parameter(&params::find(name)?.name)
+    }
+
+    /// `get_parameter` for canonical names only, without the registry.
+    pub(crate) fn read_parameter(&self, name: &str) -> Option<f32> {
+        self.patch().read(name)
+    }
+
+    /// The current patch (and performance state) as plain values.
+    pub(crate) fn patch(&self) -> Patch {
+        Patch {
+            osc: self.osc_settings,
+            cross_mod: self.cross_mod,
+            polyphony: self.polyphony,
+            steal_policy: self.steal_policy,
+            retrigger_same_note: self.retrigger_same_note,
+            voice_pan_spread: self.voice_pan_spread,
+            mono: self.mono,
+            env: self.env_defaults,
+            env2: self.env2,
+            filter: self.filter,
+            lfos: self.lfos,
+            mod_matrix: self.mod_matrix,
+            delay_time: self.delay.time(),
+            delay_feedback: self.delay.feedback,
+            delay_wet: self.delay.wet,
+            delay_mode: self.delay.mode,
+            delay_sync: self.delay.sync,
+            delay_division: self.delay.division,
+            reverb_wet: self.reverb.wet,
+            reverb_width: self.reverb.width,
+            master_gain: self.master_gain,
+            tempo: self.transport.bpm,
+            reference_pitch: self.tuning.reference_pitch(),
+            bend_range: self.bend_range,
+            mpe: self.mpe,
+            mod_wheel: self.mod_wheel,
+            aftertouch: self.aftertouch,
+            pitch_bend: self.pitch_bend,
+            sustain: self.sustain,
+            sostenuto: self.sostenuto,
+            macros: self.macros,
+        }
+    }
+
+    // ---------- smoothing ----------
+    /// Ramp time in seconds for a smoothed parameter (`Smoothed::name`), or for all
+    /// of them with "all"; 0 makes changes immediate. False for other names.
+    pub fn set_smoothing(&mut self, name: &str, seconds: f32) -> bool {
+        if name == "all" {
+            for p in Smoothed::ALL {
+                self.smooth.set_time(p, seconds);
+            }
+            return true;
+        }
+        let Some(p) = Smoothed::from_name(name) else { return false };
+        self.smooth.set_time(p, seconds);
+        true
+    }
+
+    pub fn smoothing(&self, name: &str) -> Option<f32> {
+        Smoothed::from_name(name).map(|p| self.smooth.time(p))
+    }
+
+    /// The patch values the smoother heads for, in `Smoothed` order.
+    fn smoothed_targets(&self) -> [f32; Smoothed::COUNT] {
+        let (osc, filt) = (&self.osc_settings, &self.filter);
+        Smoothed::ALL.map(|p| match p {
+            Smoothed::MasterGain => self.master_gain,
+            Smoothed::Osc0Gain => osc[0].gain,
+            Smoothed::Osc1Gain => osc[1].gain,
+            Smoothed::Osc0Pan => osc[0].pan,
+            Smoothed::Osc1Pan => osc[1].pan,
+            Smoothed::Osc0WtPosition => osc[0].wt_position,
+            Smoothed::Osc1WtPosition => osc[1].wt_position,
+            Smoothed::FilterCutoff => filt.cutoff,
+            Smoothed::FilterResonance => filt.resonance,
+            Smoothed::FilterDrive => filt.drive,
+            Smoothed::FilterMorph => filt.morph,
+            Smoothed::DelayWet => self.delay.wet,
+            Smoothed::DelayFeedback => self.delay.feedback,
+            Smoothed::ReverbWet => self.reverb.wet,
+        })
+    }
+
+    // ---------- LFO curves ----------
+    /// Replace LFO `lfo`'s drawn shape with consecutive `x, y, curve` triples (see
+    /// `LfoCurve::from_flat`). Returns points loaded; 0 leaves the curve unchanged.
+    pub fn set_lfo_points(&mut self, lfo: usize, flat: &[f32]) -> usize {
+        let Some(l) = self.lfos.get_mut(lfo) else { return 0 };
+        match LfoCurve::from_flat(flat) {
+            Some(curve) => {
+                l.curve = curve;
+                curve.points().len()
+            }
+            None => 0,
+        }
+    }
+
+    pub fn lfo_points(&self, lfo: usize) -> Option<&[CurvePoint]> {
+        self.lfos.get(lfo).map(|l| l.curve.points())
+    }
+
+    // ---------- mod matrix ----------
+    pub fn add_mod_slot(&mut self, slot: ModSlot) -> Result<usize, ModError> {
+        self.mod_matrix.add(slot)
+    }
+
+    pub fn remove_mod_slot(&mut self, index: usize) -> bool {
+        self.mod_matrix.remove(index)
+    }
+
+    pub fn clear_mod_slots(&mut self) {
+        self.mod_matrix.clear();
+    }
+
+    pub fn mod_matrix(&self) -> &ModMatrix {
+        &self.mod_matrix
+    }
+
+    // ---------- presets ----------
+    /// The whole patch as current-version preset JSON (see `preset`).
+    pub fn export_preset(&self) -> String {
+        Preset::capture(self).to_json()
+    }
+
+    /// Load preset JSON of any supported version. Nothing changes on error.
+    pub fn import_preset(&mut self, json: &str) -> Result<(), PresetError> {
+        Preset::from_json(json)?.apply(self);
+        Ok(())
+    }
+
+    // ---------- wavetables ----------
+    /// Resample an arbitrary-length single cycle into the oscillator's table
+    /// and rebuild its band-limited mip levels.
+    pub fn set_wavetable(&mut self, osc: usize, src: &[f32]) {
+        if osc >= 2 || src.is_empty() {
+            return;
+        }
+        self.wavetables[osc] = Wavetable::new(resample_cycle(src));
+    }
+
+    /// Load `src` as consecutive frames of `frame_len` samples each (each resampled
+    /// to `WAVETABLE_SIZE`). A trailing partial frame is dropped; returns frames loaded.
+    pub fn set_wavetable_frames(&mut self, osc: usize, src: &[f32], frame_len: usize) -> usize {
+        if osc >= 2 || frame_len == 0 || src.len() < frame_len {
+            return 0;
+        }
+        let frames: Vec<Vec<f32>> = src
+            .chunks_exact(frame_len)
+            .take(MAX_WT_FRAMES)
+            .map(resample_cycle)
+            .collect();
+        let n = frames.len();
+        self.wavetables[osc] = Wavetable::from_frames(frames);
+        n
+    }
+
+    /// Parse a (Serum-style) wavetable .wav and load all of its frames into `osc`.
+    pub fn load_wavetable_wav(&mut self, osc: usize, bytes: &[u8]) -> Result<usize, WavError> {
+        let wav = parse_wavetable(bytes)?;
+        Ok(self.set_wavetable_frames(osc, &wav.samples, wav.frame_size))
+    }
+
+    pub fn wavetable_frame_count(&self, osc: usize) -> usize {
+        self.wavetables.get(osc).map_or(0, |w| w.frame_count())
+    }
+
+    pub fn wavetable(&self, osc: usize) -> Option<&[f32]> {
+        self.wavetables.get(osc).map(|w| w.raw())
+    }
+
+    /// Notes of the playing voices, oldest first.
+    pub fn active_notes(&self) -> Vec<u8> {
+        let mut live: Vec<&Voice> = self.voices.iter().filter(|v| !v.is_stolen()).collect();
+        live.sort_by_key(|v| v.serial);
+        live.into_iter().map(|v| v.midi_note).collect()
+    }
+
+    /// Voices playing, not counting stolen ones still fading out.
+    pub fn active_voices(&self) -> usize {
+        self.voices.iter().filter(|v| !v.is_stolen()).count()
+    }
+
+    // ---------- main render ----------
+    /// Render `out.len()` frames as a mono downmix of the stereo path.
+    pub fn render(&mut self, out: &mut [f32]) {
+        for (i, o) in out.iter_mut().enumerate() {
+            self.apply_due_events(i);
+            let (l, r) = self.tick_frame();
+            *o = 0.5 * (l + r);
+        }
+        self.events.advance(out.len());
+    }
+
+    /// Render planar stereo; renders `min(left.len(), right.len())` frames.
+    pub fn render_stereo(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let mut frames = 0;
+        for (i, (l, r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            self.apply_due_events(i);
+            (*l, *r) = self.tick_frame();
+            frames += 1;
+        }
+        self.events.advance(frames);
+    }
+
+    #[inline]
+    fn tick_frame(&mut self) -> (f32, f32) {
+        let dt = 1.0 / self.sample_rate;
+
+        // this sample's smoothed controls; voices render from these copies
+        let targets = self.smoothed_targets();
+        let panning = [Smoothed::Osc0Pan, Smoothed::Osc1Pan].map(|p| self.smooth.is_ramping(p));
+        let sv = *self.smooth.tick(&targets);
+        let v = |p: Smoothed| sv[p as usize];
+        let mut osc = self.osc_settings;
+        for (i, (gain, pan, wt)) in [
+            (Smoothed::Osc0Gain, Smoothed::Osc0Pan, Smoothed::Osc0WtPosition),
+            (Smoothed::Osc1Gain, Smoothed::Osc1Pan, Smoothed::Osc1WtPosition),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            osc[i].gain = v(gain);
+            osc[i].pan = v(pan);
+            osc[i].wt_position = v(wt);
+        }
+        let mut filt = self.filter;
+        filt.cutoff = v(Smoothed::FilterCutoff);
+        filt.resonance = v(Smoothed::FilterResonance);
+        filt.drive = v(Smoothed::FilterDrive);
+        filt.morph = v(Smoothed::FilterMorph);
+
+        // global sources; they may move the LFO rates, so use last sample's LFO values
+        let mut src: SourceValues = [0.0; ModSource::COUNT];
+        src[ModSource::Lfo0 as usize] = self.lfos[0].value();
+        src[ModSource::Lfo1 as usize] = self.lfos[1].value();
+        src[ModSource::ModWheel as usize] = self.mod_wheel;
+        src[ModSource::Aftertouch as usize] = self.aftertouch;
+        src[ModSource::PitchBend as usize] = self.pitch_bend;
+        for (s, m) in src[ModSource::Macro0 as usize..].iter_mut().zip(self.macros) {
+            *s = m;
+        }
+        let g = self.mod_matrix.evaluate(&src, true);
+
+        // tick LFOs (scaling dt is scaling the rate); synced ones follow a playing song
+        let mut lfo_dt = [dt; 2];
+        for ((l, ldt), dest) in self.lfos.iter_mut().zip(&mut lfo_dt).zip([ModDest::Lfo0Rate, ModDest::Lfo1Rate]) {
+            let oct = g[dest.index()];
+            if oct != 0.0 {
+                *ldt *= 2f32.powf(oct);
+            }
+            if l.sync && self.transport.playing && !l.per_voice && !l.one_shot {
+                l.follow_song(self.transport.position, dt);
+            } else {
+                l.tick(*ldt);
+            }
+        }
+        self.transport.advance(dt);
+        src[ModSource::Lfo0 as usize] = self.lfos[0].value();
+        src[ModSource::Lfo1 as usize] = self.lfos[1].value();
+
+        // the pan is folded into the unison gains, so a ramping pan rebuilds its table too
+        for (i, moving) in panning.into_iter().enumerate() {
+            if self.unison_stale || moving {
+                self.unison[i] = UnisonLayout::new(&osc[i]);
+            }
+        }
+        self.unison_stale = false;
+
+        let ctx = RenderCtx {
+            dt,
+            sr: self.sample_rate,
+            osc: &osc,
+            unison: &self.unison,
+            cross: &self.cross_mod,
+            wts: &self.wavetables,
+            lfos: &self.lfos,
+            lfo_dt,
+            note_bend_range: self.mpe.bend_range,
+            key_center: if self.tuning.freq(60) > 0.0 { self.tuning.freq(60) } else { midi_to_freq(60) },
+            bend: if self.pitch_bend != 0.0 { 2f32.powf(self.pitch_bend * self.bend_range / 12.0) } else { 1.0 },
+            globals: &src,
+            mods: &self.mod_matrix,
+            filt: &filt,
+        };
+
+        // mix voices and retire finished
+        let (mut mix_l, mut mix_r) = (0.0f32, 0.0f32);
+        let mut i = 0;
+        while i < self.voices.len() {
+            let (l, r) = self.voices[i].render(&ctx);
+            mix_l += l;
+            mix_r += r;
+            if self.voices[i].is_finished() {
+                self.spare_filters.push(self.voices.remove(i).into_filter());
+            } else {
+                i += 1;
+            }
+        }
+
+        // modulated FX levels only last for this sample
+        let (delay_wet, delay_feedback, reverb_wet) = (self.delay.wet, self.delay.feedback, self.reverb.wet);
+        self.delay.wet = (v(Smoothed::DelayWet) + g[ModDest::DelayWet.index()]).clamp(0.0, 1.0);
+        self.delay.feedback = v(Smoothed::DelayFeedback);
+        self.reverb.wet = (v(Smoothed::ReverbWet) + g[ModDest::ReverbWet.index()]).clamp(0.0, 1.0);
+        let (dl, dr) = self.delay.process_stereo(mix_l, mix_r);
+        let (rl, rr) = self.reverb.process_stereo(dl, dr);
+        self.delay.wet = delay_wet;
+        self.delay.feedback = delay_feedback;
+        self.reverb.wet = reverb_wet;
+
+        // gentle soft clip for mix glue / perceived loudness
+        let gain = (v(Smoothed::MasterGain) + g[ModDest::MasterGain.index()]).max(0.0);
+        (soft_clip(rl * gain), soft_clip(rr * gain))
+    }
+}
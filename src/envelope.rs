@@ -0,0 +1,175 @@
+// src/envelope.rs
+// DAHDSR envelope with per-segment curvature. Each segment runs linearly in time
+// and its level is bent by `shape`, so curvature never changes segment length.
+
+/// Curvature strength at |curve| = 1 (about -70 dB of "RC" travel).
+const CURVE_K: f32 = 8.0;
+
+#[derive(Clone, Copy)]
+pub struct ADSRParams {
+    pub delay: f32,
+    pub attack: f32,
+    pub hold: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+    /// -1..1 per segment: 0 linear, >0 fast start then easing in (RC-style,
+    /// exponential decays/releases), <0 slow start.
+    pub attack_curve: f32,
+    pub decay_curve: f32,
+    pub release_curve: f32,
+}
+impl Default for ADSRParams {
+    fn default() -> Self {
+        Self {
+            delay: 0.0,
+            attack: 0.01,
+            hold: 0.0,
+            decay: 0.2,
+            sustain: 0.8,
+            release: 0.3,
+            attack_curve: 0.0,
+            decay_curve: 0.0,
+            release_curve: 0.0,
+        }
+    }
+}
+impl ADSRParams {
+    /// `key` is the part after the envelope prefix, e.g. `attack` or `release_curve`.
+    pub fn set_by_name(&mut self, key: &str, value: f32) {
+        match key {
+            "delay"   => self.delay   = value.max(0.0),
+            "attack"  => self.attack  = value.max(0.0001),
+            "hold"    => self.hold    = value.max(0.0),
+            "decay"   => self.decay   = value.max(0.0001),
+            "sustain" => self.sustain = value.clamp(0.0, 1.0),
+            "release" => self.release = value.max(0.0001),
+            "attack_curve"  => self.attack_curve  = value.clamp(-1.0, 1.0),
+            "decay_curve"   => self.decay_curve   = value.clamp(-1.0, 1.0),
+            "release_curve" => self.release_curve = value.clamp(-1.0, 1.0),
+            _ => {}
+        }
+    }
+
+    /// Every setting under its `set_by_name` key, for preset export.
+    pub fn named_values(&self) -> [(&'static str, f32); 9] {
+        [
+            ("delay", self.delay),
+            ("attack", self.attack),
+            ("hold", self.hold),
+            ("decay", self.decay),
+            ("sustain", self.sustain),
+            ("release", self.release),
+            ("attack_curve", self.attack_curve),
+            ("decay_curve", self.decay_curve),
+            ("release_curve", self.release_curve),
+        ]
+    }
+}
+
+/// Bend a linear 0..1 segment position.
+#[inline]
+pub fn shape(x: f32, curve: f32) -> f32 {
+    let k = curve * CURVE_K;
+    if k.abs() < 1e-3 {
+        x
+    } else {
+        (1.0 - (-k * x).exp()) / (1.0 - (-k).exp())
+    }
+}
+
+pub struct PerVoiceADSR {
+    params: ADSRParams,
+    pub state: AdsrState,
+    pub level: f32,
+    /// Linear progress through the current segment, 0..1.
+    pos: f32,
+    /// Level the current segment started from (attack and release can start mid-way).
+    from: f32,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdsrState {
+    Idle,
+    Delay,
+    Attack,
+    Hold,
+    Decay,
+    Sustain,
+    Release,
+}
+impl PerVoiceADSR {
+    pub fn new(d: &ADSRParams) -> Self {
+        Self {
+            params: *d,
+            state: if d.delay > 0.0 { AdsrState::Delay } else { AdsrState::Attack },
+            level: 0.0,
+            pos: 0.0,
+            from: 0.0,
+        }
+    }
+    pub fn note_off(&mut self) {
+        if !matches!(self.state, AdsrState::Idle | AdsrState::Release) {
+            self.enter(AdsrState::Release);
+        }
+    }
+
+    fn enter(&mut self, state: AdsrState) {
+        self.state = state;
+        self.pos = 0.0;
+        self.from = self.level;
+    }
+
+    /// Step the segment clock; true once the segment is complete.
+    #[inline]
+    fn advance(&mut self, dt: f32, len: f32) -> bool {
+        self.pos += dt / len.max(1e-6);
+        if self.pos >= 1.0 {
+            self.pos = 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn tick(&mut self, dt: f32) -> f32 {
+        let p = self.params;
+        match self.state {
+            AdsrState::Idle | AdsrState::Sustain => {}
+            AdsrState::Delay => {
+                if self.advance(dt, p.delay) {
+                    self.enter(AdsrState::Attack);
+                }
+            }
+            AdsrState::Attack => {
+                let done = self.advance(dt, p.attack);
+                self.level = self.from + (1.0 - self.from) * shape(self.pos, p.attack_curve);
+                if done {
+                    self.level = 1.0;
+                    self.enter(if p.hold > 0.0 { AdsrState::Hold } else { AdsrState::Decay });
+                }
+            }
+            AdsrState::Hold => {
+                if self.advance(dt, p.hold) {
+                    self.enter(AdsrState::Decay);
+                }
+            }
+            AdsrState::Decay => {
+                let done = self.advance(dt, p.decay);
+                self.level = 1.0 - (1.0 - p.sustain) * shape(self.pos, p.decay_curve);
+                if done {
+                    self.level = p.sustain;
+                    self.enter(AdsrState::Sustain);
+                }
+            }
+            AdsrState::Release => {
+                let done = self.advance(dt, p.release);
+                self.level = self.from * (1.0 - shape(self.pos, p.release_curve));
+                if done {
+                    self.level = 0.0;
+                    self.enter(AdsrState::Idle);
+                }
+            }
+        }
+        self.level
+    }
+}
@@ -0,0 +1,87 @@
+// src/events.rs
+// Timestamped events for sample-accurate scheduling. Frames count from the start
+// of the next rendered block; whatever lies beyond a block carries over into the
+// next one with its offset reduced. Events are plain values and the queue is
+// allocated up front, so scheduling and applying them don't touch the heap.
+use std::collections::VecDeque;
+
+use crate::params;
+
+/// Pending events the queue holds before pushing has to grow it.
+pub const EVENT_CAPACITY: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+    NoteOn { note: u8, velocity: f32 },
+    NoteOff { note: u8 },
+    /// A registry parameter by id (see `params::by_id`).
+    Param { id: u32, value: f32 },
+    /// Up to three raw bytes for `Engine::process_midi`; the parser keeps its state
+    /// between events, so a longer stream can be queued as consecutive chunks.
+    Midi { bytes: [u8; 3], len: u8 },
+}
+impl EventKind {
+    /// `Param` for a `set_parameter` name; `None` if the registry doesn't know it.
+    pub fn param(name: &str, value: f32) -> Option<Self> {
+        params::find(name).map(|p| Self::Param { id: p.id, value })
+    }
+
+    /// `Midi` for up to three bytes; `None` for an empty or longer slice.
+    pub fn midi(bytes: &[u8]) -> Option<Self> {
+        let mut buf = [0; 3];
+        buf.get_mut(..bytes.len())?.copy_from_slice(bytes);
+        (!bytes.is_empty()).then_some(Self::Midi { bytes: buf, len: bytes.len() as u8 })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScheduledEvent {
+    pub frame: usize,
+    pub kind: EventKind,
+}
+
+/// Kept sorted by frame; events on the same frame run in the order they were added.
+#[derive(Clone, Debug)]
+pub struct EventQueue {
+    events: VecDeque<ScheduledEvent>,
+}
+impl Default for EventQueue {
+    fn default() -> Self {
+        Self { events: VecDeque::with_capacity(EVENT_CAPACITY) }
+    }
+}
+impl EventQueue {
+    pub fn push(&mut self, frame: usize, kind: EventKind) {
+        let i = self.events.partition_point(|e| e.frame <= frame);
+        self.events.insert(i, ScheduledEvent { frame, kind });
+    }
+
+    /// The next event due at or before `frame`, if any.
+    #[inline]
+    pub fn pop_due(&mut self, frame: usize) -> Option<EventKind> {
+        if self.events.front()?.frame <= frame {
+            self.events.pop_front().map(|e| e.kind)
+        } else {
+            None
+        }
+    }
+
+    /// Shift the remaining events after a block of `frames` was rendered.
+    pub fn advance(&mut self, frames: usize) {
+        for e in &mut self.events {
+            e.frame = e.frame.saturating_sub(frames);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
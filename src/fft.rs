@@ -0,0 +1,53 @@
+// src/fft.rs
+// In-place iterative radix-2 complex FFT; only used off the audio path
+// (building band-limited wavetable mips).
+use std::f32::consts::PI;
+
+/// `re.len()` must equal `im.len()` and be a power of two. The inverse is scaled by 1/n.
+pub fn fft(re: &mut [f32], im: &mut [f32], inverse: bool) {
+    let n = re.len();
+    debug_assert!(n.is_power_of_two() && im.len() == n);
+    if n < 2 {
+        return;
+    }
+
+    // bit-reversal permutation
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let ang = sign * 2.0 * PI / len as f32;
+        let half = len / 2;
+        for start in (0..n).step_by(len) {
+            for k in 0..half {
+                // twiddle computed directly: avoids drift from repeated multiplication
+                let (w_im, w_re) = (ang * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + half;
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let s = 1.0 / n as f32;
+        for (r, i) in re.iter_mut().zip(im.iter_mut()) {
+            *r *= s;
+            *i *= s;
+        }
+    }
+}
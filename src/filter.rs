@@ -0,0 +1,415 @@
+// src/filter.rs
+use crate::envelope::ADSRParams;
+
+/// Octaves of cutoff sweep at full filter-envelope amount.
+pub const FILTER_ENV_OCTAVES: f32 = 6.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FilterType {
+    Lowpass,
+    Highpass,
+    Bandpass,
+    Notch,
+    Peak,
+    Allpass,
+    Morph, // LP -> BP -> HP, position set by `morph`
+}
+impl FilterType {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            0 => Self::Lowpass,
+            1 => Self::Highpass,
+            2 => Self::Bandpass,
+            3 => Self::Notch,
+            4 => Self::Peak,
+            5 => Self::Allpass,
+            6 => Self::Morph,
+            _ => Self::Lowpass,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Lowpass => 0,
+            Self::Highpass => 1,
+            Self::Bandpass => 2,
+            Self::Notch => 3,
+            Self::Peak => 4,
+            Self::Allpass => 5,
+            Self::Morph => 6,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FilterModel {
+    Svf,    // 12 dB/oct TPT state-variable, multi-mode
+    Ladder, // 24 dB/oct transistor ladder with drive
+    Diode,  // 24 dB/oct diode ladder (squelchier, acid)
+    Comb,   // feedback comb tuned to the cutoff
+}
+impl FilterModel {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            1 => Self::Ladder,
+            2 => Self::Diode,
+            3 => Self::Comb,
+            _ => Self::Svf,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Svf => 0,
+            Self::Ladder => 1,
+            Self::Diode => 2,
+            Self::Comb => 3,
+        }
+    }
+}
+
+/// Common interface of the per-voice filter models.
+pub trait Filter {
+    /// Set the (already modulated) cutoff in Hz and resonance (0..~1.2).
+    fn set_params(&mut self, cutoff: f32, resonance: f32);
+    fn process_stereo(&mut self, l: f32, r: f32) -> (f32, f32);
+    fn reset(&mut self);
+}
+
+/// Patch-level filter settings; every voice runs its own filter from these.
+#[derive(Clone, Copy)]
+pub struct FilterSettings {
+    pub model: FilterModel,
+    pub drive: f32, // 0..1, input gain into the ladder models
+    pub filter_type: FilterType,
+    pub morph: f32, // 0 = LP, 0.5 = BP, 1 = HP (Morph type only)
+    pub cutoff: f32,
+    pub resonance: f32,
+    pub key_track: f32, // 0..1; 1 = cutoff follows pitch (relative to C4)
+    pub env_enabled: bool,
+    /// Octaves the filter envelope sweeps the cutoff at full level, per note.
+    pub env_amount: f32,
+    pub env: ADSRParams,
+}
+impl Default for FilterSettings {
+    fn default() -> Self {
+        Self {
+            model: FilterModel::Svf,
+            drive: 0.0,
+            filter_type: FilterType::Lowpass,
+            morph: 0.0,
+            cutoff: 1200.0,
+            resonance: 0.6,
+            key_track: 0.0,
+            env_enabled: true,
+            env_amount: 0.0,
+            env: ADSRParams {
+                attack: 0.005,
+                decay: 0.3,
+                sustain: 0.0,
+                release: 0.3,
+                ..ADSRParams::default()
+            },
+        }
+    }
+}
+
+// --- Topology-Preserving Transform SVF (multi-mode out) ----------------
+pub struct StateVarFilter {
+    pub base_cutoff: f32,
+    pub resonance: f32,   // 0..~1.2; musical mapping to Q
+    pub mode: FilterType,
+    pub morph: f32,
+    sample_rate: f32,
+
+    // TPT state (one integrator pair per channel)
+    g: f32,
+    k: f32,
+    ic1eq: [f32; 2],
+    ic2eq: [f32; 2],
+}
+impl StateVarFilter {
+    pub fn new(c: f32, q: f32, sr: f32) -> Self {
+        let mut f = Self {
+            base_cutoff: c,
+            resonance: q,
+            mode: FilterType::Lowpass,
+            morph: 0.0,
+            sample_rate: sr,
+            g: 0.0, k: 0.0, ic1eq: [0.0; 2], ic2eq: [0.0; 2],
+        };
+        f.update_coeffs();
+        f
+    }
+    pub fn set_cutoff(&mut self, c: f32) {
+        self.base_cutoff = c.max(20.0).min(self.sample_rate * 0.49);
+        self.update_coeffs();
+    }
+    #[inline]
+    pub fn update_coeffs(&mut self) {
+        // bilinear transform prewarp with normalized T=1
+        let wc = (std::f32::consts::PI * (self.base_cutoff / self.sample_rate)).tan();
+        self.g = wc;
+
+        // Map resonance (0..1.2) to k = 1/Q; lower "resonance" => higher Q (more peak)
+        let q = 0.5 + (1.5 * (1.0 - self.resonance.clamp(0.0, 1.2))).max(0.05);
+        self.k = 1.0 / q;
+    }
+    #[inline]
+    fn tick(&mut self, ch: usize, x: f32) -> f32 {
+        // TPT 2-pole core: v1 = bandpass, v2 = lowpass
+        let v0 = x;
+        let v1 = (self.ic1eq[ch] + self.g * (v0 - self.ic2eq[ch])) / (1.0 + self.g * (self.g + self.k));
+        let v2 = self.ic2eq[ch] + self.g * v1;
+
+        self.ic1eq[ch] = 2.0 * v1 - self.ic1eq[ch];
+        self.ic2eq[ch] = 2.0 * v2 - self.ic2eq[ch];
+
+        let k = self.k;
+        match self.mode {
+            FilterType::Lowpass => v2,
+            FilterType::Highpass => v0 - k * v1 - v2,
+            FilterType::Bandpass => v1,
+            FilterType::Notch => v0 - k * v1,
+            FilterType::Peak => 2.0 * v2 - v0 + k * v1,
+            FilterType::Allpass => v0 - 2.0 * k * v1,
+            FilterType::Morph => {
+                let hp = v0 - k * v1 - v2;
+                let m = self.morph.clamp(0.0, 1.0) * 2.0;
+                if m < 1.0 {
+                    v2 + (v1 - v2) * m
+                } else {
+                    v1 + (hp - v1) * (m - 1.0)
+                }
+            }
+        }
+    }
+    #[inline]
+    pub fn process(&mut self, x: f32) -> f32 {
+        self.tick(0, x)
+    }
+}
+impl Filter for StateVarFilter {
+    fn set_params(&mut self, cutoff: f32, resonance: f32) {
+        self.resonance = resonance;
+        self.set_cutoff(cutoff);
+    }
+    #[inline]
+    fn process_stereo(&mut self, l: f32, r: f32) -> (f32, f32) {
+        (self.tick(0, l), self.tick(1, r))
+    }
+    fn reset(&mut self) {
+        self.ic1eq = [0.0; 2];
+        self.ic2eq = [0.0; 2];
+    }
+}
+
+#[inline]
+fn one_pole_g(cutoff: f32, sr: f32) -> f32 {
+    // matched one-pole coefficient; capped so the explicit stages stay stable
+    (1.0 - (-2.0 * std::f32::consts::PI * cutoff / sr).exp()).min(0.9)
+}
+
+#[inline]
+fn drive_gain(drive: f32) -> f32 {
+    1.0 + 9.0 * drive.clamp(0.0, 1.0)
+}
+
+// --- 4-pole transistor ladder (Huovilainen-style, tanh per stage) ------
+pub struct LadderFilter {
+    pub drive: f32,
+    sample_rate: f32,
+    g: f32,
+    k: f32,
+    s: [[f32; 4]; 2],
+}
+impl LadderFilter {
+    pub fn new(sr: f32) -> Self {
+        Self { drive: 0.0, sample_rate: sr, g: 0.0, k: 0.0, s: [[0.0; 4]; 2] }
+    }
+    #[inline]
+    fn tick(&mut self, ch: usize, x: f32) -> f32 {
+        let g = self.g;
+        let s = &mut self.s[ch];
+        let u = (x * drive_gain(self.drive) - self.k * s[3]).tanh();
+        s[0] += g * (u - s[0].tanh());
+        s[1] += g * (s[0].tanh() - s[1].tanh());
+        s[2] += g * (s[1].tanh() - s[2].tanh());
+        s[3] += g * (s[2].tanh() - s[3].tanh());
+        // partial passband make-up as resonance eats the low end
+        s[3] * (1.0 + 0.5 * self.k)
+    }
+}
+impl Filter for LadderFilter {
+    fn set_params(&mut self, cutoff: f32, resonance: f32) {
+        self.g = one_pole_g(cutoff.clamp(20.0, self.sample_rate * 0.49), self.sample_rate);
+        // self-oscillates a little past resonance = 1.1
+        self.k = resonance.clamp(0.0, 1.2) * 3.6;
+    }
+    #[inline]
+    fn process_stereo(&mut self, l: f32, r: f32) -> (f32, f32) {
+        (self.tick(0, l), self.tick(1, r))
+    }
+    fn reset(&mut self) {
+        self.s = [[0.0; 4]; 2];
+    }
+}
+
+// --- 4-pole diode ladder: coupled stages, TB-303-ish flavour -----------
+pub struct DiodeLadderFilter {
+    pub drive: f32,
+    sample_rate: f32,
+    g: f32,
+    k: f32,
+    s: [[f32; 4]; 2],
+}
+impl DiodeLadderFilter {
+    pub fn new(sr: f32) -> Self {
+        Self { drive: 0.0, sample_rate: sr, g: 0.0, k: 0.0, s: [[0.0; 4]; 2] }
+    }
+    #[inline]
+    fn tick(&mut self, ch: usize, x: f32) -> f32 {
+        let g = self.g;
+        let s = &mut self.s[ch];
+        let u = x * drive_gain(self.drive) - self.k * s[3];
+        // each capacitor sees the current from the stage below minus what the next one draws
+        let i0 = (u - s[0]).tanh();
+        let i1 = (s[0] - s[1]).tanh();
+        let i2 = (s[1] - s[2]).tanh();
+        let i3 = (s[2] - s[3]).tanh();
+        s[0] += g * (i0 - i1);
+        s[1] += 0.5 * g * (i1 - i2);
+        s[2] += 0.5 * g * (i2 - i3);
+        s[3] += 0.5 * g * i3;
+        s[3] * (1.0 + 0.5 * self.k)
+    }
+}
+impl Filter for DiodeLadderFilter {
+    fn set_params(&mut self, cutoff: f32, resonance: f32) {
+        // the coupled stages roll off early; push g up so the knee sits near the cutoff
+        self.g = one_pole_g((cutoff * 2.0).clamp(20.0, self.sample_rate * 0.49), self.sample_rate);
+        self.k = resonance.clamp(0.0, 1.2) * 8.0;
+    }
+    #[inline]
+    fn process_stereo(&mut self, l: f32, r: f32) -> (f32, f32) {
+        (self.tick(0, l), self.tick(1, r))
+    }
+    fn reset(&mut self) {
+        self.s = [[0.0; 4]; 2];
+    }
+}
+
+// --- Feedback comb tuned to the cutoff (period = 1 / cutoff) -----------
+const COMB_MIN_HZ: f32 = 20.0;
+
+pub struct CombFilter {
+    sample_rate: f32,
+    buffers: [Vec<f32>; 2],
+    write_pos: usize,
+    delay: f32,    // samples
+    feedback: f32,
+}
+impl CombFilter {
+    pub fn new(sr: f32) -> Self {
+        let len = (sr / COMB_MIN_HZ) as usize + 2;
+        Self {
+            sample_rate: sr,
+            buffers: [vec![0.0; len], vec![0.0; len]],
+            write_pos: 0,
+            delay: 1.0,
+            feedback: 0.0,
+        }
+    }
+    #[inline]
+    fn tick(&mut self, ch: usize, x: f32) -> f32 {
+        let len = self.buffers[ch].len();
+        let rp = self.write_pos as f32 - self.delay + len as f32;
+        let i0 = rp as usize % len;
+        let i1 = (i0 + 1) % len;
+        let frac = rp.fract();
+        let buf = &mut self.buffers[ch];
+        let delayed = buf[i0] + (buf[i1] - buf[i0]) * frac;
+        let y = x + self.feedback * delayed;
+        buf[self.write_pos] = y;
+        y * (1.0 - 0.5 * self.feedback.abs())
+    }
+}
+impl Filter for CombFilter {
+    fn set_params(&mut self, cutoff: f32, resonance: f32) {
+        let len = self.buffers[0].len();
+        self.delay = (self.sample_rate / cutoff.max(COMB_MIN_HZ)).clamp(1.0, (len - 2) as f32);
+        self.feedback = (resonance.clamp(0.0, 1.2) / 1.2) * 0.97;
+    }
+    #[inline]
+    fn process_stereo(&mut self, l: f32, r: f32) -> (f32, f32) {
+        let out = (self.tick(0, l), self.tick(1, r));
+        self.write_pos = (self.write_pos + 1) % self.buffers[0].len();
+        out
+    }
+    fn reset(&mut self) {
+        for b in &mut self.buffers {
+            b.fill(0.0);
+        }
+    }
+}
+
+// --- Per-voice filter slot: every model is kept so switching never allocates
+pub struct VoiceFilter {
+    pub model: FilterModel,
+    svf: StateVarFilter,
+    ladder: LadderFilter,
+    diode: DiodeLadderFilter,
+    comb: CombFilter,
+}
+impl VoiceFilter {
+    pub fn new(settings: &FilterSettings, sr: f32) -> Self {
+        Self {
+            model: settings.model,
+            svf: StateVarFilter::new(settings.cutoff, settings.resonance, sr),
+            ladder: LadderFilter::new(sr),
+            diode: DiodeLadderFilter::new(sr),
+            comb: CombFilter::new(sr),
+        }
+    }
+
+    /// Ready a pooled filter for a new note: silent, on the patch's model.
+    pub fn restart(&mut self, settings: &FilterSettings) {
+        self.model = settings.model;
+        self.active().reset();
+    }
+
+    fn active(&mut self) -> &mut dyn Filter {
+        match self.model {
+            FilterModel::Svf => &mut self.svf,
+            FilterModel::Ladder => &mut self.ladder,
+            FilterModel::Diode => &mut self.diode,
+            FilterModel::Comb => &mut self.comb,
+        }
+    }
+
+    /// Pull patch settings and the modulated cutoff into the active model.
+    #[inline]
+    pub fn configure(&mut self, settings: &FilterSettings, cutoff: f32) {
+        if self.model != settings.model {
+            self.model = settings.model;
+            self.active().reset();
+        }
+        match self.model {
+            FilterModel::Svf => {
+                self.svf.mode = settings.filter_type;
+                self.svf.morph = settings.morph;
+            }
+            FilterModel::Ladder => self.ladder.drive = settings.drive,
+            FilterModel::Diode => self.diode.drive = settings.drive,
+            FilterModel::Comb => {}
+        }
+        self.active().set_params(cutoff, settings.resonance);
+    }
+
+    #[inline]
+    pub fn process_stereo(&mut self, l: f32, r: f32) -> (f32, f32) {
+        self.active().process_stereo(l, r)
+    }
+
+    pub fn reset(&mut self) {
+        self.active().reset();
+    }
+}
@@ -0,0 +1,302 @@
+// src/lfo.rs
+// LFO settings plus its running state. Global LFOs tick their own `state`; in
+// per-voice mode each voice carries an `LfoState` started at note-on instead.
+// Tempo-synced LFOs run at a note division; a synced global LFO follows the song
+// position while the transport plays.
+use std::f32::consts::PI;
+
+use crate::envelope::shape;
+use crate::rng::Rng;
+use crate::tempo::{SyncDivision, DEFAULT_BPM};
+
+pub const MAX_CURVE_POINTS: usize = 32;
+
+/// Index order matches the UI's waveform buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    /// New random level each cycle, held.
+    SampleHold,
+    /// New random level each cycle, glided to with a cosine ease.
+    SmoothRandom,
+    /// The LFO's drawn `LfoCurve`.
+    Custom,
+}
+impl LfoShape {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            0 => Self::Sine,
+            1 => Self::Triangle,
+            2 => Self::Saw,
+            3 => Self::Square,
+            4 => Self::SampleHold,
+            5 => Self::SmoothRandom,
+            6 => Self::Custom,
+            _ => Self::Sine,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Sine => 0,
+            Self::Triangle => 1,
+            Self::Saw => 2,
+            Self::Square => 3,
+            Self::SampleHold => 4,
+            Self::SmoothRandom => 5,
+            Self::Custom => 6,
+        }
+    }
+}
+
+/// One breakpoint of a drawn LFO shape.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CurvePoint {
+    pub x: f32, // phase, 0..1
+    pub y: f32, // level, -1..1
+    /// Bend of the segment to the next point, -1..1, as for the envelope curves.
+    pub curve: f32,
+}
+
+/// Breakpoint shape, sorted by `x`. Fixed capacity so the LFO stays `Copy` and
+/// nothing allocates on the audio path. Before the first point and after the
+/// last the level holds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LfoCurve {
+    points: [CurvePoint; MAX_CURVE_POINTS],
+    len: usize,
+}
+impl Default for LfoCurve {
+    /// Same as the built-in triangle.
+    fn default() -> Self {
+        let pt = |x, y| CurvePoint { x, y, curve: 0.0 };
+        Self::new(&[pt(0.0, 0.0), pt(0.25, 1.0), pt(0.75, -1.0), pt(1.0, 0.0)]).unwrap()
+    }
+}
+impl LfoCurve {
+    /// Clamps and sorts `points`; anything past `MAX_CURVE_POINTS` is dropped.
+    /// `None` if there are no (finite) points.
+    pub fn new(points: &[CurvePoint]) -> Option<Self> {
+        let mut curve = Self { points: [CurvePoint::default(); MAX_CURVE_POINTS], len: 0 };
+        for p in points.iter().filter(|p| p.x.is_finite() && p.y.is_finite() && p.curve.is_finite()) {
+            if curve.len == MAX_CURVE_POINTS {
+                break;
+            }
+            curve.points[curve.len] = CurvePoint {
+                x: p.x.clamp(0.0, 1.0),
+                y: p.y.clamp(-1.0, 1.0),
+                curve: p.curve.clamp(-1.0, 1.0),
+            };
+            curve.len += 1;
+        }
+        // stable, so points sharing an `x` keep their order (a vertical step)
+        curve.points[..curve.len].sort_by(|a, b| a.x.total_cmp(&b.x));
+        (curve.len > 0).then_some(curve)
+    }
+
+    /// From consecutive `x, y, curve` triples, as the JS side passes them; a
+    /// trailing partial triple is dropped.
+    pub fn from_flat(flat: &[f32]) -> Option<Self> {
+        let points: Vec<CurvePoint> = flat
+            .chunks_exact(3)
+            .map(|c| CurvePoint { x: c[0], y: c[1], curve: c[2] })
+            .collect();
+        Self::new(&points)
+    }
+
+    pub fn to_flat(&self) -> Vec<f32> {
+        self.points().iter().flat_map(|p| [p.x, p.y, p.curve]).collect()
+    }
+
+    pub fn points(&self) -> &[CurvePoint] {
+        &self.points[..self.len]
+    }
+
+    /// Level at phase `p` (0..1).
+    #[inline]
+    pub fn value(&self, p: f32) -> f32 {
+        let pts = self.points();
+        let i = pts.partition_point(|pt| pt.x <= p);
+        if i == 0 {
+            return pts[0].y;
+        }
+        if i == pts.len() {
+            return pts[i - 1].y;
+        }
+        let (a, b) = (pts[i - 1], pts[i]);
+        let t = (p - a.x) / (b.x - a.x);
+        a.y + (b.y - a.y) * shape(t, a.curve)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LfoState {
+    pub phase: f32, // cycles, 0..1
+    /// Seconds since (re)start, for the fade-in.
+    age: f32,
+    rng: Rng,
+    /// Random level for the current cycle, and the previous one (smooth random glides between them).
+    target: f32,
+    prev: f32,
+}
+impl LfoState {
+    pub fn new(start_phase: f32, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let target = rng.range(-1.0, 1.0);
+        Self { phase: start_phase, age: 0.0, rng, target, prev: target }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
+pub struct LFO {
+    pub rate: f32, // Hz, unless synced
+    /// Take the cycle length from `division` at `tempo` instead of `rate`.
+    pub sync: bool,
+    pub division: SyncDivision,
+    /// Host tempo in BPM; the engine keeps it current.
+    pub tempo: f32,
+    pub amount: f32,
+    pub shape: LfoShape,
+    pub start_phase: f32, // 0..1
+    pub fade_in: f32,     // seconds
+    /// Restart the global LFO on every note-on.
+    pub retrigger: bool,
+    /// Give every voice its own LFO, started at note-on.
+    pub per_voice: bool,
+    /// Run a single cycle from note-on and hold the end level, i.e. act as an envelope.
+    pub one_shot: bool,
+    pub curve: LfoCurve,
+    pub state: LfoState,
+}
+impl Default for LFO {
+    fn default() -> Self {
+        Self {
+            rate: 2.5,
+            sync: false,
+            division: SyncDivision::default(),
+            tempo: DEFAULT_BPM,
+            amount: 0.0,
+            shape: LfoShape::Sine,
+            start_phase: 0.0,
+            fade_in: 0.0,
+            retrigger: false,
+            per_voice: false,
+            one_shot: false,
+            curve: LfoCurve::default(),
+            state: LfoState::new(0.0, 0),
+        }
+    }
+}
+impl LFO {
+    /// `key` is the part after `lfoN_`, e.g. `rate` or `waveform`.
+    pub fn set_by_name(&mut self, key: &str, value: f32) {
+        match key {
+            "rate"      => self.rate = value.max(0.0),
+            "sync"      => self.sync = value > 0.5,
+            "division"  => self.division = SyncDivision::from_f32(value),
+            "amount"    => self.amount = value,
+            "waveform"  => self.shape = LfoShape::from_f32(value),
+            "phase"     => self.start_phase = value.rem_euclid(1.0),
+            "fade"      => self.fade_in = value.max(0.0),
+            "retrigger" => self.retrigger = value > 0.5,
+            "per_voice" => self.per_voice = value > 0.5,
+            "one_shot"  => self.one_shot = value > 0.5,
+            _ => {}
+        }
+    }
+
+    /// Every scalar setting under its `set_by_name` key, for preset export
+    /// (the curve goes separately).
+    pub fn named_values(&self) -> [(&'static str, f32); 10] {
+        [
+            ("rate", self.rate),
+            ("sync", if self.sync { 1.0 } else { 0.0 }),
+            ("division", self.division.to_index() as f32),
+            ("amount", self.amount),
+            ("waveform", self.shape.to_index() as f32),
+            ("phase", self.start_phase),
+            ("fade", self.fade_in),
+            ("retrigger", if self.retrigger { 1.0 } else { 0.0 }),
+            ("per_voice", if self.per_voice { 1.0 } else { 0.0 }),
+            ("one_shot", if self.one_shot { 1.0 } else { 0.0 }),
+        ]
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        let mut st = self.state;
+        self.advance(&mut st, dt);
+        self.state = st;
+    }
+
+    pub fn retrigger(&mut self) {
+        self.state.phase = self.start_phase;
+        self.state.age = 0.0;
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.state = LfoState { rng: Rng::new(seed), ..self.state };
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value_of(&self.state)
+    }
+
+    /// Fresh state for a per-voice LFO.
+    pub fn voice_state(&self, seed: u64) -> LfoState {
+        LfoState::new(self.start_phase, seed)
+    }
+
+    /// Cycles per second, from `rate` or the synced division.
+    pub fn hz(&self) -> f32 {
+        if self.sync { self.division.hz(self.tempo) } else { self.rate }
+    }
+
+    /// Set the global state's phase from the song position (in beats) instead of
+    /// free-running, so synced LFOs line up with the bar. Rate modulation is
+    /// ignored while following.
+    pub fn follow_song(&mut self, beats: f64, dt: f32) {
+        let st = &mut self.state;
+        st.age += dt;
+        let phase = (beats / self.division.beats() as f64 + self.start_phase as f64).rem_euclid(1.0) as f32;
+        if phase < st.phase {
+            st.prev = st.target;
+            st.target = st.rng.range(-1.0, 1.0);
+        }
+        st.phase = phase;
+    }
+
+    /// Step `st` with these settings; random shapes pick a new level on each wrap.
+    /// One-shot LFOs stop at the end of the cycle instead of wrapping.
+    pub fn advance(&self, st: &mut LfoState, dt: f32) {
+        st.age += dt;
+        let next = st.phase + dt * self.hz();
+        if self.one_shot {
+            st.phase = next.min(1.0);
+            return;
+        }
+        if next >= 1.0 {
+            st.prev = st.target;
+            st.target = st.rng.range(-1.0, 1.0);
+        }
+        st.phase = next.rem_euclid(1.0);
+    }
+
+    pub fn value_of(&self, st: &LfoState) -> f32 {
+        let p = st.phase;
+        let base = match self.shape {
+            LfoShape::Sine => (2.0 * PI * p).sin(),
+            // starts at 0 rising, like the oscillator triangle
+            LfoShape::Triangle => 1.0 - 4.0 * ((p + 0.25).fract() - 0.5).abs(),
+            LfoShape::Saw => 2.0 * p - 1.0,
+            LfoShape::Square => if p < 0.5 { 1.0 } else { -1.0 },
+            LfoShape::SampleHold => st.target,
+            LfoShape::SmoothRandom => st.prev + (st.target - st.prev) * (0.5 - 0.5 * (PI * p).cos()),
+            LfoShape::Custom => self.curve.value(p),
+        };
+        let fade = if self.fade_in > 0.0 { (st.age / self.fade_in).min(1.0) } else { 1.0 };
+        base * self.amount * fade
+    }
+}
@@ -0,0 +1,147 @@
+// src/midi.rs
+// Raw MIDI 1.0 byte-stream parser. Keeps running status between calls so a
+// message split across `Engine::process_midi` calls still decodes; realtime
+// bytes are skipped anywhere. SysEx bodies are collected into a fixed buffer
+// and handed out through `take_sysex`.
+
+/// Channel voice messages; `channel` is 0..15. Note-on with velocity 0 comes out as `NoteOff`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidiMessage {
+    NoteOff { channel: u8, note: u8, velocity: u8 },
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    PolyPressure { channel: u8, note: u8, pressure: u8 },
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    ProgramChange { channel: u8, program: u8 },
+    ChannelPressure { channel: u8, pressure: u8 },
+    /// -8192..8191, 0 is centre.
+    PitchBend { channel: u8, value: i16 },
+}
+
+pub const CC_MOD_WHEEL: u8 = 1;
+pub const CC_DATA_ENTRY: u8 = 6;
+pub const CC_SUSTAIN: u8 = 64;
+pub const CC_SOSTENUTO: u8 = 66;
+pub const CC_RPN_LSB: u8 = 100;
+pub const CC_RPN_MSB: u8 = 101;
+pub const CC_ALL_SOUND_OFF: u8 = 120;
+pub const CC_ALL_NOTES_OFF: u8 = 123;
+
+/// Registered parameter numbers, `(msb << 7) | lsb`.
+pub const RPN_BEND_RANGE: u16 = 0;
+pub const RPN_MPE_CONFIG: u16 = 6;
+pub const RPN_NULL: u16 = 0x3FFF;
+
+/// Longest SysEx body kept (an MTS bulk dump is 406 bytes); longer ones are dropped.
+pub const SYSEX_MAX: usize = 512;
+
+#[derive(Clone, Debug)]
+pub struct MidiParser {
+    /// Status of the message being assembled (also the running status).
+    status: Option<u8>,
+    data: [u8; 2],
+    len: usize,
+    in_sysex: bool,
+    /// Body of the current/last SysEx, without F0 and F7. Reserved up front.
+    sysex: Vec<u8>,
+    sysex_overflow: bool,
+    sysex_ready: bool,
+}
+impl Default for MidiParser {
+    fn default() -> Self {
+        Self {
+            status: None,
+            data: [0; 2],
+            len: 0,
+            in_sysex: false,
+            sysex: Vec::with_capacity(SYSEX_MAX),
+            sysex_overflow: false,
+            sysex_ready: false,
+        }
+    }
+}
+impl MidiParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The SysEx body completed by the last `push`, once.
+    pub fn take_sysex(&mut self) -> Option<&[u8]> {
+        if !std::mem::take(&mut self.sysex_ready) {
+            return None;
+        }
+        Some(&self.sysex)
+    }
+
+    /// Feed one byte; returns a message once its last data byte arrives.
+    pub fn push(&mut self, byte: u8) -> Option<MidiMessage> {
+        match byte {
+            // realtime: may appear between any two bytes, leaves everything alone
+            0xF8..=0xFF => None,
+            0xF0 => {
+                self.in_sysex = true;
+                self.status = None;
+                self.sysex.clear();
+                self.sysex_overflow = false;
+                self.sysex_ready = false;
+                None
+            }
+            // system common (incl. end of SysEx) cancels running status
+            0xF1..=0xF7 => {
+                self.sysex_ready = byte == 0xF7 && self.in_sysex && !self.sysex_overflow;
+                self.in_sysex = false;
+                self.status = None;
+                None
+            }
+            0x80..=0xEF => {
+                self.in_sysex = false;
+                self.status = Some(byte);
+                self.len = 0;
+                None
+            }
+            _ if self.in_sysex => {
+                if self.sysex.len() < SYSEX_MAX {
+                    self.sysex.push(byte);
+                } else {
+                    self.sysex_overflow = true;
+                }
+                None
+            }
+            _ => {
+                let status = self.status?;
+                self.data[self.len] = byte;
+                self.len += 1;
+                if self.len < data_len(status) {
+                    return None;
+                }
+                self.len = 0;
+                Some(decode(status, self.data))
+            }
+        }
+    }
+
+    /// Every complete message in `bytes`.
+    pub fn parse<'a>(&'a mut self, bytes: &'a [u8]) -> impl Iterator<Item = MidiMessage> + 'a {
+        bytes.iter().filter_map(move |&b| self.push(b))
+    }
+}
+
+fn data_len(status: u8) -> usize {
+    match status & 0xF0 {
+        0xC0 | 0xD0 => 1,
+        _ => 2,
+    }
+}
+
+fn decode(status: u8, [d0, d1]: [u8; 2]) -> MidiMessage {
+    let channel = status & 0x0F;
+    match status & 0xF0 {
+        0x80 => MidiMessage::NoteOff { channel, note: d0, velocity: d1 },
+        0x90 if d1 == 0 => MidiMessage::NoteOff { channel, note: d0, velocity: 0 },
+        0x90 => MidiMessage::NoteOn { channel, note: d0, velocity: d1 },
+        0xA0 => MidiMessage::PolyPressure { channel, note: d0, pressure: d1 },
+        0xB0 => MidiMessage::ControlChange { channel, controller: d0, value: d1 },
+        0xC0 => MidiMessage::ProgramChange { channel, program: d0 },
+        0xD0 => MidiMessage::ChannelPressure { channel, pressure: d0 },
+        _ => MidiMessage::PitchBend { channel, value: ((d1 as i16) << 7 | d0 as i16) - 8192 },
+    }
+}
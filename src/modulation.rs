@@ -0,0 +1,424 @@
+// src/modulation.rs
+// Slot-based modulation matrix: each slot routes one source to one destination
+// with an amount, a polarity and an optional aux source that scales it.
+// Fixed capacity so routing never allocates on the audio path.
+use std::fmt;
+
+use crate::filter::FILTER_ENV_OCTAVES;
+
+pub const MOD_SLOTS: usize = 32;
+pub const MACROS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModSource {
+    Lfo0,
+    Lfo1,
+    /// Amp envelope.
+    Env0,
+    /// Mod envelope 1, which is also the filter envelope (reads 0 while `filter_env` is off).
+    Env1,
+    /// Mod envelope 2.
+    Env2,
+    Velocity,
+    /// MIDI note, 0..127 mapped to 0..1.
+    Key,
+    ModWheel,
+    /// Channel pressure.
+    Aftertouch,
+    /// Per-note pressure: poly aftertouch, or MPE channel pressure.
+    PolyAftertouch,
+    /// MPE per-note pitch bend, -1..1.
+    NoteBend,
+    /// MPE slide (CC74).
+    Timbre,
+    /// -1..1, before the bend range is applied.
+    PitchBend,
+    Macro0,
+    Macro1,
+    Macro2,
+    Macro3,
+    /// Per-voice value drawn at note-on.
+    Random,
+}
+impl ModSource {
+    pub const COUNT: usize = 18;
+    pub const ALL: [Self; Self::COUNT] = [
+        Self::Lfo0,
+        Self::Lfo1,
+        Self::Env0,
+        Self::Env1,
+        Self::Env2,
+        Self::Velocity,
+        Self::Key,
+        Self::ModWheel,
+        Self::Aftertouch,
+        Self::PolyAftertouch,
+        Self::NoteBend,
+        Self::Timbre,
+        Self::PitchBend,
+        Self::Macro0,
+        Self::Macro1,
+        Self::Macro2,
+        Self::Macro3,
+        Self::Random,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lfo0 => "lfo0",
+            Self::Lfo1 => "lfo1",
+            Self::Env0 => "env0",
+            Self::Env1 => "env1",
+            Self::Env2 => "env2",
+            Self::Velocity => "velocity",
+            Self::Key => "key",
+            Self::ModWheel => "mod_wheel",
+            Self::Aftertouch => "aftertouch",
+            Self::PolyAftertouch => "poly_aftertouch",
+            Self::NoteBend => "note_bend",
+            Self::Timbre => "timbre",
+            Self::PitchBend => "pitch_bend",
+            Self::Macro0 => "macro0",
+            Self::Macro1 => "macro1",
+            Self::Macro2 => "macro2",
+            Self::Macro3 => "macro3",
+            Self::Random => "random",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+    /// Natively bipolar sources (-1..1); the rest are 0..1.
+    pub fn is_bipolar(self) -> bool {
+        matches!(self, Self::Lfo0 | Self::Lfo1 | Self::PitchBend | Self::NoteBend)
+    }
+    /// True if the value is the same for every voice (usable on global destinations).
+    pub fn is_global(self) -> bool {
+        matches!(
+            self,
+            Self::Lfo0 | Self::Lfo1 | Self::ModWheel | Self::Aftertouch | Self::PitchBend
+                | Self::Macro0 | Self::Macro1 | Self::Macro2 | Self::Macro3
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModDest {
+    Osc0Pitch,
+    Osc1Pitch,
+    Osc0Gain,
+    Osc1Gain,
+    Osc0Pan,
+    Osc1Pan,
+    Osc0WtPosition,
+    Osc1WtPosition,
+    Osc0UnisonDetune,
+    Osc1UnisonDetune,
+    Fm,
+    Pm,
+    Am,
+    Ring,
+    FilterCutoff,
+    FilterResonance,
+    FilterDrive,
+    FilterMorph,
+    Amp,
+    VoicePan,
+    // global: only global sources reach these
+    Lfo0Rate,
+    Lfo1Rate,
+    DelayWet,
+    ReverbWet,
+    MasterGain,
+}
+impl ModDest {
+    pub const COUNT: usize = 25;
+    pub const ALL: [Self; Self::COUNT] = [
+        Self::Osc0Pitch,
+        Self::Osc1Pitch,
+        Self::Osc0Gain,
+        Self::Osc1Gain,
+        Self::Osc0Pan,
+        Self::Osc1Pan,
+        Self::Osc0WtPosition,
+        Self::Osc1WtPosition,
+        Self::Osc0UnisonDetune,
+        Self::Osc1UnisonDetune,
+        Self::Fm,
+        Self::Pm,
+        Self::Am,
+        Self::Ring,
+        Self::FilterCutoff,
+        Self::FilterResonance,
+        Self::FilterDrive,
+        Self::FilterMorph,
+        Self::Amp,
+        Self::VoicePan,
+        Self::Lfo0Rate,
+        Self::Lfo1Rate,
+        Self::DelayWet,
+        Self::ReverbWet,
+        Self::MasterGain,
+    ];
+
+    /// Mostly the `set_parameter` name of the modulated value.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Osc0Pitch => "osc0_pitch",
+            Self::Osc1Pitch => "osc1_pitch",
+            Self::Osc0Gain => "osc0_gain",
+            Self::Osc1Gain => "osc1_gain",
+            Self::Osc0Pan => "osc0_pan",
+            Self::Osc1Pan => "osc1_pan",
+            Self::Osc0WtPosition => "osc0_wt_position",
+            Self::Osc1WtPosition => "osc1_wt_position",
+            Self::Osc0UnisonDetune => "osc0_unison_detune",
+            Self::Osc1UnisonDetune => "osc1_unison_detune",
+            Self::Fm => "osc1_fm",
+            Self::Pm => "osc1_pm",
+            Self::Am => "osc1_am",
+            Self::Ring => "osc1_ring",
+            Self::FilterCutoff => "filter_cutoff",
+            Self::FilterResonance => "filter_resonance",
+            Self::FilterDrive => "filter_drive",
+            Self::FilterMorph => "filter_morph",
+            Self::Amp => "amp",
+            Self::VoicePan => "voice_pan",
+            Self::Lfo0Rate => "lfo0_rate",
+            Self::Lfo1Rate => "lfo1_rate",
+            Self::DelayWet => "fx_delay_wet",
+            Self::ReverbWet => "fx_reverb_wet",
+            Self::MasterGain => "master_gain",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.name() == name)
+    }
+    pub fn index(self) -> usize {
+        self as usize
+    }
+    pub fn is_global(self) -> bool {
+        matches!(
+            self,
+            Self::Lfo0Rate | Self::Lfo1Rate | Self::DelayWet | Self::ReverbWet | Self::MasterGain
+        )
+    }
+    /// Offset produced by a full-scale (amount 1) modulation, in the destination's units.
+    pub fn depth(self) -> f32 {
+        match self {
+            Self::Osc0Pitch | Self::Osc1Pitch => 12.0,                // semitones
+            Self::Osc0UnisonDetune | Self::Osc1UnisonDetune => 50.0,  // cents
+            Self::FilterCutoff => FILTER_ENV_OCTAVES,                 // octaves
+            Self::Lfo0Rate | Self::Lfo1Rate => 4.0,                   // octaves
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModSlot {
+    pub source: ModSource,
+    pub dest: ModDest,
+    pub amount: f32, // -1..1
+    /// Read the source as -1..1 (unipolar sources are stretched) rather than 0..1.
+    pub bipolar: bool,
+    /// Scales the slot by this source's 0..1 value.
+    pub aux: Option<ModSource>,
+}
+impl ModSlot {
+    pub fn new(source: ModSource, dest: ModDest, amount: f32) -> Self {
+        let amount = if amount.is_nan() { 0.0 } else { amount.clamp(-1.0, 1.0) };
+        Self { source, dest, amount, bipolar: source.is_bipolar(), aux: None }
+    }
+
+    /// Build a slot from JS-facing names; an empty `aux` (or `"none"`) means no aux source.
+    pub fn parse(source: &str, dest: &str, amount: f32, bipolar: bool, aux: &str) -> Result<Self, ModError> {
+        let src = ModSource::from_name(source).ok_or_else(|| ModError::UnknownSource(source.to_string()))?;
+        let dst = ModDest::from_name(dest).ok_or_else(|| ModError::UnknownDestination(dest.to_string()))?;
+        let aux = match aux {
+            "" | "none" => None,
+            name => Some(ModSource::from_name(name).ok_or_else(|| ModError::UnknownSource(name.to_string()))?),
+        };
+        Ok(Self { bipolar, aux, ..Self::new(src, dst, amount) })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModError {
+    UnknownSource(String),
+    UnknownDestination(String),
+    MatrixFull,
+}
+impl ModError {
+    /// Stable machine-readable code for the JS side.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownSource(_) => "unknown_source",
+            Self::UnknownDestination(_) => "unknown_destination",
+            Self::MatrixFull => "matrix_full",
+        }
+    }
+}
+impl fmt::Display for ModError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownSource(s) => write!(f, "unknown modulation source {s:?}"),
+            Self::UnknownDestination(d) => write!(f, "unknown modulation destination {d:?}"),
+            Self::MatrixFull => write!(f, "all {MOD_SLOTS} modulation slots are in use"),
+        }
+    }
+}
+impl std::error::Error for ModError {}
+
+/// Current value of every source, indexed by `ModSource as usize`.
+pub type SourceValues = [f32; ModSource::COUNT];
+/// Summed offsets per destination (already scaled by `ModDest::depth`).
+pub type DestValues = [f32; ModDest::COUNT];
+
+#[derive(Clone, Copy)]
+pub struct ModMatrix {
+    slots: [Option<ModSlot>; MOD_SLOTS],
+}
+impl Default for ModMatrix {
+    fn default() -> Self {
+        let mut m = Self { slots: [None; MOD_SLOTS] };
+        // historical default: LFO 1 lightly on the cutoff (silent until lfo0_amount is raised)
+        m.slots[0] = Some(ModSlot::new(ModSource::Lfo0, ModDest::FilterCutoff, 0.3));
+        m
+    }
+}
+impl ModMatrix {
+    /// Put `slot` in the first free position.
+    pub fn add(&mut self, slot: ModSlot) -> Result<usize, ModError> {
+        let i = self.slots.iter().position(Option::is_none).ok_or(ModError::MatrixFull)?;
+        self.slots[i] = Some(slot);
+        Ok(i)
+    }
+
+    /// Put `slot` at `index`, replacing what was there. False if `index` is out of range.
+    pub fn set(&mut self, index: usize, slot: ModSlot) -> bool {
+        match self.slots.get_mut(index) {
+            Some(s) => {
+                *s = Some(slot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> bool {
+        self.slots.get_mut(index).and_then(Option::take).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.slots = [None; MOD_SLOTS];
+    }
+
+    pub fn get(&self, index: usize) -> Option<&ModSlot> {
+        self.slots.get(index).and_then(Option::as_ref)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut ModSlot> {
+        self.slots.get_mut(index).and_then(Option::as_mut)
+    }
+
+    /// Occupied slots with their indices.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &ModSlot)> {
+        self.slots.iter().enumerate().filter_map(|(i, s)| s.as_ref().map(|s| (i, s)))
+    }
+
+    pub fn find(&self, source: ModSource, dest: ModDest) -> Option<usize> {
+        self.iter().find(|(_, s)| s.source == source && s.dest == dest).map(|(i, _)| i)
+    }
+
+    /// `mod_slot<N>_amount`, plus the old fixed routes (`mod_lfo0_to_cutoff`, ...),
+    /// which update (or create) the matching slot.
+    pub fn set_by_name(&mut self, name: &str, value: f32) {
+        if let Some(index) = name
+            .strip_prefix("mod_slot")
+            .and_then(|rest| rest.strip_suffix("_amount"))
+            .and_then(|n| n.parse::<usize>().ok())
+        {
+            if let Some(slot) = self.get_mut(index) {
+                slot.amount = value.clamp(-1.0, 1.0);
+            }
+            return;
+        }
+
+        let Some((source, dests)) = legacy_route(name) else { return };
+        for &dest in dests {
+            match self.find(source, dest) {
+                Some(i) => self.slots[i].as_mut().unwrap().amount = value.clamp(-1.0, 1.0),
+                None if value != 0.0 => {
+                    let _ = self.add(ModSlot::new(source, dest, value));
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Read back a `set_by_name` value: the slot's amount (0 for an empty slot), or
+    /// the amount of an old fixed route's slot (0 if there isn't one).
+    pub fn get_by_name(&self, name: &str) -> Option<f32> {
+        if let Some(index) = name
+            .strip_prefix("mod_slot")
+            .and_then(|rest| rest.strip_suffix("_amount"))
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|&i| i < MOD_SLOTS)
+        {
+            return Some(self.get(index).map_or(0.0, |s| s.amount));
+        }
+        let (source, dests) = legacy_route(name)?;
+        Some(self.find(source, dests[0]).and_then(|i| self.get(i)).map_or(0.0, |s| s.amount))
+    }
+
+    /// Sum every slot into per-destination offsets. With `global_only`, slots whose
+    /// source or destination is per-voice are skipped.
+    #[inline]
+    pub fn evaluate(&self, src: &SourceValues, global_only: bool) -> DestValues {
+        let mut out = [0.0; ModDest::COUNT];
+        for slot in self.slots.iter().flatten() {
+            if slot.dest.is_global() != global_only || (global_only && !slot.source.is_global()) {
+                continue;
+            }
+            let raw = src[slot.source as usize];
+            let v = match (slot.bipolar, slot.source.is_bipolar()) {
+                (true, false) => raw * 2.0 - 1.0,
+                (false, true) => raw * 0.5 + 0.5,
+                _ => raw,
+            };
+            let scale = slot.aux.map_or(1.0, |aux| {
+                let a = src[aux as usize];
+                if aux.is_bipolar() { a * 0.5 + 0.5 } else { a }
+            });
+            out[slot.dest.index()] += v * scale * slot.amount * slot.dest.depth();
+        }
+        out
+    }
+}
+
+/// Sources and destinations of the fixed routes the matrix had before it became
+/// slot-based, named `mod_<source>_to_<destination>` (`env` also means `env1`).
+pub const LEGACY_SOURCES: [(&str, ModSource); 4] = [
+    ("lfo0", ModSource::Lfo0),
+    ("lfo1", ModSource::Lfo1),
+    ("env1", ModSource::Env1),
+    ("env2", ModSource::Env2),
+];
+pub const LEGACY_DESTS: [(&str, &[ModDest]); 7] = [
+    ("cutoff", &[ModDest::FilterCutoff]),
+    ("amp", &[ModDest::Amp]),
+    ("wtpos", &[ModDest::Osc0WtPosition, ModDest::Osc1WtPosition]),
+    ("fm", &[ModDest::Fm]),
+    ("pm", &[ModDest::Pm]),
+    ("am", &[ModDest::Am]),
+    ("ring", &[ModDest::Ring]),
+];
+
+fn legacy_route(name: &str) -> Option<(ModSource, &'static [ModDest])> {
+    let (src, dest) = name.strip_prefix("mod_")?.split_once("_to_")?;
+    let src = if src == "env" { "env1" } else { src };
+    let (_, source) = LEGACY_SOURCES.iter().find(|(n, _)| *n == src)?;
+    let (_, dests) = LEGACY_DESTS.iter().find(|(n, _)| *n == dest)?;
+    Some((*source, *dests))
+}
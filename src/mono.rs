@@ -0,0 +1,198 @@
+// src/mono.rs
+// Mono / legato play: a held-note stack with note priority, and portamento.
+// Glide is an offset in semitones from the voice's own pitch that runs to 0,
+// so it works the same whatever the note -> frequency mapping is.
+
+/// Most keys tracked at once; further presses while this many are down are ignored.
+pub const NOTE_STACK: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoiceMode {
+    Poly,
+    /// One voice; every new note retriggers the envelopes.
+    Mono,
+    /// One voice; overlapping notes change pitch without retriggering.
+    Legato,
+}
+impl VoiceMode {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            1 => Self::Mono,
+            2 => Self::Legato,
+            _ => Self::Poly,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Poly => 0,
+            Self::Mono => 1,
+            Self::Legato => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotePriority {
+    Last,
+    Low,
+    High,
+}
+impl NotePriority {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            1 => Self::Low,
+            2 => Self::High,
+            _ => Self::Last,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Last => 0,
+            Self::Low => 1,
+            Self::High => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlideCurve {
+    /// Constant semitones per second.
+    Linear,
+    /// RC-style approach, fast then settling (about -40 dB of the interval left at the glide time).
+    Exponential,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MonoSettings {
+    pub mode: VoiceMode,
+    pub priority: NotePriority,
+    /// Seconds; 0 turns portamento off.
+    pub glide_time: f32,
+    /// `glide_time` is per octave rather than per glide.
+    pub glide_rate: bool,
+    /// Only glide between overlapping notes.
+    pub glide_legato: bool,
+    pub glide_curve: GlideCurve,
+}
+impl Default for MonoSettings {
+    fn default() -> Self {
+        Self {
+            mode: VoiceMode::Poly,
+            priority: NotePriority::Last,
+            glide_time: 0.0,
+            glide_rate: false,
+            glide_legato: false,
+            glide_curve: GlideCurve::Linear,
+        }
+    }
+}
+impl MonoSettings {
+    pub fn set_by_name(&mut self, name: &str, value: f32) {
+        match name {
+            "voice_mode"    => self.mode = VoiceMode::from_f32(value),
+            "note_priority" => self.priority = NotePriority::from_f32(value),
+            "glide_time"    => self.glide_time = value.clamp(0.0, 10.0),
+            "glide_mode"    => self.glide_rate = value > 0.5,
+            "glide_legato"  => self.glide_legato = value > 0.5,
+            "glide_curve"   => self.glide_curve = if value > 0.5 { GlideCurve::Exponential } else { GlideCurve::Linear },
+            _ => {}
+        }
+    }
+
+    /// Every setting under its `set_by_name` name, for preset export.
+    pub fn named_values(&self) -> [(&'static str, f32); 6] {
+        [
+            ("voice_mode", self.mode.to_index() as f32),
+            ("note_priority", self.priority.to_index() as f32),
+            ("glide_time", self.glide_time),
+            ("glide_mode", if self.glide_rate { 1.0 } else { 0.0 }),
+            ("glide_legato", if self.glide_legato { 1.0 } else { 0.0 }),
+            ("glide_curve", if self.glide_curve == GlideCurve::Exponential { 1.0 } else { 0.0 }),
+        ]
+    }
+
+    /// A glide covering `semitones`, or `None` if portamento is off.
+    pub fn glide(&self, semitones: f32, overlapping: bool) -> Option<Glide> {
+        if self.glide_time <= 0.0 || semitones.abs() < 1e-4 || (self.glide_legato && !overlapping) {
+            return None;
+        }
+        let time = if self.glide_rate { self.glide_time * semitones.abs() / 12.0 } else { self.glide_time };
+        Some(Glide { offset: semitones, time: time.max(1e-4), speed: semitones.abs() / time.max(1e-4), curve: self.glide_curve })
+    }
+}
+
+/// A running portamento: `offset` semitones away from the voice's pitch, heading to 0.
+#[derive(Clone, Copy, Debug)]
+pub struct Glide {
+    pub offset: f32,
+    time: f32,
+    /// Semitones per second, for the linear curve.
+    speed: f32,
+    curve: GlideCurve,
+}
+impl Glide {
+    /// Step by `dt`; false once the glide has arrived.
+    #[inline]
+    pub fn tick(&mut self, dt: f32) -> bool {
+        match self.curve {
+            GlideCurve::Linear => {
+                let step = self.speed * dt;
+                self.offset = if self.offset.abs() <= step { 0.0 } else { self.offset - step * self.offset.signum() };
+            }
+            GlideCurve::Exponential => {
+                self.offset *= (-4.6 * dt / self.time).exp();
+                if self.offset.abs() < 1e-3 {
+                    self.offset = 0.0;
+                }
+            }
+        }
+        self.offset != 0.0
+    }
+}
+
+/// Keys currently down, in press order. Fixed capacity, no allocation.
+#[derive(Clone, Copy, Debug)]
+pub struct NoteStack {
+    notes: [u8; NOTE_STACK],
+    len: usize,
+}
+impl Default for NoteStack {
+    fn default() -> Self {
+        Self { notes: [0; NOTE_STACK], len: 0 }
+    }
+}
+impl NoteStack {
+    /// Add `note` as the most recent (moving it up if already held).
+    pub fn push(&mut self, note: u8) {
+        self.remove(note);
+        if self.len < NOTE_STACK {
+            self.notes[self.len] = note;
+            self.len += 1;
+        }
+    }
+
+    pub fn remove(&mut self, note: u8) -> bool {
+        let Some(i) = self.notes[..self.len].iter().position(|&n| n == note) else { return false };
+        self.notes.copy_within(i + 1..self.len, i);
+        self.len -= 1;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The held note that should sound.
+    pub fn pick(&self, priority: NotePriority) -> Option<u8> {
+        let held = &self.notes[..self.len];
+        match priority {
+            NotePriority::Last => held.last().copied(),
+            NotePriority::Low => held.iter().min().copied(),
+            NotePriority::High => held.iter().max().copied(),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+// src/mpe.rs
+// MIDI Polyphonic Expression: one zone whose member channels each carry a single
+// note's pitch bend, pressure and timbre (CC74). The zone's master channel
+// behaves like a normal channel (global bend, controllers, pedals); pedals sent on a
+// member channel hold the whole zone too.
+
+pub const CC_TIMBRE: u8 = 74;
+
+/// Where the zone sits: lower uses master channel 1 (0 here) with members above it,
+/// upper uses master 16 (15) with members below.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MpeZone {
+    Off,
+    Lower,
+    Upper,
+}
+impl MpeZone {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            1 => Self::Lower,
+            2 => Self::Upper,
+            _ => Self::Off,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Off => 0,
+            Self::Lower => 1,
+            Self::Upper => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MpeConfig {
+    pub zone: MpeZone,
+    /// Member channel count, 1..15.
+    pub members: u8,
+    /// Semitones at full per-note bend (the MPE default is 48).
+    pub bend_range: f32,
+}
+impl Default for MpeConfig {
+    fn default() -> Self {
+        Self { zone: MpeZone::Off, members: 15, bend_range: 48.0 }
+    }
+}
+impl MpeConfig {
+    /// `name` is the part after `mpe_`.
+    pub fn set_by_name(&mut self, name: &str, value: f32) {
+        match name {
+            "zone"       => self.zone = MpeZone::from_f32(value),
+            "members"    => self.members = (value.round() as i32).clamp(1, 15) as u8,
+            "bend_range" => self.bend_range = value.clamp(0.0, 96.0),
+            _ => {}
+        }
+    }
+
+    /// Every setting under its `set_by_name` name.
+    pub fn named_values(&self) -> [(&'static str, f32); 3] {
+        [
+            ("zone", self.zone.to_index() as f32),
+            ("members", self.members as f32),
+            ("bend_range", self.bend_range),
+        ]
+    }
+
+    pub fn master(&self) -> Option<u8> {
+        match self.zone {
+            MpeZone::Off => None,
+            MpeZone::Lower => Some(0),
+            MpeZone::Upper => Some(15),
+        }
+    }
+
+    pub fn is_member(&self, channel: u8) -> bool {
+        match self.zone {
+            MpeZone::Off => false,
+            MpeZone::Lower => (1..=self.members).contains(&channel),
+            MpeZone::Upper => (15 - self.members..15).contains(&channel),
+        }
+    }
+}
+
+/// Per-note expression, as last sent on the note's member channel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Expression {
+    pub bend: f32,     // -1..1
+    pub pressure: f32, // 0..1
+    pub timbre: f32,   // 0..1, CC74
+}
+impl Default for Expression {
+    /// Centred bend, no pressure, CC74 at its conventional 64.
+    fn default() -> Self {
+        Self { bend: 0.0, pressure: 0.0, timbre: 64.0 / 127.0 }
+    }
+}
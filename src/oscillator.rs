@@ -0,0 +1,55 @@
+// src/oscillator.rs
+// Anti-aliased analytic waveforms. `t` is the phase in cycles (0..1) and `dt`
+// the phase increment per sample (freq / sample_rate).
+
+/// 2-sample polynomial BLEP residual for a unit upward step at t = 0 (scaled x2).
+#[inline]
+pub fn poly_blep(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let x = t / dt;
+        x + x - x * x - 1.0
+    } else if t > 1.0 - dt {
+        let x = (t - 1.0) / dt;
+        x * x + x + x + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Integrated BLEP residual for a unit change of slope (per sample) at t = 0.
+#[inline]
+pub fn poly_blamp(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let x = 1.0 - t / dt;
+        x * x * x / 6.0
+    } else if t > 1.0 - dt {
+        let x = (t - 1.0) / dt + 1.0;
+        x * x * x / 6.0
+    } else {
+        0.0
+    }
+}
+
+#[inline]
+fn wrap01(t: f32) -> f32 {
+    t - t.floor()
+}
+
+#[inline]
+pub fn saw(t: f32, dt: f32) -> f32 {
+    2.0 * t - 1.0 - poly_blep(t, dt)
+}
+
+#[inline]
+pub fn square(t: f32, dt: f32) -> f32 {
+    let naive = if t < 0.5 { 1.0 } else { -1.0 };
+    naive + poly_blep(t, dt) - poly_blep(wrap01(t + 0.5), dt)
+}
+
+/// Starts at 0 rising, peak at t = 0.25, trough at t = 0.75.
+#[inline]
+pub fn triangle(t: f32, dt: f32) -> f32 {
+    let naive = 1.0 - 4.0 * (wrap01(t + 0.25) - 0.5).abs();
+    // slope flips by -8 at the peak and +8 at the trough (per cycle)
+    naive - 8.0 * dt * poly_blamp(wrap01(t - 0.25), dt) + 8.0 * dt * poly_blamp(wrap01(t - 0.75), dt)
+}
@@ -0,0 +1,444 @@
+// src/params.rs
+// Parameter registry: every `set_parameter` name with a stable numeric id, its
+// range, default, unit, taper and display format. UIs and automation are built
+// from `all()`, and `find` is what rejects misspelt names.
+//
+// Ids are a group base plus the position in the group's list, so entries are
+// only ever appended to a group, never reordered or removed.
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::modulation::{LEGACY_DESTS, LEGACY_SOURCES, MACROS, MOD_SLOTS};
+use crate::patch::Patch;
+use crate::tempo::DIVISION_NAMES;
+use crate::unison::MAX_UNISON;
+use crate::MAX_VOICES;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    None,
+    Hz,
+    Seconds,
+    Semitones,
+    Cents,
+    /// A 0..1 amount shown as 0..100%.
+    Percent,
+    /// -1..1 shown as L100..C..R100.
+    Pan,
+    Bpm,
+    Octaves,
+}
+impl Unit {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "",
+            Self::Hz => "Hz",
+            Self::Seconds => "s",
+            Self::Semitones => "st",
+            Self::Cents => "ct",
+            Self::Percent => "%",
+            Self::Pan => "pan",
+            Self::Bpm => "BPM",
+            Self::Octaves => "oct",
+        }
+    }
+}
+
+/// How 0..1 (knob travel) maps onto the range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Taper {
+    Linear,
+    /// Equal ratios per unit of travel; the range must be above 0.
+    Exponential,
+    /// `min + (max - min) * n^skew`: above 1 gives the low end more travel.
+    Skew(f32),
+}
+impl Taper {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::Exponential => "exponential",
+            Self::Skew(_) => "skew",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Continuous,
+    /// Whole numbers only.
+    Integer,
+    /// 0 or 1.
+    Toggle,
+    /// An index into `ParamInfo::choices`.
+    Choice,
+}
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Continuous => "continuous",
+            Self::Integer => "integer",
+            Self::Toggle => "toggle",
+            Self::Choice => "choice",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamInfo {
+    pub id: u32,
+    pub name: String,
+    pub min: f32,
+    pub max: f32,
+    /// What a new engine starts with.
+    pub default: f32,
+    pub unit: Unit,
+    pub taper: Taper,
+    pub kind: Kind,
+    pub choices: &'static [&'static str],
+}
+impl ParamInfo {
+    /// Pull `value` into range, rounding the stepped kinds.
+    pub fn clamp(&self, value: f32) -> f32 {
+        let v = if value.is_nan() { self.default } else { value.clamp(self.min, self.max) };
+        match self.kind {
+            Kind::Continuous => v,
+            Kind::Integer | Kind::Choice => v.round(),
+            Kind::Toggle => if v > 0.5 { 1.0 } else { 0.0 },
+        }
+    }
+
+    /// Knob position 0..1 of `value`.
+    pub fn normalize(&self, value: f32) -> f32 {
+        let v = self.clamp(value);
+        if self.max <= self.min {
+            return 0.0;
+        }
+        let n = match self.taper {
+            Taper::Linear => (v - self.min) / (self.max - self.min),
+            Taper::Exponential => (v / self.min).ln() / (self.max / self.min).ln(),
+            Taper::Skew(skew) => ((v - self.min) / (self.max - self.min)).powf(1.0 / skew),
+        };
+        n.clamp(0.0, 1.0)
+    }
+
+    /// Value at knob position `n` (0..1).
+    pub fn denormalize(&self, n: f32) -> f32 {
+        let n = if n.is_nan() { 0.0 } else { n.clamp(0.0, 1.0) };
+        let v = match self.taper {
+            Taper::Linear => self.min + (self.max - self.min) * n,
+            Taper::Exponential => self.min * (self.max / self.min).powf(n),
+            Taper::Skew(skew) => self.min + (self.max - self.min) * n.powf(skew),
+        };
+        self.clamp(v)
+    }
+
+    /// Saved in presets: everything but live performance state (controllers, host
+    /// tempo) and the per-slot and old fixed-route views of the mod matrix, which
+    /// a preset stores as its slot list instead.
+    pub fn in_preset(&self) -> bool {
+        let performance = matches!(
+            self.name.as_str(),
+            "tempo" | "mod_wheel" | "aftertouch" | "pitch_bend" | "sustain_pedal" | "sostenuto_pedal"
+        );
+        !performance && self.id < MOD_SLOT_BASE
+    }
+
+    /// `value` for display, e.g. `1.20 kHz`, `35 ms`, `L40`, `1/8d`, `On`.
+    pub fn format(&self, value: f32) -> String {
+        let v = self.clamp(value);
+        match self.kind {
+            Kind::Toggle => return if v > 0.5 { "On" } else { "Off" }.to_string(),
+            Kind::Choice => return self.choices.get(v as usize).copied().unwrap_or("?").to_string(),
+            Kind::Integer => return format!("{v:.0}"),
+            Kind::Continuous => {}
+        }
+        match self.unit {
+            Unit::None => format!("{v:.2}"),
+            Unit::Hz if v >= 1000.0 => format!("{:.2} kHz", v / 1000.0),
+            Unit::Hz if v < 10.0 => format!("{v:.2} Hz"),
+            Unit::Hz => format!("{v:.0} Hz"),
+            Unit::Seconds if v < 1.0 => format!("{:.0} ms", v * 1000.0),
+            Unit::Seconds => format!("{v:.2} s"),
+            Unit::Semitones => format!("{v:+.1} st"),
+            Unit::Cents => format!("{v:+.1} ct"),
+            Unit::Percent => format!("{:.0}%", v * 100.0),
+            Unit::Pan if v.abs() < 0.005 => "C".to_string(),
+            Unit::Pan => format!("{}{:.0}", if v < 0.0 { 'L' } else { 'R' }, v.abs() * 100.0),
+            Unit::Bpm => format!("{v:.1} BPM"),
+            Unit::Octaves => format!("{v:+.2} oct"),
+        }
+    }
+}
+
+/// A group entry; the full name is the group prefix + `key`.
+struct Spec {
+    key: &'static str,
+    min: f32,
+    max: f32,
+    unit: Unit,
+    taper: Taper,
+    kind: Kind,
+    choices: &'static [&'static str],
+}
+const fn lin(key: &'static str, min: f32, max: f32, unit: Unit) -> Spec {
+    Spec { key, min, max, unit, taper: Taper::Linear, kind: Kind::Continuous, choices: &[] }
+}
+const fn exp(key: &'static str, min: f32, max: f32, unit: Unit) -> Spec {
+    Spec { key, min, max, unit, taper: Taper::Exponential, kind: Kind::Continuous, choices: &[] }
+}
+const fn skew(key: &'static str, min: f32, max: f32, skew: f32, unit: Unit) -> Spec {
+    Spec { key, min, max, unit, taper: Taper::Skew(skew), kind: Kind::Continuous, choices: &[] }
+}
+const fn int(key: &'static str, min: f32, max: f32, unit: Unit) -> Spec {
+    Spec { key, min, max, unit, taper: Taper::Linear, kind: Kind::Integer, choices: &[] }
+}
+const fn toggle(key: &'static str) -> Spec {
+    Spec { key, min: 0.0, max: 1.0, unit: Unit::None, taper: Taper::Linear, kind: Kind::Toggle, choices: &[] }
+}
+const fn choice(key: &'static str, choices: &'static [&'static str]) -> Spec {
+    let max = (choices.len() - 1) as f32;
+    Spec { key, min: 0.0, max, unit: Unit::None, taper: Taper::Linear, kind: Kind::Choice, choices }
+}
+
+const WAVEFORMS: &[&str] = &["Sine", "Saw", "Square", "Triangle", "Noise", "Wavetable"];
+const UNISON_CURVES: &[&str] = &["Linear", "Exponential", "Root"];
+const LFO_SHAPES: &[&str] = &["Sine", "Triangle", "Saw", "Square", "Sample & Hold", "Smooth Random", "Custom"];
+const FILTER_MODELS: &[&str] = &["SVF", "Ladder", "Diode", "Comb"];
+const FILTER_TYPES: &[&str] = &["Lowpass", "Highpass", "Bandpass", "Notch", "Peak", "Allpass", "Morph"];
+const DELAY_MODES: &[&str] = &["Mono", "Stereo", "Ping-Pong"];
+const STEAL_POLICIES: &[&str] = &["Oldest", "Quietest", "Lowest", "Highest"];
+const VOICE_MODES: &[&str] = &["Poly", "Mono", "Legato"];
+const NOTE_PRIORITIES: &[&str] = &["Last", "Low", "High"];
+const GLIDE_MODES: &[&str] = &["Time", "Rate"];
+const GLIDE_CURVES: &[&str] = &["Linear", "Exponential"];
+const MPE_ZONES: &[&str] = &["Off", "Lower", "Upper"];
+
+const OSC: &[Spec] = &[
+    choice("waveform", WAVEFORMS),
+    lin("gain", 0.0, 1.0, Unit::None),
+    int("coarse", -48.0, 48.0, Unit::Semitones),
+    lin("detune", -100.0, 100.0, Unit::Cents),
+    lin("pan", -1.0, 1.0, Unit::Pan),
+    lin("wt_position", 0.0, 1.0, Unit::Percent),
+    int("unison", 1.0, MAX_UNISON as f32, Unit::None),
+    lin("unison_detune", 0.0, 100.0, Unit::Cents),
+    choice("unison_curve", UNISON_CURVES),
+    lin("unison_width", 0.0, 1.0, Unit::Percent),
+    lin("unison_blend", 0.0, 1.0, Unit::Percent),
+    lin("unison_phase_rand", 0.0, 1.0, Unit::Percent),
+];
+const CROSS_MOD: &[Spec] = &[
+    toggle("osc0_sync"),
+    toggle("osc1_sync"),
+    lin("osc1_fm", 0.0, 1.0, Unit::Percent),
+    lin("osc1_pm", 0.0, 1.0, Unit::Percent),
+    lin("osc1_am", 0.0, 1.0, Unit::Percent),
+    lin("osc1_ring", 0.0, 1.0, Unit::Percent),
+];
+const VOICING: &[Spec] = &[
+    int("polyphony", 1.0, MAX_VOICES as f32, Unit::None),
+    choice("voice_steal", STEAL_POLICIES),
+    toggle("voice_retrigger"),
+    lin("voice_pan_spread", 0.0, 1.0, Unit::Percent),
+    choice("voice_mode", VOICE_MODES),
+    choice("note_priority", NOTE_PRIORITIES),
+    skew("glide_time", 0.0, 10.0, 3.0, Unit::Seconds),
+    choice("glide_mode", GLIDE_MODES),
+    toggle("glide_legato"),
+    choice("glide_curve", GLIDE_CURVES),
+];
+const ENVELOPE: &[Spec] = &[
+    skew("delay", 0.0, 10.0, 3.0, Unit::Seconds),
+    skew("attack", 0.0001, 10.0, 3.0, Unit::Seconds),
+    skew("hold", 0.0, 10.0, 3.0, Unit::Seconds),
+    skew("decay", 0.0001, 10.0, 3.0, Unit::Seconds),
+    lin("sustain", 0.0, 1.0, Unit::Percent),
+    skew("release", 0.0001, 10.0, 3.0, Unit::Seconds),
+    lin("attack_curve", -1.0, 1.0, Unit::None),
+    lin("decay_curve", -1.0, 1.0, Unit::None),
+    lin("release_curve", -1.0, 1.0, Unit::None),
+];
+const FILTER: &[Spec] = &[
+    choice("model", FILTER_MODELS),
+    lin("drive", 0.0, 1.0, Unit::Percent),
+    choice("type", FILTER_TYPES),
+    lin("morph", 0.0, 1.0, Unit::None),
+    exp("cutoff", 20.0, 22_000.0, Unit::Hz),
+    lin("resonance", 0.0, 2.0, Unit::None),
+    lin("keytrack", 0.0, 1.0, Unit::Percent),
+    toggle("env"),
+    lin("env_amount", -8.0, 8.0, Unit::Octaves),
+];
+const LFO: &[Spec] = &[
+    exp("rate", 0.01, 100.0, Unit::Hz),
+    toggle("sync"),
+    choice("division", &DIVISION_NAMES),
+    lin("amount", -1.0, 1.0, Unit::None),
+    choice("waveform", LFO_SHAPES),
+    lin("phase", 0.0, 1.0, Unit::None),
+    skew("fade", 0.0, 10.0, 2.0, Unit::Seconds),
+    toggle("retrigger"),
+    toggle("per_voice"),
+    toggle("one_shot"),
+];
+const FX: &[Spec] = &[
+    skew("delay_time", 0.0, 5.0, 2.0, Unit::Seconds),
+    lin("delay_feedback", 0.0, 0.99, Unit::Percent),
+    lin("delay_wet", 0.0, 1.0, Unit::Percent),
+    choice("delay_mode", DELAY_MODES),
+    toggle("delay_sync"),
+    choice("delay_division", &DIVISION_NAMES),
+    lin("reverb_wet", 0.0, 1.0, Unit::Percent),
+    lin("reverb_width", 0.0, 1.0, Unit::Percent),
+];
+const MASTER: &[Spec] = &[
+    lin("master_gain", 0.0, 2.0, Unit::None),
+    exp("tempo", 20.0, 999.0, Unit::Bpm),
+    lin("reference_pitch", 200.0, 1000.0, Unit::Hz),
+];
+const CONTROLLERS: &[Spec] = &[
+    lin("mod_wheel", 0.0, 1.0, Unit::Percent),
+    lin("aftertouch", 0.0, 1.0, Unit::Percent),
+    lin("pitch_bend", -1.0, 1.0, Unit::None),
+    lin("pitch_bend_range", 0.0, 48.0, Unit::Semitones),
+    toggle("sustain_pedal"),
+    toggle("sostenuto_pedal"),
+];
+const MPE: &[Spec] = &[
+    choice("zone", MPE_ZONES),
+    int("members", 1.0, 15.0, Unit::None),
+    lin("bend_range", 0.0, 96.0, Unit::Semitones),
+];
+
+/// `(id base, name prefix, entries)`.
+const GROUPS: &[(u32, &str, &[Spec])] = &[
+    (100, "osc0_", OSC),
+    (200, "osc1_", OSC),
+    (300, "", CROSS_MOD),
+    (400, "", VOICING),
+    (500, "env_", ENVELOPE),
+    (520, "filter_env_", ENVELOPE),
+    (540, "env2_", ENVELOPE),
+    (600, "filter_", FILTER),
+    (700, "lfo0_", LFO),
+    (720, "lfo1_", LFO),
+    (800, "fx_", FX),
+    (900, "", MASTER),
+    (1000, "", CONTROLLERS),
+    (1100, "mpe_", MPE),
+];
+/// Generated groups: `macro<N>`, `mod_slot<N>_amount`, then the old fixed routes.
+const MACRO_BASE: u32 = 1200;
+const MOD_SLOT_BASE: u32 = 1300;
+const LEGACY_ROUTE_BASE: u32 = 1400;
+
+struct Registry {
+    params: Vec<ParamInfo>,
+    by_name: HashMap<String, usize>,
+    /// `(id base, index of its first entry, entry count)` per group, by ascending base.
+    groups: Vec<(u32, usize, usize)>,
+}
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+/// Built on first use; `Engine::with_seed` touches it so that is never the audio thread.
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(build)
+}
+
+fn build() -> Registry {
+    // defaults are whatever a new engine starts with
+    let defaults = Patch::default();
+    let mut params = Vec::new();
+    let mut push = |id: u32, name: String, spec: &Spec| {
+        params.push(ParamInfo {
+            id,
+            default: defaults.read(&name).unwrap_or(spec.min),
+            name,
+            min: spec.min,
+            max: spec.max,
+            unit: spec.unit,
+            taper: spec.taper,
+            kind: spec.kind,
+            choices: spec.choices,
+        });
+    };
+    for &(base, prefix, specs) in GROUPS {
+        for (i, spec) in specs.iter().enumerate() {
+            push(base + i as u32, format!("{prefix}{}", spec.key), spec);
+        }
+    }
+    let amount = lin("", 0.0, 1.0, Unit::Percent);
+    for i in 0..MACROS {
+        push(MACRO_BASE + i as u32, format!("macro{i}"), &amount);
+    }
+    let depth = lin("", -1.0, 1.0, Unit::None);
+    for i in 0..MOD_SLOTS {
+        push(MOD_SLOT_BASE + i as u32, format!("mod_slot{i}_amount"), &depth);
+    }
+    let routes = LEGACY_SOURCES.iter().flat_map(|(s, _)| LEGACY_DESTS.iter().map(move |(d, _)| format!("mod_{s}_to_{d}")));
+    for (i, name) in routes.enumerate() {
+        push(LEGACY_ROUTE_BASE + i as u32, name, &depth);
+    }
+
+    // runs of consecutive ids, for `by_id`
+    let mut groups: Vec<(u32, usize, usize)> = Vec::new();
+    for (i, p) in params.iter().enumerate() {
+        match groups.last_mut() {
+            Some((base, _, len)) if *base + *len as u32 == p.id => *len += 1,
+            _ => groups.push((p.id, i, 1)),
+        }
+    }
+    let by_name = params.iter().enumerate().map(|(i, p)| (p.name.clone(), i)).collect();
+    Registry { params, by_name, groups }
+}
+
+/// Every parameter, grouped and in id order within a group.
+pub fn all() -> &'static [ParamInfo] {
+    &registry().params
+}
+
+/// Look up a `set_parameter` name, including the older aliases (`osc0_volume`,
+/// `env1_*`, `osc0_unison_voices`, `mod_env_to_*`).
+pub fn find(name: &str) -> Option<&'static ParamInfo> {
+    let r = registry();
+    if let Some(&i) = r.by_name.get(name) {
+        return Some(&r.params[i]);
+    }
+    let &(_, canonical) = ALIASES.iter().find(|(alias, _)| *alias == name)?;
+    r.by_name.get(canonical).map(|&i| &r.params[i])
+}
+
+pub fn by_id(id: u32) -> Option<&'static ParamInfo> {
+    let r = registry();
+    let g = r.groups.partition_point(|&(base, _, _)| base <= id).checked_sub(1)?;
+    let (base, start, len) = r.groups[g];
+    let offset = (id - base) as usize;
+    (offset < len).then(|| &r.params[start + offset])
+}
+
+/// Older names, each with the one it stands for.
+const ALIASES: &[(&str, &str)] = &[
+    ("osc0_volume", "osc0_gain"),
+    ("osc1_volume", "osc1_gain"),
+    ("osc0_unison_voices", "osc0_unison"),
+    ("osc1_unison_voices", "osc1_unison"),
+    ("env1_delay", "filter_env_delay"),
+    ("env1_attack", "filter_env_attack"),
+    ("env1_hold", "filter_env_hold"),
+    ("env1_decay", "filter_env_decay"),
+    ("env1_sustain", "filter_env_sustain"),
+    ("env1_release", "filter_env_release"),
+    ("env1_attack_curve", "filter_env_attack_curve"),
+    ("env1_decay_curve", "filter_env_decay_curve"),
+    ("env1_release_curve", "filter_env_release_curve"),
+    ("env1_amount", "filter_env_amount"),
+    ("mod_env_to_cutoff", "mod_env1_to_cutoff"),
+    ("mod_env_to_amp", "mod_env1_to_amp"),
+    ("mod_env_to_wtpos", "mod_env1_to_wtpos"),
+    ("mod_env_to_fm", "mod_env1_to_fm"),
+    ("mod_env_to_pm", "mod_env1_to_pm"),
+    ("mod_env_to_am", "mod_env1_to_am"),
+    ("mod_env_to_ring", "mod_env1_to_ring"),
+];
@@ -0,0 +1,159 @@
+// src/patch.rs
+// An engine's patch as plain values, without voices, delay lines, reverb buffers or
+// wavetables, and reading it back by parameter name. `Patch::default()` is what a
+// new engine starts with, so the parameter registry takes its defaults from here.
+use crate::alloc::StealPolicy;
+use crate::delay::DelayMode;
+use crate::envelope::ADSRParams;
+use crate::filter::FilterSettings;
+use crate::lfo::LFO;
+use crate::modulation::{ModMatrix, MACROS};
+use crate::mono::MonoSettings;
+use crate::mpe::MpeConfig;
+use crate::tempo::{SyncDivision, DEFAULT_BPM};
+use crate::tuning::DEFAULT_REFERENCE;
+use crate::voice::{CrossMod, OscSettings};
+use crate::MAX_VOICES;
+
+#[derive(Clone, Copy)]
+pub struct Patch {
+    pub osc: [OscSettings; 2],
+    pub cross_mod: CrossMod,
+    pub polyphony: usize,
+    pub steal_policy: StealPolicy,
+    pub retrigger_same_note: bool,
+    pub voice_pan_spread: f32,
+    pub mono: MonoSettings,
+    pub env: ADSRParams,
+    pub env2: ADSRParams,
+    pub filter: FilterSettings,
+    pub lfos: [LFO; 2],
+    pub mod_matrix: ModMatrix,
+    pub delay_time: f32,
+    pub delay_feedback: f32,
+    pub delay_wet: f32,
+    pub delay_mode: DelayMode,
+    pub delay_sync: bool,
+    pub delay_division: SyncDivision,
+    pub reverb_wet: f32,
+    pub reverb_width: f32,
+    pub master_gain: f32,
+    pub tempo: f32,
+    pub reference_pitch: f32,
+    pub bend_range: f32,
+    pub mpe: MpeConfig,
+    // performance state, also readable as parameters
+    pub mod_wheel: f32,
+    pub aftertouch: f32,
+    pub pitch_bend: f32,
+    pub sustain: bool,
+    pub sostenuto: bool,
+    pub macros: [f32; MACROS],
+}
+
+impl Default for Patch {
+    fn default() -> Self {
+        Self {
+            osc: [OscSettings::default(), OscSettings::default()],
+            cross_mod: CrossMod::default(),
+            polyphony: MAX_VOICES,
+            steal_policy: StealPolicy::Oldest,
+            retrigger_same_note: true,
+            voice_pan_spread: 0.0,
+            mono: MonoSettings::default(),
+            env: ADSRParams::default(),
+            env2: ADSRParams::default(),
+            filter: FilterSettings::default(),
+            lfos: [LFO::default(), LFO::default()],
+            mod_matrix: ModMatrix::default(),
+            delay_time: 0.3,
+            delay_feedback: 0.35,
+            delay_wet: 0.35,
+            delay_mode: DelayMode::Stereo,
+            delay_sync: false,
+            delay_division: SyncDivision::default(),
+            reverb_wet: 0.35,
+            reverb_width: 1.0,
+            master_gain: 0.9,
+            tempo: DEFAULT_BPM,
+            reference_pitch: DEFAULT_REFERENCE,
+            bend_range: 2.0,
+            mpe: MpeConfig::default(),
+            mod_wheel: 0.0,
+            aftertouch: 0.0,
+            pitch_bend: 0.0,
+            sustain: false,
+            sostenuto: false,
+            macros: [0.0; MACROS],
+        }
+    }
+}
+
+impl Patch {
+    /// The value of a parameter by canonical name (aliases are resolved by `params::find`).
+    pub fn read(&self, name: &str) -> Option<f32> {
+        let flag = |b: bool| if b { 1.0 } else { 0.0 };
+        let lookup = |pairs: &[(&str, f32)], key: &str| pairs.iter().find(|(k, _)| *k == key).map(|&(_, v)| v);
+        let v = match name {
+            "polyphony"        => self.polyphony as f32,
+            "voice_steal"      => self.steal_policy.to_index() as f32,
+            "voice_retrigger"  => flag(self.retrigger_same_note),
+            "voice_pan_spread" => self.voice_pan_spread,
+            "filter_model"     => self.filter.model.to_index() as f32,
+            "filter_drive"     => self.filter.drive,
+            "filter_type"      => self.filter.filter_type.to_index() as f32,
+            "filter_morph"     => self.filter.morph,
+            "filter_cutoff"    => self.filter.cutoff,
+            "filter_resonance" => self.filter.resonance,
+            "filter_keytrack"  => self.filter.key_track,
+            "filter_env"       => flag(self.filter.env_enabled),
+            "filter_env_amount" => self.filter.env_amount,
+            "fx_delay_time"     => self.delay_time,
+            "fx_delay_feedback" => self.delay_feedback,
+            "fx_delay_wet"      => self.delay_wet,
+            "fx_delay_mode"     => self.delay_mode.to_index() as f32,
+            "fx_delay_sync"     => flag(self.delay_sync),
+            "fx_delay_division" => self.delay_division.to_index() as f32,
+            "fx_reverb_wet"     => self.reverb_wet,
+            "fx_reverb_width"   => self.reverb_width,
+            "master_gain"      => self.master_gain,
+            "tempo"            => self.tempo,
+            "reference_pitch"  => self.reference_pitch,
+            "mod_wheel"        => self.mod_wheel,
+            "aftertouch"       => self.aftertouch,
+            "pitch_bend"       => self.pitch_bend,
+            "pitch_bend_range" => self.bend_range,
+            "sustain_pedal"    => flag(self.sustain),
+            "sostenuto_pedal"  => flag(self.sostenuto),
+            name if name.starts_with("mod_") => return self.mod_matrix.get_by_name(name),
+            name if name.starts_with("env_") => return lookup(&self.env.named_values(), &name[4..]),
+            name if name.starts_with("filter_env_") => return lookup(&self.filter.env.named_values(), &name[11..]),
+            name if name.starts_with("env2_") => return lookup(&self.env2.named_values(), &name[5..]),
+            name if name.starts_with("lfo0_") => return lookup(&self.lfos[0].named_values(), &name[5..]),
+            name if name.starts_with("lfo1_") => return lookup(&self.lfos[1].named_values(), &name[5..]),
+            name if name.starts_with("mpe_") => return lookup(&self.mpe.named_values(), &name[4..]),
+            name if name.starts_with("macro") => return name[5..].parse::<usize>().ok().and_then(|i| self.macros.get(i).copied()),
+            name => {
+                if let Some(v) = lookup(&self.mono.named_values(), name).or_else(|| lookup(&self.cross_mod.named_values(), name)) {
+                    return Some(v);
+                }
+                let i = match name.get(..4) {
+                    Some("osc0") => 0,
+                    Some("osc1") => 1,
+                    _ => return None,
+                };
+                let os = &self.osc[i];
+                match name.get(5..)? {
+                    "waveform"    => os.waveform.to_index() as f32,
+                    "gain"        => os.gain,
+                    "coarse"      => os.coarse,
+                    "detune"      => os.detune_cents,
+                    "pan"         => os.pan,
+                    "wt_position" => os.wt_position,
+                    key => return lookup(&os.unison.named_values(), key),
+                }
+            }
+        };
+        Some(v)
+    }
+}
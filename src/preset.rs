@@ -0,0 +1,646 @@
+// src/preset.rs
+// Versioned preset format. A preset is JSON holding every patch parameter (by
+// registry name), the mod matrix, the drawn LFO curves, the tuning table, the
+// smoothing times and every wavetable frame, so export -> import restores an
+// engine bit for bit. Older versions are migrated on import; if any field fails
+// validation, all of them are reported and nothing is applied.
+//
+// Version 2:
+//   { "version": 2,
+//     "params": { "<name>": number, ... },
+//     "mod_slots": [{ "index", "source", "destination", "amount", "bipolar", "aux" }, ...],
+//     "lfo_points": [[x, y, curve, ...], ...],
+//     "tuning": [128 x Hz],
+//     "smoothing": { "<name>": seconds, ... },
+//     "wavetables": [{ "frame_len": 2048, "data": "<base64 f32 little-endian>" }, ...] }
+// Anything left out takes a new engine's value (no "tuning" means 12-TET).
+//
+// Version 1 is the flat, unversioned object the UI saved before: parameters as
+// top-level keys, single-cycle wavetables cut to 256 samples, and either a
+// `mod_slots` list or the old fixed `mod_<src>_to_<dest>` routes.
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::engine::Engine;
+use crate::json::{self, Json, JsonError};
+use crate::lfo::LfoCurve;
+use crate::modulation::{ModError, ModMatrix, ModSlot, ModSource, MOD_SLOTS};
+use crate::params::{self, Kind, ParamInfo};
+use crate::smooth::{Smoothed, MAX_RAMP};
+use crate::wavetable::{resample_cycle, Wavetable, MAX_WT_FRAMES};
+use crate::WAVETABLE_SIZE;
+
+/// What `export_preset` writes.
+pub const PRESET_VERSION: u32 = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    /// Path to the field, e.g. `params.filter_cutoff` or `mod_slots[2].source`.
+    pub field: String,
+    pub reason: String,
+}
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.reason)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PresetError {
+    Syntax(JsonError),
+    NotAnObject,
+    /// Written by a newer version of the synth.
+    UnsupportedVersion(u32),
+    Invalid(Vec<FieldError>),
+}
+impl PresetError {
+    /// Stable machine-readable code for the JS side.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Syntax(_) => "syntax",
+            Self::NotAnObject => "not_an_object",
+            Self::UnsupportedVersion(_) => "unsupported_version",
+            Self::Invalid(_) => "invalid_fields",
+        }
+    }
+
+    /// The fields that failed validation (empty for the other errors).
+    pub fn fields(&self) -> &[FieldError] {
+        match self {
+            Self::Invalid(fields) => fields,
+            _ => &[],
+        }
+    }
+}
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(e) => write!(f, "preset is not valid JSON: {e}"),
+            Self::NotAnObject => write!(f, "preset is not a JSON object"),
+            Self::UnsupportedVersion(v) => write!(f, "preset version {v} is newer than this synth (up to {PRESET_VERSION})"),
+            Self::Invalid(fields) => {
+                write!(f, "{} invalid preset field(s): ", fields.len())?;
+                for (i, e) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("; ")?;
+                    }
+                    write!(f, "{e}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+impl std::error::Error for PresetError {}
+impl From<JsonError> for PresetError {
+    fn from(e: JsonError) -> Self {
+        Self::Syntax(e)
+    }
+}
+
+/// A decoded, validated patch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preset {
+    /// Every `ParamInfo::in_preset` parameter, in registry order.
+    pub params: Vec<(&'static ParamInfo, f32)>,
+    pub mod_slots: Vec<(usize, ModSlot)>,
+    /// Per LFO, flat `x, y, curve` triples.
+    pub lfo_points: [Vec<f32>; 2],
+    /// Key -> Hz; `None` is 12-TET at the `reference_pitch` parameter.
+    pub tuning: Option<Vec<f32>>,
+    /// Ramp seconds, indexed by `Smoothed as usize`.
+    pub smoothing: [f32; Smoothed::COUNT],
+    /// Per oscillator, frames of `WAVETABLE_SIZE` samples.
+    pub wavetables: [Vec<Vec<f32>>; 2],
+}
+
+impl Preset {
+    pub fn capture(e: &Engine) -> Self {
+        let patch = e.patch();
+        let params = params::all()
+            .iter()
+            .filter(|p| p.in_preset())
+            .map(|p| (p, patch.read(&p.name).unwrap_or(p.default)))
+            .collect();
+        let wavetable = |osc: usize| {
+            let table = &e.wavetables[osc];
+            (0..table.frame_count()).filter_map(|f| table.raw_frame(f)).map(<[f32]>::to_vec).collect()
+        };
+        Self {
+            params,
+            mod_slots: e.mod_matrix().iter().map(|(i, slot)| (i, *slot)).collect(),
+            lfo_points: [e.lfos[0].curve.to_flat(), e.lfos[1].curve.to_flat()],
+            tuning: Some(e.tuning().table().to_vec()),
+            smoothing: Smoothed::ALL.map(|p| e.smoothing(p.name()).unwrap_or_default()),
+            wavetables: [wavetable(0), wavetable(1)],
+        }
+    }
+
+    /// Replace the engine's patch with this one. Playing voices carry on.
+    pub fn apply(&self, e: &mut Engine) {
+        for &(info, v) in &self.params {
+            // the tuning below sets it together with its table
+            if info.name != "reference_pitch" {
+                e.set_parameter(&info.name, v);
+            }
+        }
+        let reference = self.param("reference_pitch").unwrap_or(crate::tuning::DEFAULT_REFERENCE);
+        match &self.tuning {
+            Some(table) => e.set_tuning_table(table, reference),
+            None => {
+                e.reset_tuning();
+                e.set_reference_pitch(reference);
+            }
+        }
+        e.clear_mod_slots();
+        for &(i, slot) in &self.mod_slots {
+            e.mod_matrix.set(i, slot);
+        }
+        for (i, flat) in self.lfo_points.iter().enumerate() {
+            e.set_lfo_points(i, flat);
+        }
+        for p in Smoothed::ALL {
+            e.set_smoothing(p.name(), self.smoothing[p as usize]);
+        }
+        for (osc, frames) in self.wavetables.iter().enumerate() {
+            if !frames.is_empty() {
+                e.wavetables[osc] = Wavetable::from_frames(frames.clone());
+            }
+        }
+    }
+
+    pub fn param(&self, name: &str) -> Option<f32> {
+        self.params.iter().find(|(p, _)| p.name == name).map(|&(_, v)| v)
+    }
+
+    /// The current-version JSON text.
+    pub fn to_json(&self) -> String {
+        let params = self.params.iter().map(|&(p, v)| (p.name.clone(), Json::number(v))).collect();
+        let slots = self.mod_slots.iter().map(|&(i, slot)| slot_json(i, &slot)).collect();
+        let numbers = |values: &[f32]| Json::Array(values.iter().map(|&v| Json::number(v)).collect());
+        let smoothing = Smoothed::ALL
+            .iter()
+            .map(|&p| (p.name().to_string(), Json::number(self.smoothing[p as usize])))
+            .collect();
+        let wavetables = self.wavetables.iter().map(|frames| wavetable_json(frames)).collect();
+
+        let mut doc = vec![
+            ("version".to_string(), Json::number(PRESET_VERSION as f32)),
+            ("params".into(), Json::Object(params)),
+            ("mod_slots".into(), Json::Array(slots)),
+            ("lfo_points".into(), Json::Array(self.lfo_points.iter().map(|p| numbers(p)).collect())),
+        ];
+        if let Some(table) = &self.tuning {
+            doc.push(("tuning".into(), numbers(table)));
+        }
+        doc.push(("smoothing".into(), Json::Object(smoothing)));
+        doc.push(("wavetables".into(), Json::Array(wavetables)));
+        Json::Object(doc).to_string()
+    }
+
+    /// Parse and validate preset JSON of any version up to `PRESET_VERSION`.
+    pub fn from_json(text: &str) -> Result<Self, PresetError> {
+        let doc = json::parse(text)?;
+        let fields = doc.as_object().ok_or(PresetError::NotAnObject)?;
+        let version = match doc.get("version") {
+            None => 1,
+            Some(v) => match v.as_f32() {
+                Some(n) if n >= 1.0 && n.fract() == 0.0 => n as u32,
+                _ => {
+                    return Err(PresetError::Invalid(vec![FieldError {
+                        field: "version".into(),
+                        reason: "expected a whole number from 1".into(),
+                    }]))
+                }
+            },
+        };
+        match version {
+            1 => decode(&migrate_v1(fields)),
+            PRESET_VERSION => decode(&doc),
+            v => Err(PresetError::UnsupportedVersion(v)),
+        }
+    }
+}
+
+fn slot_json(index: usize, s: &ModSlot) -> Json {
+    Json::Object(vec![
+        ("index".into(), Json::number(index as f32)),
+        ("source".into(), Json::String(s.source.name().into())),
+        ("destination".into(), Json::String(s.dest.name().into())),
+        ("amount".into(), Json::number(s.amount)),
+        ("bipolar".into(), Json::Bool(s.bipolar)),
+        ("aux".into(), Json::String(s.aux.map_or("", ModSource::name).into())),
+    ])
+}
+
+fn wavetable_json(frames: &[Vec<f32>]) -> Json {
+    let mut bytes = Vec::with_capacity(frames.len() * WAVETABLE_SIZE * 4);
+    for s in frames.iter().flatten() {
+        bytes.extend_from_slice(&s.to_le_bytes());
+    }
+    Json::Object(vec![
+        ("frame_len".into(), Json::number(WAVETABLE_SIZE as f32)),
+        ("data".into(), Json::String(base64_encode(&bytes))),
+    ])
+}
+
+/// Rewrite a version 1 object as version 2. Values are pulled into range, as
+/// the version 1 import did; what can't be converted is passed on for
+/// `decode` to report.
+fn migrate_v1(fields: &[(String, Json)]) -> Json {
+    let mut params = Vec::new();
+    let mut out = Vec::new();
+    let mut lfo_points = [Json::Null, Json::Null];
+    let mut routes = Vec::new();
+    let mut has_slots = false;
+    for (key, value) in fields {
+        match key.as_str() {
+            "version" => {}
+            "tuning" => out.push((key.clone(), value.clone())),
+            "lfo0_points" => lfo_points[0] = value.clone(),
+            "lfo1_points" => lfo_points[1] = value.clone(),
+            "mod_slots" => {
+                has_slots = true;
+                // slots were added in list order, so they sat in the first positions
+                let slots = match value {
+                    Json::Array(items) => Json::Array(
+                        items
+                            .iter()
+                            .enumerate()
+                            .map(|(i, item)| match item {
+                                Json::Object(f) => {
+                                    let mut f = f.clone();
+                                    f.push(("index".into(), Json::number(i as f32)));
+                                    Json::Object(f)
+                                }
+                                other => other.clone(),
+                            })
+                            .collect(),
+                    ),
+                    other => other.clone(),
+                };
+                out.push((key.clone(), slots));
+            }
+            "wavetables" => {
+                let tables = match value.as_array() {
+                    Some(items) => Json::Array(
+                        items
+                            .iter()
+                            .map(|t| match v1_cycle(t) {
+                                Some(cycle) => wavetable_json(&[resample_cycle(&cycle)]),
+                                None => t.clone(),
+                            })
+                            .collect(),
+                    ),
+                    None => value.clone(),
+                };
+                out.push((key.clone(), tables));
+            }
+            _ if key.starts_with("mod_") && ModMatrix::default().get_by_name(key).is_some() => {
+                if let Some(v) = value.as_f32() {
+                    routes.push((key.as_str(), v));
+                }
+            }
+            _ => {
+                let value = match (params::find(key), value.as_f32()) {
+                    (Some(info), Some(v)) => Json::number(info.clamp(v)),
+                    _ => value.clone(),
+                };
+                let name = params::find(key).map_or(key.clone(), |p| p.name.clone());
+                params.push((name, value));
+            }
+        }
+    }
+    // the old fixed routes only counted when there was no slot list
+    if !has_slots && !routes.is_empty() {
+        let mut matrix = ModMatrix::default();
+        for (name, v) in routes {
+            matrix.set_by_name(name, v);
+        }
+        let slots = matrix.iter().map(|(i, slot)| slot_json(i, slot)).collect();
+        out.push(("mod_slots".into(), Json::Array(slots)));
+    }
+    if lfo_points.iter().any(|p| *p != Json::Null) {
+        out.push(("lfo_points".into(), Json::Array(lfo_points.to_vec())));
+    }
+    out.push(("params".into(), Json::Object(params)));
+    Json::Object(out)
+}
+
+/// A version 1 wavetable: an array of samples, or the index-keyed object a
+/// `Float32Array` becomes in `JSON.stringify`.
+fn v1_cycle(t: &Json) -> Option<Vec<f32>> {
+    let samples: Vec<f32> = match t {
+        Json::Array(items) => items.iter().map(Json::as_f32).collect::<Option<_>>()?,
+        Json::Object(fields) => {
+            let mut indexed: Vec<(usize, f32)> =
+                fields.iter().map(|(k, v)| Some((k.parse().ok()?, v.as_f32()?))).collect::<Option<_>>()?;
+            indexed.sort_by_key(|&(i, _)| i);
+            indexed.into_iter().map(|(_, v)| v).collect()
+        }
+        _ => return None,
+    };
+    (!samples.is_empty() && samples.iter().all(|v| v.is_finite())).then_some(samples)
+}
+
+/// A new engine's patch, captured once; missing fields fall back to it.
+fn new_engine_patch() -> &'static Preset {
+    static PATCH: OnceLock<Preset> = OnceLock::new();
+    PATCH.get_or_init(|| Preset { tuning: None, ..Preset::capture(&Engine::new(48_000.0)) })
+}
+
+/// Validate a version 2 document into a `Preset`, on top of a new engine's patch.
+fn decode(doc: &Json) -> Result<Preset, PresetError> {
+    let mut preset = new_engine_patch().clone();
+    let mut d = Decoder::default();
+    for (key, value) in doc.as_object().unwrap_or_default() {
+        match key.as_str() {
+            "version" => {}
+            "params" => d.params(value, &mut preset.params),
+            "mod_slots" => {
+                if let Some(slots) = d.mod_slots(value) {
+                    preset.mod_slots = slots;
+                }
+            }
+            "lfo_points" => d.lfo_points(value, &mut preset.lfo_points),
+            "tuning" => preset.tuning = d.tuning(value),
+            "smoothing" => d.smoothing(value, &mut preset.smoothing),
+            "wavetables" => d.wavetables(value, &mut preset.wavetables),
+            _ => d.fail(key, "unknown field"),
+        }
+    }
+    if d.errors.is_empty() {
+        Ok(preset)
+    } else {
+        Err(PresetError::Invalid(d.errors))
+    }
+}
+
+#[derive(Default)]
+struct Decoder {
+    errors: Vec<FieldError>,
+}
+impl Decoder {
+    fn fail(&mut self, field: &str, reason: impl Into<String>) {
+        self.errors.push(FieldError { field: field.to_string(), reason: reason.into() });
+    }
+
+    fn object<'a>(&mut self, value: &'a Json, field: &str) -> Option<&'a [(String, Json)]> {
+        let fields = value.as_object();
+        if fields.is_none() {
+            self.fail(field, "expected an object");
+        }
+        fields
+    }
+
+    fn array<'a>(&mut self, value: &'a Json, field: &str) -> Option<&'a [Json]> {
+        let items = value.as_array();
+        if items.is_none() {
+            self.fail(field, "expected an array");
+        }
+        items
+    }
+
+    /// A finite number in `min..=max`.
+    fn number(&mut self, value: &Json, field: &str, min: f32, max: f32) -> Option<f32> {
+        match value.as_f32() {
+            None => self.fail(field, "expected a number"),
+            Some(v) if !(min..=max).contains(&v) => self.fail(field, format!("expected {min}..{max}")),
+            Some(v) => return Some(v),
+        }
+        None
+    }
+
+    fn numbers(&mut self, value: &Json, field: &str) -> Option<Vec<f32>> {
+        let items = self.array(value, field)?;
+        let out: Option<Vec<f32>> = items.iter().map(|v| v.as_f32().filter(|v| v.is_finite())).collect();
+        if out.is_none() {
+            self.fail(field, "expected an array of numbers");
+        }
+        out
+    }
+
+    fn params(&mut self, value: &Json, out: &mut [(&'static ParamInfo, f32)]) {
+        let Some(fields) = self.object(value, "params") else { return };
+        for (name, v) in fields {
+            let field = format!("params.{name}");
+            let Some(info) = params::find(name) else {
+                self.fail(&field, "unknown parameter");
+                continue;
+            };
+            let Some(slot) = out.iter_mut().find(|(p, _)| p.name == info.name) else {
+                self.fail(&field, "not stored in presets");
+                continue;
+            };
+            let Some(v) = self.number(v, &field, info.min, info.max) else { continue };
+            if info.clamp(v) != v {
+                self.fail(&field, if info.kind == Kind::Toggle { "expected 0 or 1" } else { "expected a whole number" });
+                continue;
+            }
+            slot.1 = v;
+        }
+    }
+
+    fn mod_slots(&mut self, value: &Json) -> Option<Vec<(usize, ModSlot)>> {
+        let items = self.array(value, "mod_slots")?;
+        let mut out: Vec<(usize, ModSlot)> = Vec::new();
+        let errors = self.errors.len();
+        for (n, item) in items.iter().enumerate() {
+            let field = |key: &str| format!("mod_slots[{n}].{key}");
+            if item.as_object().is_none() {
+                self.fail(&format!("mod_slots[{n}]"), "expected an object");
+                continue;
+            }
+            let text = |d: &mut Self, key: &str, required: bool| match item.get(key) {
+                Some(Json::String(s)) => Some(s.as_str()),
+                None if !required => Some(""),
+                _ => {
+                    d.fail(&field(key), "expected a string");
+                    None
+                }
+            };
+            let index = item.get("index").and_then(Json::as_f32);
+            let index = match index {
+                Some(i) if i >= 0.0 && i < MOD_SLOTS as f32 && i.fract() == 0.0 => Some(i as usize),
+                _ => {
+                    self.fail(&field("index"), format!("expected a whole number in 0..{}", MOD_SLOTS - 1));
+                    None
+                }
+            };
+            if let Some(i) = index.filter(|i| out.iter().any(|(j, _)| j == i)) {
+                self.fail(&field("index"), format!("slot {i} appears twice"));
+            }
+            let source = text(self, "source", true);
+            let dest = text(self, "destination", true);
+            let aux = text(self, "aux", false);
+            let amount = match item.get("amount") {
+                Some(v) => self.number(v, &field("amount"), -1.0, 1.0),
+                None => {
+                    self.fail(&field("amount"), "expected a number");
+                    None
+                }
+            };
+            let bipolar = item.get("bipolar").and_then(Json::as_bool);
+            if bipolar.is_none() {
+                self.fail(&field("bipolar"), "expected true or false");
+            }
+            let (Some(index), Some(source), Some(dest), Some(aux), Some(amount), Some(bipolar)) =
+                (index, source, dest, aux, amount, bipolar)
+            else {
+                continue;
+            };
+            match ModSlot::parse(source, dest, amount, bipolar, aux) {
+                Ok(slot) => out.push((index, slot)),
+                Err(e) => {
+                    let key = match &e {
+                        ModError::UnknownSource(name) if name == aux => "aux",
+                        ModError::UnknownSource(_) => "source",
+                        _ => "destination",
+                    };
+                    self.fail(&field(key), e.to_string());
+                }
+            }
+        }
+        (self.errors.len() == errors).then_some(out)
+    }
+
+    fn lfo_points(&mut self, value: &Json, out: &mut [Vec<f32>; 2]) {
+        let Some(items) = self.array(value, "lfo_points") else { return };
+        if items.len() > out.len() {
+            self.fail("lfo_points", format!("expected at most {} curves", out.len()));
+            return;
+        }
+        for (i, item) in items.iter().enumerate() {
+            // null keeps the default curve
+            if *item == Json::Null {
+                continue;
+            }
+            let field = format!("lfo_points[{i}]");
+            let Some(flat) = self.numbers(item, &field) else { continue };
+            if !flat.len().is_multiple_of(3) || LfoCurve::from_flat(&flat).is_none() {
+                self.fail(&field, "expected x, y, curve triples making a valid curve");
+                continue;
+            }
+            out[i] = flat;
+        }
+    }
+
+    fn tuning(&mut self, value: &Json) -> Option<Vec<f32>> {
+        let table = self.numbers(value, "tuning")?;
+        if table.len() != 128 {
+            self.fail("tuning", format!("expected 128 entries, found {}", table.len()));
+            return None;
+        }
+        if let Some(key) = table.iter().position(|&hz| hz < 0.0) {
+            self.fail(&format!("tuning[{key}]"), "expected a frequency in Hz (0 for unmapped)");
+            return None;
+        }
+        Some(table)
+    }
+
+    fn smoothing(&mut self, value: &Json, out: &mut [f32; Smoothed::COUNT]) {
+        let Some(fields) = self.object(value, "smoothing") else { return };
+        for (name, v) in fields {
+            let field = format!("smoothing.{name}");
+            let Some(p) = Smoothed::from_name(name) else {
+                self.fail(&field, "not a smoothed parameter");
+                continue;
+            };
+            if let Some(v) = self.number(v, &field, 0.0, MAX_RAMP) {
+                out[p as usize] = v;
+            }
+        }
+    }
+
+    fn wavetables(&mut self, value: &Json, out: &mut [Vec<Vec<f32>>; 2]) {
+        let Some(items) = self.array(value, "wavetables") else { return };
+        if items.len() > out.len() {
+            self.fail("wavetables", format!("expected at most {} tables", out.len()));
+            return;
+        }
+        for (osc, item) in items.iter().enumerate() {
+            let field = |key: &str| format!("wavetables[{osc}].{key}");
+            if item.as_object().is_none() {
+                self.fail(&format!("wavetables[{osc}]"), "expected an object");
+                continue;
+            }
+            let frame_len = match item.get("frame_len").and_then(Json::as_f32) {
+                Some(n) if n >= 1.0 && n.fract() == 0.0 => n as usize,
+                _ => {
+                    self.fail(&field("frame_len"), "expected a whole number of samples");
+                    continue;
+                }
+            };
+            let Some(bytes) = item.get("data").and_then(Json::as_str).and_then(base64_decode) else {
+                self.fail(&field("data"), "expected base64 text");
+                continue;
+            };
+            let samples: Vec<f32> = bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+            let frames = samples.len() / frame_len;
+            if !bytes.len().is_multiple_of(4) || !samples.len().is_multiple_of(frame_len) || frames == 0 {
+                self.fail(&field("data"), format!("expected whole frames of {frame_len} 32-bit samples"));
+            } else if frames > MAX_WT_FRAMES {
+                self.fail(&field("data"), format!("expected at most {MAX_WT_FRAMES} frames, found {frames}"));
+            } else if samples.iter().any(|s| !s.is_finite()) {
+                self.fail(&field("data"), "contains non-finite samples");
+            } else {
+                out[osc] = samples
+                    .chunks_exact(frame_len)
+                    .map(|f| if frame_len == WAVETABLE_SIZE { f.to_vec() } else { resample_cycle(f) })
+                    .collect();
+            }
+        }
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for (n, chunk) in text.chunks(4).enumerate() {
+        let last = n == text.len() / 4 - 1;
+        let pad = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if pad > 2 || (pad > 0 && !last) {
+            return None;
+        }
+        let mut v = 0u32;
+        for &c in &chunk[..4 - pad] {
+            v = v << 6 | base64_digit(c)?;
+        }
+        v <<= 6 * pad as u32;
+        out.extend_from_slice(&v.to_be_bytes()[1..4 - pad]);
+    }
+    Some(out)
+}
+
+fn base64_digit(c: u8) -> Option<u32> {
+    let d = match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => return None,
+    };
+    Some(u32::from(d))
+}
@@ -0,0 +1,175 @@
+// src/reverb.rs
+
+// comb / allpass base lengths in seconds (left tank); the right tank adds STEREO_SPREAD
+const COMB_TIMES: [f32; 4] = [0.050, 0.056, 0.061, 0.068];
+const COMB_DAMP_HZ: [f32; 4] = [5000.0, 4500.0, 4000.0, 3500.0];
+const AP_TIMES: [f32; 2] = [0.012, 0.004];
+const STEREO_SPREAD: f32 = 0.00052; // ~23 samples @ 44.1k, Freeverb-style decorrelation
+
+// --- Lush Schroeder/Moorer Reverb (stereo, decorrelated L/R tanks) -----
+pub struct SimpleReverb {
+    // pre-delay
+    pre_buf: Vec<f32>,
+    pre_pos: usize,
+    pre_len: usize,
+
+    // 4 damped combs per side: 0..4 left, 4..8 right
+    comb_bufs: [Vec<f32>; 8],
+    comb_pos: [usize; 8],
+    comb_len: [usize; 8],
+    comb_feedback: [f32; 8],
+    comb_lp_state: [f32; 8],
+    comb_lp_coef: [f32; 8], // feedback damping
+
+    // 2 series allpasses per side: 0..2 left, 2..4 right
+    ap_bufs: [Vec<f32>; 4],
+    ap_pos: [usize; 4],
+    ap_len: [usize; 4],
+    ap_g: [f32; 4],
+
+    pub wet: f32,
+    pub width: f32, // 0 = mono tail, 1 = fully decorrelated
+    sample_rate: f32,
+    decay: f32, // seconds
+    size: f32,  // scale
+}
+impl SimpleReverb {
+    pub fn new(sr: f32) -> Self {
+        let mut comb_lp_coef = [0.0; 8];
+        for (i, c) in comb_lp_coef.iter_mut().enumerate() {
+            *c = Self::lp_coef(sr, COMB_DAMP_HZ[i % 4]);
+        }
+        let mut r = Self {
+            pre_buf: Vec::new(),
+            pre_pos: 0,
+            pre_len: 1,
+
+            comb_bufs: Default::default(),
+            comb_pos: [0; 8],
+            comb_len: [1; 8],
+            comb_feedback: [0.77, 0.80, 0.82, 0.84, 0.77, 0.80, 0.82, 0.84],
+            comb_lp_state: [0.0; 8],
+            comb_lp_coef,
+
+            ap_bufs: Default::default(),
+            ap_pos: [0; 4],
+            ap_len: [1; 4],
+            ap_g: [0.7; 4],
+
+            wet: 0.35,
+            width: 1.0,
+            sample_rate: sr,
+            decay: 2.2,
+            size: 1.0,
+        };
+        r.recalc_lengths();
+        r
+    }
+
+    #[inline]
+    fn lp_coef(sr: f32, fc: f32) -> f32 {
+        let x = (-2.0 * std::f32::consts::PI * fc / sr).exp();
+        1.0 - x
+    }
+
+    pub fn set_size(&mut self, s: f32) {
+        self.size = s.clamp(0.5, 1.5);
+        self.recalc_lengths();
+    }
+
+    pub fn set_decay(&mut self, seconds: f32) {
+        self.decay = seconds.clamp(0.2, 8.0);
+        for i in 0..8 {
+            let len_s = (self.comb_len[i] as f32) / self.sample_rate;
+            // T60 ≈ -3 * len / ln(feedback)  => feedback ≈ e^( -3*len / T60 )
+            let fb = (-3.0 * len_s / self.decay).exp();
+            self.comb_feedback[i] = fb.clamp(0.5, 0.98);
+        }
+    }
+
+    fn recalc_lengths(&mut self) {
+        let scale = self.size;
+        let scale_len = |l: usize| ((l as f32) * scale).max(1.0) as usize;
+
+        self.pre_len = scale_len((0.02 * self.sample_rate) as usize);
+        self.pre_buf.resize(self.pre_len.max(1), 0.0);
+        self.pre_pos %= self.pre_len.max(1);
+
+        let sr = self.sample_rate;
+        let spread = |side: usize| if side == 0 { 0.0 } else { STEREO_SPREAD };
+        for i in 0..8 {
+            let nl = scale_len(((COMB_TIMES[i % 4] + spread(i / 4)) * sr) as usize);
+            self.comb_len[i] = nl.max(1);
+            self.comb_bufs[i].resize(self.comb_len[i], 0.0);
+            self.comb_pos[i] %= self.comb_len[i];
+        }
+
+        for i in 0..4 {
+            let nl = scale_len(((AP_TIMES[i % 2] + spread(i / 2)) * sr) as usize);
+            self.ap_len[i] = nl.max(1);
+            self.ap_bufs[i].resize(self.ap_len[i], 0.0);
+            self.ap_pos[i] %= self.ap_len[i];
+        }
+
+        self.set_decay(self.decay);
+    }
+
+    #[inline]
+    fn comb_process(&mut self, i: usize, x: f32) -> f32 {
+        let p = self.comb_pos[i];
+        let y = self.comb_bufs[i][p];
+
+        // damping in feedback loop
+        let a = self.comb_lp_coef[i];
+        self.comb_lp_state[i] += a * (y - self.comb_lp_state[i]);
+        let fb = self.comb_feedback[i];
+
+        self.comb_bufs[i][p] = x + self.comb_lp_state[i] * fb;
+        self.comb_pos[i] = (p + 1) % self.comb_len[i];
+        y
+    }
+
+    #[inline]
+    fn allpass_process(&mut self, i: usize, x: f32) -> f32 {
+        let p = self.ap_pos[i];
+        let buf = self.ap_bufs[i][p];
+        let g = self.ap_g[i];
+
+        let y = -x + buf;
+        self.ap_bufs[i][p] = x + buf * g;
+
+        self.ap_pos[i] = (p + 1) % self.ap_len[i];
+        y
+    }
+
+    pub fn process_stereo(&mut self, l: f32, r: f32) -> (f32, f32) {
+        // pre-delay (tanks share a mono feed; the tails differ)
+        let x = 0.5 * (l + r);
+        let y0 = self.pre_buf[self.pre_pos];
+        self.pre_buf[self.pre_pos] = x;
+        self.pre_pos = (self.pre_pos + 1) % self.pre_len;
+
+        // parallel combs → average, per side
+        let mut tails = [0.0f32; 2];
+        for (side, t) in tails.iter_mut().enumerate() {
+            let mut s = 0.0;
+            for i in 0..4 { s += self.comb_process(side * 4 + i, y0); }
+            s *= 0.25;
+
+            // diffusion allpasses
+            let y1 = self.allpass_process(side * 2, s);
+            *t = self.allpass_process(side * 2 + 1, y1);
+        }
+
+        // width: crossfade each side towards the other tail
+        let w = self.width.clamp(0.0, 1.0);
+        let (a, b) = (0.5 + 0.5 * w, 0.5 - 0.5 * w);
+        let tail_l = tails[0] * a + tails[1] * b;
+        let tail_r = tails[1] * a + tails[0] * b;
+
+        (
+            l * (1.0 - self.wet) + tail_l * self.wet,
+            r * (1.0 - self.wet) + tail_r * self.wet,
+        )
+    }
+}
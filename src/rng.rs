@@ -0,0 +1,57 @@
+// src/rng.rs
+// Small seedable PRNG (xorshift64*) so the engine never needs a host RNG.
+use crate::WAVETABLE_SIZE;
+
+const DEFAULT_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rng {
+    state: u64,
+}
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut r = Self { state: DEFAULT_SEED };
+        r.reseed(seed);
+        r
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        // splitmix64 scramble so small/similar seeds still diverge; xorshift can't hold 0
+        let mut z = seed.wrapping_add(DEFAULT_SEED);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        self.state = if z == 0 { DEFAULT_SEED } else { z };
+    }
+
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in [0, 1).
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    #[inline]
+    pub fn range(&mut self, a: f32, b: f32) -> f32 {
+        a + (b - a) * self.next_f32()
+    }
+}
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Random start phase in wavetable samples.
+pub fn rand_phase(rng: &mut Rng) -> f32 {
+    rng.next_f32() * (WAVETABLE_SIZE as f32)
+}
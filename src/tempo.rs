@@ -0,0 +1,103 @@
+// src/tempo.rs
+// Host tempo / song position and the musical note divisions that tempo-synced
+// LFOs and the delay lock to. Positions are in beats (quarter notes).
+
+pub const DEFAULT_BPM: f32 = 120.0;
+
+/// `(name, length in beats)`, longest first; the index is the division's `f32` value.
+const DIVISIONS: [(&str, f32); 20] = [
+    ("8/1", 32.0),
+    ("4/1", 16.0),
+    ("2/1", 8.0),
+    ("1/1", 4.0),
+    ("1/2d", 3.0),
+    ("1/2", 2.0),
+    ("1/2t", 4.0 / 3.0),
+    ("1/4d", 1.5),
+    ("1/4", 1.0),
+    ("1/4t", 2.0 / 3.0),
+    ("1/8d", 0.75),
+    ("1/8", 0.5),
+    ("1/8t", 1.0 / 3.0),
+    ("1/16d", 0.375),
+    ("1/16", 0.25),
+    ("1/16t", 1.0 / 6.0),
+    ("1/32d", 0.1875),
+    ("1/32", 0.125),
+    ("1/32t", 1.0 / 12.0),
+    ("1/64", 0.0625),
+];
+
+/// Division names in index order.
+pub const DIVISION_NAMES: [&str; DIVISIONS.len()] = {
+    let mut names = [""; DIVISIONS.len()];
+    let mut i = 0;
+    while i < names.len() {
+        names[i] = DIVISIONS[i].0;
+        i += 1;
+    }
+    names
+};
+
+/// A note length: straight, dotted (`d`, x1.5) or triplet (`t`, x2/3).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyncDivision(u8);
+impl Default for SyncDivision {
+    fn default() -> Self {
+        Self::QUARTER
+    }
+}
+impl SyncDivision {
+    pub const COUNT: usize = DIVISIONS.len();
+    pub const QUARTER: Self = Self(8);
+
+    pub fn from_f32(v: f32) -> Self {
+        Self((v.round().max(0.0) as usize).min(Self::COUNT - 1) as u8)
+    }
+    pub fn to_index(self) -> u8 {
+        self.0
+    }
+    /// Names such as `1/4`, `1/8d` or `1/16t`.
+    pub fn name(self) -> &'static str {
+        DIVISIONS[self.0 as usize].0
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        DIVISIONS.iter().position(|(n, _)| *n == name).map(|i| Self(i as u8))
+    }
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..Self::COUNT as u8).map(Self)
+    }
+
+    pub fn beats(self) -> f32 {
+        DIVISIONS[self.0 as usize].1
+    }
+    pub fn seconds(self, bpm: f32) -> f32 {
+        self.beats() * 60.0 / bpm
+    }
+    /// Cycles per second when one cycle lasts this division.
+    pub fn hz(self, bpm: f32) -> f32 {
+        bpm / (60.0 * self.beats())
+    }
+}
+
+/// Host transport. While `playing`, `position` advances with the rendered audio.
+#[derive(Clone, Copy, Debug)]
+pub struct Transport {
+    pub bpm: f32,
+    pub playing: bool,
+    /// Song position in beats; f64 so long sessions stay sample-accurate.
+    pub position: f64,
+}
+impl Default for Transport {
+    fn default() -> Self {
+        Self { bpm: DEFAULT_BPM, playing: false, position: 0.0 }
+    }
+}
+impl Transport {
+    #[inline]
+    pub fn advance(&mut self, dt: f32) {
+        if self.playing {
+            self.position += dt as f64 * self.bpm as f64 / 60.0;
+        }
+    }
+}
@@ -0,0 +1,273 @@
+// src/tuning.rs
+// Microtuning: a 128-entry key -> Hz table built from Scala .scl/.kbm text or
+// edited by MIDI Tuning Standard SysEx. A key mapped to 0 Hz is silent.
+use std::fmt;
+
+/// A4 in the default tuning.
+pub const DEFAULT_REFERENCE: f32 = 440.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TuningError {
+    /// `line` is 1-based.
+    BadLine { line: usize, text: String },
+    /// Fewer pitch / mapping lines than the file declares.
+    Truncated { expected: usize, found: usize },
+    EmptyScale,
+    /// A key-map setting out of range (e.g. first note > last note).
+    BadKeymap(String),
+}
+impl TuningError {
+    /// Stable machine-readable code for the JS side.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BadLine { .. } => "bad_line",
+            Self::Truncated { .. } => "truncated",
+            Self::EmptyScale => "empty_scale",
+            Self::BadKeymap(_) => "bad_keymap",
+        }
+    }
+}
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadLine { line, text } => write!(f, "line {line}: cannot parse {text:?}"),
+            Self::Truncated { expected, found } => write!(f, "expected {expected} entries, found {found}"),
+            Self::EmptyScale => write!(f, "scale has no notes"),
+            Self::BadKeymap(s) => write!(f, "bad keyboard mapping: {s}"),
+        }
+    }
+}
+impl std::error::Error for TuningError {}
+
+/// Non-comment lines of a Scala file with their 1-based line numbers. A comment is
+/// any line whose first non-blank character is `!`.
+fn scala_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines().enumerate().filter(|(_, l)| !l.trim_start().starts_with('!')).map(|(i, l)| (i + 1, l.trim()))
+}
+
+fn bad(line: usize, text: &str) -> TuningError {
+    TuningError::BadLine { line, text: text.to_string() }
+}
+
+/// A parsed .scl: the degrees above the tonic in cents, the last being the period.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scale {
+    pub description: String,
+    pub cents: Vec<f32>,
+}
+impl Scale {
+    pub fn equal(steps: usize) -> Self {
+        Self { description: format!("{steps}-TET"), cents: (1..=steps).map(|i| i as f32 * 1200.0 / steps as f32).collect() }
+    }
+
+    /// Pitches are cents if they contain a `.`, otherwise a ratio `n/d` or integer `n`.
+    /// Anything after the first whitespace on a pitch line is a label.
+    pub fn parse(text: &str) -> Result<Self, TuningError> {
+        let mut lines = scala_lines(text);
+        let description = lines.next().map(|(_, l)| l.to_string()).unwrap_or_default();
+        let (n_line, n_text) = lines.next().ok_or(TuningError::EmptyScale)?;
+        let count: usize = n_text.split_whitespace().next().and_then(|n| n.parse().ok()).ok_or_else(|| bad(n_line, n_text))?;
+        if count == 0 {
+            return Err(TuningError::EmptyScale);
+        }
+        let mut cents = Vec::with_capacity(count);
+        for (line, l) in lines.take(count) {
+            let pitch = l.split_whitespace().next().unwrap_or("");
+            let value = if pitch.contains('.') {
+                pitch.parse::<f32>().ok()
+            } else {
+                let (n, d) = pitch.split_once('/').unwrap_or((pitch, "1"));
+                match (n.parse::<f64>(), d.parse::<f64>()) {
+                    (Ok(n), Ok(d)) if n > 0.0 && d > 0.0 => Some((1200.0 * (n / d).log2()) as f32),
+                    _ => None,
+                }
+            };
+            cents.push(value.filter(|c| c.is_finite()).ok_or_else(|| bad(line, l))?);
+        }
+        if cents.len() < count {
+            return Err(TuningError::Truncated { expected: count, found: cents.len() });
+        }
+        Ok(Self { description, cents })
+    }
+
+    /// Cents of scale degree `degree` (any integer) above the tonic.
+    pub fn degree_cents(&self, degree: i32) -> f32 {
+        let n = self.cents.len() as i32;
+        let (octave, step) = (degree.div_euclid(n), degree.rem_euclid(n));
+        let period = self.cents[n as usize - 1];
+        octave as f32 * period + if step == 0 { 0.0 } else { self.cents[step as usize - 1] }
+    }
+}
+
+/// A parsed .kbm keyboard mapping.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    pub first: u8,
+    pub last: u8,
+    /// Key on which scale degree 0 sits.
+    pub middle: u8,
+    pub reference_key: u8,
+    pub reference_freq: f32,
+    /// Degree that one repeat of the map spans (0 means the scale's size).
+    pub octave_degree: u32,
+    /// Degree per key in one repeat, `None` for unmapped keys; empty means linear.
+    pub map: Vec<Option<u32>>,
+}
+impl Default for Keymap {
+    /// Linear, degree 0 on middle C, A4 = 440 Hz.
+    fn default() -> Self {
+        Self {
+            first: 0,
+            last: 127,
+            middle: 60,
+            reference_key: 69,
+            reference_freq: DEFAULT_REFERENCE,
+            octave_degree: 0,
+            map: Vec::new(),
+        }
+    }
+}
+impl Keymap {
+    pub fn parse(text: &str) -> Result<Self, TuningError> {
+        let lines: Vec<(usize, &str)> = scala_lines(text).filter(|(_, l)| !l.is_empty()).collect();
+        if lines.len() < 7 {
+            return Err(TuningError::Truncated { expected: 7, found: lines.len() });
+        }
+        let field = |i: usize| lines[i].1.split_whitespace().next().unwrap_or("");
+        let int = |i: usize| field(i).parse::<u32>().map_err(|_| bad(lines[i].0, lines[i].1));
+        let key = |i: usize| int(i).and_then(|k| u8::try_from(k).ok().filter(|&k| k < 128).ok_or_else(|| bad(lines[i].0, lines[i].1)));
+
+        let size = int(0)? as usize;
+        let km = Self {
+            first: key(1)?,
+            last: key(2)?,
+            middle: key(3)?,
+            reference_key: key(4)?,
+            reference_freq: field(5).parse::<f32>().ok().filter(|f| *f > 0.0).ok_or_else(|| bad(lines[5].0, lines[5].1))?,
+            octave_degree: int(6)?,
+            map: (0..size)
+                .map(|i| match lines.get(7 + i) {
+                    None => Err(TuningError::Truncated { expected: size, found: lines.len() - 7 }),
+                    Some(_) if field(7 + i) == "x" => Ok(None),
+                    Some(_) => int(7 + i).map(Some),
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        if km.first > km.last {
+            return Err(TuningError::BadKeymap(format!("first note {} is above last note {}", km.first, km.last)));
+        }
+        Ok(km)
+    }
+
+    /// Scale degree of `key`; `None` if unmapped.
+    fn degree(&self, key: u8, scale_len: usize) -> Option<i32> {
+        let offset = key as i32 - self.middle as i32;
+        if self.map.is_empty() {
+            return Some(offset);
+        }
+        let size = self.map.len() as i32;
+        let span = if self.octave_degree == 0 { scale_len as i32 } else { self.octave_degree as i32 };
+        let deg = self.map[offset.rem_euclid(size) as usize]?;
+        Some(offset.div_euclid(size) * span + deg as i32)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tuning {
+    table: [f32; 128],
+    /// Hz of the reference key; `set_reference_pitch` rescales against it.
+    reference: f32,
+}
+impl Default for Tuning {
+    /// 12-TET, A4 = 440 Hz.
+    fn default() -> Self {
+        Self::from_scala(&Scale::equal(12), &Keymap::default())
+    }
+}
+impl Tuning {
+    pub fn from_scala(scale: &Scale, keymap: &Keymap) -> Self {
+        let n = scale.cents.len();
+        // an unmapped reference key still fixes the pitch its linear degree would have
+        let reference_degree = keymap
+            .degree(keymap.reference_key, n)
+            .unwrap_or(keymap.reference_key as i32 - keymap.middle as i32);
+        let reference_cents = scale.degree_cents(reference_degree);
+        let mut table = [0.0; 128];
+        for (key, hz) in table.iter_mut().enumerate() {
+            let key = key as u8;
+            if key < keymap.first || key > keymap.last {
+                continue;
+            }
+            if let Some(deg) = keymap.degree(key, n) {
+                *hz = keymap.reference_freq * 2f32.powf((scale.degree_cents(deg) - reference_cents) / 1200.0);
+            }
+        }
+        Self { table, reference: keymap.reference_freq }
+    }
+
+    /// Frequency of `key`; 0 for unmapped keys.
+    #[inline]
+    pub fn freq(&self, key: u8) -> f32 {
+        self.table[(key & 0x7F) as usize]
+    }
+
+    pub fn table(&self) -> &[f32; 128] {
+        &self.table
+    }
+
+    /// Take a whole table (e.g. from a preset) and the reference pitch it was built
+    /// with. Non-finite or negative entries become unmapped.
+    pub fn set_table(&mut self, table: &[f32], reference: f32) {
+        for (dst, &src) in self.table.iter_mut().zip(table) {
+            *dst = if src.is_finite() && src > 0.0 { src } else { 0.0 };
+        }
+        if reference.is_finite() && reference > 0.0 {
+            self.reference = reference;
+        }
+    }
+
+    pub fn reference_pitch(&self) -> f32 {
+        self.reference
+    }
+
+    /// Move the reference key (A4 unless a key map says otherwise) to `hz`, scaling every key.
+    pub fn set_reference_pitch(&mut self, hz: f32) {
+        let hz = hz.clamp(200.0, 1000.0);
+        let ratio = hz / self.reference;
+        for f in &mut self.table {
+            *f *= ratio;
+        }
+        self.reference = hz;
+    }
+
+    /// Apply a MIDI Tuning Standard message (the bytes between F0 and F7): bulk
+    /// dump (`7E dev 08 01`), single-note change (`7F dev 08 02`) or single-note
+    /// change with bank (`7E/7F dev 08 07`). True if it was one of those.
+    pub fn apply_mts(&mut self, msg: &[u8]) -> bool {
+        match msg {
+            [0x7E, _, 0x08, 0x01, _program, rest @ ..] if rest.len() >= 16 + 128 * 3 => {
+                for (key, xyz) in rest[16..16 + 128 * 3].chunks_exact(3).enumerate() {
+                    self.set_mts_key(key as u8, xyz);
+                }
+                true
+            }
+            [0x7F, _, 0x08, 0x02, _program, count, rest @ ..] | [0x7E | 0x7F, _, 0x08, 0x07, _, _program, count, rest @ ..] => {
+                for change in rest.chunks_exact(4).take(*count as usize) {
+                    self.set_mts_key(change[0], &change[1..]);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// `xyz`: semitone, then the fraction of a semitone as 14 bits; `7F 7F 7F` is "no change".
+    fn set_mts_key(&mut self, key: u8, xyz: &[u8]) {
+        if key > 127 || xyz == [0x7F, 0x7F, 0x7F] {
+            return;
+        }
+        let frac = ((xyz[1] as u32) << 7 | xyz[2] as u32) as f32 / 16384.0;
+        let semis = xyz[0] as f32 + frac;
+        self.table[key as usize] = DEFAULT_REFERENCE * 2f32.powf((semis - 69.0) / 12.0);
+    }
+}
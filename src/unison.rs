@@ -0,0 +1,151 @@
+// src/unison.rs
+// Per-oscillator unison: detune/pan/gain of each stacked voice, cached by the engine
+// and recomputed from `OscSettings` only when they change, so voices only read tables.
+use crate::util::pan_gains;
+use crate::voice::OscSettings;
+
+pub const MAX_UNISON: usize = 16;
+
+/// How detune offsets are spread between the centre and the outermost voices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnisonCurve {
+    /// Evenly spaced.
+    Linear,
+    /// Inner voices cluster near the centre, outer ones fan out (supersaw-like).
+    Exponential,
+    /// Inner voices pushed outwards; a wider, flatter chorus.
+    Root,
+}
+impl UnisonCurve {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            0 => Self::Linear,
+            1 => Self::Exponential,
+            2 => Self::Root,
+            _ => Self::Linear,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Linear => 0,
+            Self::Exponential => 1,
+            Self::Root => 2,
+        }
+    }
+    #[inline]
+    fn shape(self, x: f32) -> f32 {
+        match self {
+            Self::Linear => x,
+            Self::Exponential => x * x.abs(),
+            Self::Root => x.abs().sqrt().copysign(x),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnisonSettings {
+    pub voices: usize,       // 1..=MAX_UNISON
+    pub detune_cents: f32,   // offset of the outermost voices
+    pub curve: UnisonCurve,
+    pub width: f32,          // 0..1 stereo spread
+    pub blend: f32,          // 0 = centre only, 1 = side voices only
+    pub phase_rand: f32,     // 0 = all start at phase 0, 1 = fully random
+}
+impl Default for UnisonSettings {
+    fn default() -> Self {
+        Self {
+            voices: 1,
+            detune_cents: 20.0,
+            curve: UnisonCurve::Linear,
+            width: 1.0,
+            blend: 0.5,
+            phase_rand: 1.0,
+        }
+    }
+}
+impl UnisonSettings {
+    /// `key` is the part after `oscN_`, e.g. `unison_detune`.
+    pub fn set_by_name(&mut self, key: &str, value: f32) {
+        match key {
+            "unison" | "unison_voices" => self.voices = (value.round() as usize).clamp(1, MAX_UNISON),
+            "unison_detune"     => self.detune_cents = value.clamp(0.0, 100.0),
+            "unison_curve"      => self.curve = UnisonCurve::from_f32(value),
+            "unison_width"      => self.width = value.clamp(0.0, 1.0),
+            "unison_blend"      => self.blend = value.clamp(0.0, 1.0),
+            "unison_phase_rand" => self.phase_rand = value.clamp(0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    /// Every setting under its `set_by_name` key.
+    pub fn named_values(&self) -> [(&'static str, f32); 6] {
+        [
+            ("unison", self.voices as f32),
+            ("unison_detune", self.detune_cents),
+            ("unison_curve", self.curve.to_index() as f32),
+            ("unison_width", self.width),
+            ("unison_blend", self.blend),
+            ("unison_phase_rand", self.phase_rand),
+        ]
+    }
+}
+
+/// Frequency ratio and stereo gains of each unison voice (oscillator pan folded in;
+/// the oscillator gain is applied by the voice so it can be modulated).
+#[derive(Clone, Copy)]
+pub struct UnisonLayout {
+    pub count: usize,
+    pub ratio: [f32; MAX_UNISON],
+    /// Shaped -1..1 detune offset of each voice, for modulating the spread.
+    pub spread: [f32; MAX_UNISON],
+    pub gain_l: [f32; MAX_UNISON],
+    pub gain_r: [f32; MAX_UNISON],
+}
+impl UnisonLayout {
+    pub fn new(os: &OscSettings) -> Self {
+        let u = &os.unison;
+        let n = u.voices.clamp(1, MAX_UNISON);
+        let base = 2f32.powf((os.coarse * 100.0 + os.detune_cents) / 1200.0);
+        let mut layout = Self {
+            count: n,
+            ratio: [base; MAX_UNISON],
+            spread: [0.0; MAX_UNISON],
+            gain_l: [0.0; MAX_UNISON],
+            gain_r: [0.0; MAX_UNISON],
+        };
+        if n == 1 {
+            let (gl, gr) = pan_gains(os.pan);
+            layout.gain_l[0] = gl;
+            layout.gain_r[0] = gr;
+            return layout;
+        }
+
+        // voices sit at x in -1..1; the innermost one (or pair) counts as the centre
+        let inner = 1.0 / (n - 1) as f32 + 1e-4;
+        let mut weights = [0.0f32; MAX_UNISON];
+        let mut power = 0.0;
+        for (k, w) in weights.iter_mut().enumerate().take(n) {
+            let x = 2.0 * k as f32 / (n - 1) as f32 - 1.0;
+            *w = if n <= 2 {
+                1.0
+            } else if x.abs() <= inner {
+                1.0 - u.blend
+            } else {
+                u.blend
+            };
+            power += *w * *w;
+            layout.spread[k] = u.curve.shape(x);
+            layout.ratio[k] = base * 2f32.powf(layout.spread[k] * u.detune_cents / 1200.0);
+            let (gl, gr) = pan_gains(os.pan + x * u.width);
+            layout.gain_l[k] = gl;
+            layout.gain_r[k] = gr;
+        }
+        // uncorrelated voices sum by power: keep the stack roughly as loud as one voice
+        let norm = if power > 0.0 { 1.0 / power.sqrt() } else { 0.0 };
+        for ((gl, gr), w) in layout.gain_l.iter_mut().zip(layout.gain_r.iter_mut()).zip(weights) {
+            *gl *= w * norm;
+            *gr *= w * norm;
+        }
+        layout
+    }
+}
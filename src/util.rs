@@ -0,0 +1,21 @@
+// src/util.rs
+
+pub fn midi_to_freq(n: u8) -> f32 {
+    440.0 * 2f32.powf((n as f32 - 69.0) / 12.0)
+}
+
+// gentle, musical soft saturation
+#[inline]
+pub fn soft_clip(x: f32) -> f32 {
+    // smooth, monotonic; keeps transients while taming peaks
+    let a = 0.5;
+    let x1 = x.clamp(-2.0, 2.0);
+    x1 * (1.0 + a) / (1.0 + a * x1.abs())
+}
+
+/// Balance-law pan (-1..1): centre is unity on both sides, hard pan mutes the far side.
+#[inline]
+pub fn pan_gains(pan: f32) -> (f32, f32) {
+    let p = pan.clamp(-1.0, 1.0);
+    ((1.0 - p).min(1.0), (1.0 + p).min(1.0))
+}
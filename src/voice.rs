@@ -0,0 +1,397 @@
+// src/voice.rs
+use std::f32::consts::PI;
+
+use crate::alloc::STEAL_FADE;
+use crate::envelope::{ADSRParams, AdsrState, PerVoiceADSR};
+use crate::filter::{FilterSettings, VoiceFilter};
+use crate::lfo::{LfoState, LFO};
+use crate::modulation::{ModDest, ModMatrix, ModSource, SourceValues};
+use crate::mono::Glide;
+use crate::mpe::Expression;
+use crate::oscillator;
+use crate::rng::Rng;
+use crate::unison::{UnisonLayout, UnisonSettings, MAX_UNISON};
+use crate::util::pan_gains;
+use crate::wavetable::Wavetable;
+use crate::WAVETABLE_SIZE;
+
+#[derive(Clone, Copy)]
+pub enum Waveform {
+    Sine,
+    Saw,
+    Square,
+    Triangle,
+    Noise,
+    Wavetable,
+}
+impl Waveform {
+    pub fn from_f32(v: f32) -> Self {
+        match v.round() as i32 {
+            0 => Self::Sine,
+            1 => Self::Saw,
+            2 => Self::Square,
+            3 => Self::Triangle,
+            4 => Self::Noise,
+            5 => Self::Wavetable,
+            _ => Self::Sine,
+        }
+    }
+    pub fn to_index(self) -> u8 {
+        match self {
+            Self::Sine => 0,
+            Self::Saw => 1,
+            Self::Square => 2,
+            Self::Triangle => 3,
+            Self::Noise => 4,
+            Self::Wavetable => 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct OscSettings {
+    pub waveform: Waveform,
+    pub coarse: f32, // semitones
+    pub detune_cents: f32,
+    pub gain: f32,
+    pub pan: f32, // -1..1
+    pub wt_position: f32, // 0..1 across the wavetable's frames
+    pub unison: UnisonSettings,
+}
+impl Default for OscSettings {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Saw,
+            coarse: 0.0,
+            detune_cents: 0.0,
+            gain: 0.8,
+            pan: 0.0,
+            wt_position: 0.0,
+            unison: UnisonSettings::default(),
+        }
+    }
+}
+
+/// Interactions between the oscillators. OSC A (0) is always the modulator of
+/// OSC B (1); the amounts are 0..1 and mod-matrix offsets add to them.
+#[derive(Clone, Copy, Default)]
+pub struct CrossMod {
+    pub sync_b_to_a: bool,
+    /// OSC A restarts on OSC B's wraps (one sample late); ignored while B is synced to A.
+    pub sync_a_to_b: bool,
+    pub fm: f32,   // linear through-zero FM, 1 = +-100% of B's frequency
+    pub pm: f32,   // phase offset, 1 = +-1 cycle
+    pub am: f32,   // unipolar amplitude modulation depth
+    pub ring: f32, // crossfade to B * A
+}
+impl CrossMod {
+    pub fn set_by_name(&mut self, name: &str, value: f32) {
+        match name {
+            "osc1_sync" => self.sync_b_to_a = value > 0.5,
+            "osc0_sync" => self.sync_a_to_b = value > 0.5,
+            "osc1_fm"   => self.fm = value.clamp(0.0, 1.0),
+            "osc1_pm"   => self.pm = value.clamp(0.0, 1.0),
+            "osc1_am"   => self.am = value.clamp(0.0, 1.0),
+            "osc1_ring" => self.ring = value.clamp(0.0, 1.0),
+            _ => {}
+        }
+    }
+
+    /// Every setting under its `set_by_name` name.
+    pub fn named_values(&self) -> [(&'static str, f32); 6] {
+        [
+            ("osc0_sync", if self.sync_a_to_b { 1.0 } else { 0.0 }),
+            ("osc1_sync", if self.sync_b_to_a { 1.0 } else { 0.0 }),
+            ("osc1_fm", self.fm),
+            ("osc1_pm", self.pm),
+            ("osc1_am", self.am),
+            ("osc1_ring", self.ring),
+        ]
+    }
+}
+
+#[inline]
+fn osc_sample(waveform: Waveform, t: f32, f: f32, sr: f32, wt: &Wavetable, wt_pos: f32, rng: &mut Rng) -> f32 {
+    let pdt = (f / sr).abs().min(0.5);
+    match waveform {
+        Waveform::Sine => (t * 2.0 * PI).sin(),
+        Waveform::Saw => oscillator::saw(t, pdt),
+        Waveform::Square => oscillator::square(t, pdt),
+        Waveform::Triangle => oscillator::triangle(t, pdt),
+        Waveform::Noise => rng.range(-1.0, 1.0),
+        Waveform::Wavetable => wt.sample_at(wt_pos, t, f, sr),
+    }
+}
+
+/// Advance `ph` (in table samples) by `incr`; returns how far past a forward wrap
+/// the new phase landed, as a fraction of this sample (or `None`).
+#[inline]
+fn advance(ph: &mut f32, incr: f32, tl: f32) -> Option<f32> {
+    let next = *ph + incr;
+    if next >= tl && incr > 0.0 {
+        *ph = next % tl;
+        Some(*ph / incr)
+    } else {
+        *ph = next.rem_euclid(tl);
+        None
+    }
+}
+
+/// Everything a voice reads (but does not own) to render one sample.
+#[derive(Clone, Copy)]
+pub struct RenderCtx<'a> {
+    pub dt: f32,
+    pub sr: f32,
+    pub osc: &'a [OscSettings; 2],
+    pub unison: &'a [UnisonLayout; 2],
+    pub cross: &'a CrossMod,
+    pub wts: &'a [Wavetable; 2],
+    pub lfos: &'a [LFO; 2],
+    /// Per-LFO time step, i.e. `dt` scaled by any rate modulation.
+    pub lfo_dt: [f32; 2],
+    /// Pitch-bend frequency ratio, shared by every voice.
+    pub bend: f32,
+    /// Semitones at full per-note (MPE) bend.
+    pub note_bend_range: f32,
+    /// Key 60's frequency in the current tuning, the pivot for filter key tracking.
+    pub key_center: f32,
+    /// Global mod sources; the voice fills in its own.
+    pub globals: &'a SourceValues,
+    pub mods: &'a ModMatrix,
+    pub filt: &'a FilterSettings,
+}
+
+pub struct Voice {
+    /// Note-on order, for oldest-first stealing.
+    pub serial: u64,
+    pub midi_note: u8,
+    pub freq: f32,
+    pub vel: f32,
+    pub pan: f32, // -1..1, per-note position
+    /// Key still held; a released key's voice may ring on under the pedals.
+    pub key_down: bool,
+    /// Latched by the sostenuto pedal while the key was down.
+    pub sostenuto: bool,
+    /// MIDI channel the note came in on (0 for notes not from MIDI).
+    pub channel: u8,
+    /// Per-note bend/pressure/timbre from MPE (pressure also from poly aftertouch).
+    pub expr: Expression,
+    /// The `random` mod source, drawn once at note-on.
+    pub random: f32,
+    /// Per oscillator, per unison voice; in wavetable samples.
+    pub phases: [[f32; MAX_UNISON]; 2],
+    /// OSC B wrap offsets from the previous sample, for syncing A to B.
+    b_wraps: [Option<f32>; MAX_UNISON],
+    pub env: PerVoiceADSR,
+    /// Mod envelope 1 (`env1`), also the filter envelope.
+    pub filter_env: PerVoiceADSR,
+    /// Mod envelope 2 (`env2`).
+    pub mod_env: PerVoiceADSR,
+    /// Own LFO state, only advanced for LFOs in per-voice mode.
+    pub lfos: [LfoState; 2],
+    /// Portamento towards `freq`, for mono/legato.
+    pub glide: Option<Glide>,
+    /// Declick gain while being stolen, ramping 1 -> 0.
+    steal_gain: Option<f32>,
+    filter: VoiceFilter,
+    rng: Rng,
+}
+impl Voice {
+    /// `envs` are amp, filter (mod 1) and mod 2 envelope settings; `filter` comes
+    /// from the engine's pool, already restarted.
+    pub fn new(m: u8, f: f32, vel: f32, envs: [&ADSRParams; 3], filter: VoiceFilter, rng: Rng) -> Self {
+        Self {
+            serial: 0,
+            midi_note: m,
+            freq: f,
+            vel,
+            pan: 0.0,
+            key_down: true,
+            sostenuto: false,
+            channel: 0,
+            expr: Expression::default(),
+            random: 0.0,
+            phases: [[0.0; MAX_UNISON]; 2],
+            b_wraps: [None; MAX_UNISON],
+            env: PerVoiceADSR::new(envs[0]),
+            filter_env: PerVoiceADSR::new(envs[1]),
+            mod_env: PerVoiceADSR::new(envs[2]),
+            lfos: [LfoState::new(0.0, 0); 2],
+            glide: None,
+            steal_gain: None,
+            filter,
+            rng,
+        }
+    }
+
+    /// Hand the filter (and its buffers) back for the next voice.
+    pub fn into_filter(self) -> VoiceFilter {
+        self.filter
+    }
+
+    pub fn note_off(&mut self) {
+        self.env.note_off();
+        self.filter_env.note_off();
+        self.mod_env.note_off();
+    }
+
+    /// Retarget a legato voice without retriggering anything.
+    pub fn set_note(&mut self, midi_note: u8, freq: f32) {
+        self.midi_note = midi_note;
+        self.freq = freq;
+    }
+
+    /// Frequency right now, including any glide still under way.
+    pub fn current_freq(&self) -> f32 {
+        self.glide.map_or(self.freq, |g| self.freq * 2f32.powf(g.offset / 12.0))
+    }
+
+    /// Fade out over `STEAL_FADE` and finish, regardless of the envelopes.
+    pub fn steal(&mut self) {
+        self.key_down = false;
+        self.steal_gain.get_or_insert(1.0);
+    }
+
+    pub fn is_stolen(&self) -> bool {
+        self.steal_gain.is_some()
+    }
+
+    pub fn render(&mut self, ctx: &RenderCtx) -> (f32, f32) {
+        let RenderCtx { dt, sr, osc, unison, cross, wts, lfos, lfo_dt, bend, note_bend_range, key_center, globals, mods, filt } = *ctx;
+        let env = self.env.tick(dt);
+        let fenv = self.filter_env.tick(dt);
+        let menv = self.mod_env.tick(dt);
+
+        // global sources come in filled; add this voice's own
+        let mut src = *globals;
+        for (i, (lfo, st)) in lfos.iter().zip(&mut self.lfos).enumerate() {
+            if lfo.per_voice {
+                lfo.advance(st, lfo_dt[i]);
+                src[ModSource::Lfo0 as usize + i] = lfo.value_of(st);
+            }
+        }
+        src[ModSource::Env0 as usize] = env;
+        src[ModSource::Env1 as usize] = if filt.env_enabled { fenv } else { 0.0 };
+        src[ModSource::Env2 as usize] = menv;
+        src[ModSource::Velocity as usize] = self.vel;
+        src[ModSource::Key as usize] = self.midi_note as f32 / 127.0;
+        src[ModSource::Random as usize] = self.random;
+        src[ModSource::PolyAftertouch as usize] = self.expr.pressure;
+        src[ModSource::NoteBend as usize] = self.expr.bend;
+        src[ModSource::Timbre as usize] = self.expr.timbre;
+        let d = mods.evaluate(&src, false);
+        let m = |dest: ModDest| d[dest.index()];
+
+        let tl = WAVETABLE_SIZE as f32;
+        let pitch = |dest: ModDest| if m(dest) != 0.0 { 2f32.powf(m(dest) / 12.0) } else { 1.0 };
+        let mut base = self.current_freq() * bend;
+        if let Some(g) = &mut self.glide {
+            if !g.tick(dt) {
+                self.glide = None;
+            }
+        }
+        if self.expr.bend != 0.0 {
+            base *= 2f32.powf(self.expr.bend * note_bend_range / 12.0);
+        }
+        let freq = [base * pitch(ModDest::Osc0Pitch), base * pitch(ModDest::Osc1Pitch)];
+        let spread = [m(ModDest::Osc0UnisonDetune), m(ModDest::Osc1UnisonDetune)];
+        let ratio = |i: usize, k: usize| {
+            let r = unison[i].ratio[k];
+            if spread[i] != 0.0 { r * 2f32.powf(unison[i].spread[k] * spread[i] / 1200.0) } else { r }
+        };
+        let wt_pos = [
+            osc[0].wt_position + m(ModDest::Osc0WtPosition),
+            osc[1].wt_position + m(ModDest::Osc1WtPosition),
+        ];
+        let fm = (cross.fm + m(ModDest::Fm)).clamp(0.0, 1.0);
+        let pm = (cross.pm + m(ModDest::Pm)).clamp(0.0, 1.0);
+        let am = (cross.am + m(ModDest::Am)).clamp(0.0, 1.0);
+        let ring = (cross.ring + m(ModDest::Ring)).clamp(0.0, 1.0);
+        let sync_a = cross.sync_a_to_b && !cross.sync_b_to_a;
+
+        // OSC A; unison voice k of B follows voice k (mod count) of A
+        let (ua, ub) = (&unison[0], &unison[1]);
+        let mut a_out = [0.0f32; MAX_UNISON];
+        let mut a_wraps = [None; MAX_UNISON];
+        let (mut a_l, mut a_r) = (0.0f32, 0.0f32);
+        for k in 0..ua.count {
+            let f = freq[0] * ratio(0, k);
+            let incr = f * (tl / sr);
+            let ph = &mut self.phases[0][k];
+            a_wraps[k] = advance(ph, incr, tl);
+            if sync_a {
+                // naive (sub-sample placed) reset; no BLEP on the discontinuity.
+                // B wrapped during the previous sample, so A is already `frac + 1` samples in.
+                if let Some(frac) = self.b_wraps[k % ub.count] {
+                    *ph = ((frac + 1.0) * incr) % tl;
+                }
+            }
+            let sample = osc_sample(osc[0].waveform, *ph / tl, f, sr, &wts[0], wt_pos[0], &mut self.rng);
+            a_out[k] = sample;
+            a_l += sample * ua.gain_l[k];
+            a_r += sample * ua.gain_r[k];
+        }
+
+        // OSC B, modulated by A
+        let (mut b_l, mut b_r) = (0.0f32, 0.0f32);
+        for k in 0..ub.count {
+            let a = a_out[k % ua.count];
+            let f = freq[1] * ratio(1, k) * (1.0 + fm * a);
+            let incr = f * (tl / sr);
+            let ph = &mut self.phases[1][k];
+            self.b_wraps[k] = advance(ph, incr, tl);
+            if cross.sync_b_to_a {
+                // naive (sub-sample placed) reset, as above
+                if let Some(frac) = a_wraps[k % ua.count] {
+                    *ph = frac * incr.abs();
+                }
+            }
+            let t = *ph / tl + pm * a;
+            let t = t - t.floor();
+            let mut sample = osc_sample(osc[1].waveform, t, f, sr, &wts[1], wt_pos[1], &mut self.rng);
+            sample *= 1.0 - am * 0.5 * (1.0 - a);
+            sample += (sample * a - sample) * ring;
+            b_l += sample * ub.gain_l[k];
+            b_r += sample * ub.gain_r[k];
+        }
+
+        // oscillator level, then any modulated pan on top of the unison spread
+        let mut out_l = 0.0f32;
+        let mut out_r = 0.0f32;
+        for ((l, r), (gain, pan)) in [(a_l, a_r), (b_l, b_r)].into_iter().zip([
+            (osc[0].gain + m(ModDest::Osc0Gain), m(ModDest::Osc0Pan)),
+            (osc[1].gain + m(ModDest::Osc1Gain), m(ModDest::Osc1Pan)),
+        ]) {
+            let g = gain.max(0.0);
+            let (pl, pr) = if pan != 0.0 { pan_gains(pan) } else { (1.0, 1.0) };
+            out_l += l * g * pl;
+            out_r += r * g * pr;
+        }
+
+        // per-voice filter: key tracking, the filter envelope and matrix offsets, all in octaves
+        // tracking follows the tuned pitch, so retuned keys open the filter by what they sound
+        let key = if filt.key_track != 0.0 && self.freq > 0.0 { (self.freq / key_center).log2() } else { 0.0 };
+        let octaves = filt.key_track * key
+            + src[ModSource::Env1 as usize] * filt.env_amount
+            + m(ModDest::FilterCutoff);
+        let cutoff = (filt.cutoff * 2f32.powf(octaves)).clamp(20.0, sr * 0.49);
+        let mut fs = *filt;
+        fs.resonance = (fs.resonance + m(ModDest::FilterResonance)).max(0.0);
+        fs.drive = (fs.drive + m(ModDest::FilterDrive)).clamp(0.0, 1.0);
+        fs.morph = (fs.morph + m(ModDest::FilterMorph)).clamp(0.0, 1.0);
+        self.filter.configure(&fs, cutoff);
+        let (out_l, out_r) = self.filter.process_stereo(out_l, out_r);
+
+        let mut amp = (env * (1.0 + m(ModDest::Amp))).clamp(0.0, 4.0) * self.vel;
+        if let Some(g) = &mut self.steal_gain {
+            amp *= *g;
+            *g = (*g - dt / STEAL_FADE).max(0.0);
+        }
+        let (pl, pr) = pan_gains(self.pan + m(ModDest::VoicePan));
+        (out_l * amp * pl, out_r * amp * pr)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.env.state, AdsrState::Idle) || self.steal_gain == Some(0.0)
+    }
+}
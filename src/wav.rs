@@ -0,0 +1,178 @@
+// src/wav.rs
+// Minimal RIFF/WAVE reader for Serum-style wavetables: PCM 16/24/32-bit or
+// float 32/64-bit, frame size taken from the `clm ` chunk ("<!>2048 ...").
+use std::fmt;
+
+use crate::WAVETABLE_SIZE;
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WavError {
+    NotRiff,
+    NotWave,
+    Truncated,
+    MissingFmt,
+    MissingData,
+    UnsupportedFormat(u16),
+    UnsupportedBitDepth { format: u16, bits: u16 },
+    BadClmChunk(String),
+    /// Data holds fewer samples than one frame of the declared size.
+    NoCompleteFrame { samples: usize, frame_size: usize },
+}
+impl WavError {
+    /// Stable machine-readable code for the JS side.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotRiff => "not_riff",
+            Self::NotWave => "not_wave",
+            Self::Truncated => "truncated",
+            Self::MissingFmt => "missing_fmt",
+            Self::MissingData => "missing_data",
+            Self::UnsupportedFormat(_) => "unsupported_format",
+            Self::UnsupportedBitDepth { .. } => "unsupported_bit_depth",
+            Self::BadClmChunk(_) => "bad_clm_chunk",
+            Self::NoCompleteFrame { .. } => "no_complete_frame",
+        }
+    }
+}
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotRiff => write!(f, "not a RIFF file"),
+            Self::NotWave => write!(f, "RIFF file is not WAVE"),
+            Self::Truncated => write!(f, "file is truncated"),
+            Self::MissingFmt => write!(f, "missing fmt chunk"),
+            Self::MissingData => write!(f, "missing data chunk"),
+            Self::UnsupportedFormat(tag) => write!(f, "unsupported format tag {tag:#06x}"),
+            Self::UnsupportedBitDepth { format, bits } => {
+                write!(f, "unsupported bit depth {bits} for format {format}")
+            }
+            Self::BadClmChunk(s) => write!(f, "malformed clm chunk: {s:?}"),
+            Self::NoCompleteFrame { samples, frame_size } => {
+                write!(f, "{samples} samples is less than one {frame_size}-sample frame")
+            }
+        }
+    }
+}
+impl std::error::Error for WavError {}
+
+/// Decoded first channel plus the per-frame length.
+#[derive(Debug)]
+pub struct WavWavetable {
+    pub samples: Vec<f32>,
+    pub frame_size: usize,
+    pub sample_rate: u32,
+}
+impl WavWavetable {
+    pub fn frame_count(&self) -> usize {
+        self.samples.len() / self.frame_size
+    }
+}
+
+struct Fmt {
+    format: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits: u16,
+}
+
+fn u16_le(b: &[u8], at: usize) -> Result<u16, WavError> {
+    b.get(at..at + 2)
+        .map(|s| u16::from_le_bytes([s[0], s[1]]))
+        .ok_or(WavError::Truncated)
+}
+fn u32_le(b: &[u8], at: usize) -> Result<u32, WavError> {
+    b.get(at..at + 4)
+        .map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+        .ok_or(WavError::Truncated)
+}
+
+fn parse_fmt(c: &[u8]) -> Result<Fmt, WavError> {
+    let mut format = u16_le(c, 0)?;
+    let channels = u16_le(c, 2)?;
+    let sample_rate = u32_le(c, 4)?;
+    let bits = u16_le(c, 14)?;
+    if format == WAVE_FORMAT_EXTENSIBLE {
+        // the real tag is the first two bytes of the sub-format GUID
+        format = u16_le(c, 24)?;
+    }
+    if channels == 0 {
+        return Err(WavError::Truncated);
+    }
+    Ok(Fmt { format, channels, sample_rate, bits })
+}
+
+/// "<!>2048 01000000 wavetable (www.xferrecords.com)" -> 2048
+fn parse_clm(c: &[u8]) -> Result<usize, WavError> {
+    let text: String = c.iter().take_while(|&&b| b != 0).map(|&b| b as char).collect();
+    let bad = || WavError::BadClmChunk(text.clone());
+    let rest = text.strip_prefix("<!>").ok_or_else(bad)?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    match digits.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(bad()),
+    }
+}
+
+fn decode(data: &[u8], fmt: &Fmt) -> Result<Vec<f32>, WavError> {
+    let bytes = (fmt.bits / 8) as usize;
+    let stride = bytes * fmt.channels as usize;
+    let unsupported = WavError::UnsupportedBitDepth { format: fmt.format, bits: fmt.bits };
+    let read: fn(&[u8]) -> f32 = match (fmt.format, fmt.bits) {
+        (WAVE_FORMAT_PCM, 16) => |s| i16::from_le_bytes([s[0], s[1]]) as f32 / 32_768.0,
+        (WAVE_FORMAT_PCM, 24) => {
+            |s| (i32::from_le_bytes([0, s[0], s[1], s[2]]) >> 8) as f32 / 8_388_608.0
+        }
+        (WAVE_FORMAT_PCM, 32) => {
+            |s| i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2_147_483_648.0
+        }
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => |s| f32::from_le_bytes([s[0], s[1], s[2], s[3]]),
+        (WAVE_FORMAT_IEEE_FLOAT, 64) => {
+            |s| f64::from_le_bytes([s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7]]) as f32
+        }
+        (WAVE_FORMAT_PCM, _) | (WAVE_FORMAT_IEEE_FLOAT, _) => return Err(unsupported),
+        (other, _) => return Err(WavError::UnsupportedFormat(other)),
+    };
+    // first channel only
+    Ok(data.chunks_exact(stride).map(|frame| read(&frame[..bytes])).collect())
+}
+
+pub fn parse_wavetable(bytes: &[u8]) -> Result<WavWavetable, WavError> {
+    if bytes.get(0..4) != Some(b"RIFF") {
+        return Err(WavError::NotRiff);
+    }
+    if bytes.get(8..12) != Some(b"WAVE") {
+        return Err(if bytes.len() < 12 { WavError::Truncated } else { WavError::NotWave });
+    }
+
+    let mut fmt = None;
+    let mut data = None;
+    let mut frame_size = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let len = u32_le(bytes, pos + 4)? as usize;
+        let body = pos + 8;
+        let chunk = bytes.get(body..body + len).ok_or(WavError::Truncated)?;
+        match id {
+            b"fmt " => fmt = Some(parse_fmt(chunk)?),
+            b"data" => data = Some(chunk),
+            b"clm " => frame_size = Some(parse_clm(chunk)?),
+            _ => {}
+        }
+        // chunks are word aligned
+        pos = body + len + (len & 1);
+    }
+
+    let fmt = fmt.ok_or(WavError::MissingFmt)?;
+    let data = data.ok_or(WavError::MissingData)?;
+    let samples = decode(data, &fmt)?;
+    let frame_size = frame_size.unwrap_or(WAVETABLE_SIZE);
+    if samples.len() < frame_size {
+        return Err(WavError::NoCompleteFrame { samples: samples.len(), frame_size });
+    }
+    Ok(WavWavetable { samples, frame_size, sample_rate: fmt.sample_rate })
+}
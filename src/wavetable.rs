@@ -0,0 +1,154 @@
+// src/wavetable.rs
+// Multi-frame wavetable; every frame carries per-octave band-limited mip levels.
+use crate::fft::fft;
+use crate::WAVETABLE_SIZE;
+
+/// Level k keeps harmonics 1..=(WAVETABLE_SIZE/2 >> k); the last level is a pure fundamental.
+pub const MIP_LEVELS: usize = 11;
+/// Most frames one oscillator can hold (Serum-style tables top out at 256).
+pub const MAX_WT_FRAMES: usize = 256;
+/// Upper levels have few harmonics, so they are stored shorter (but never below this).
+const MIN_LEVEL_LEN: usize = 256;
+
+struct Frame {
+    raw: Vec<f32>,
+    mips: Vec<Vec<f32>>,
+}
+
+pub struct Wavetable {
+    frames: Vec<Frame>,
+}
+impl Wavetable {
+    /// `raw` must be one cycle of `WAVETABLE_SIZE` samples.
+    pub fn new(raw: Vec<f32>) -> Self {
+        Self::from_frames(vec![raw])
+    }
+
+    /// The single sine cycle every oscillator starts with.
+    pub fn sine() -> Self {
+        let raw = (0..WAVETABLE_SIZE)
+            .map(|i| (2.0 * std::f32::consts::PI * (i as f32) / WAVETABLE_SIZE as f32).sin())
+            .collect();
+        Self::new(raw)
+    }
+
+    /// Each frame must be `WAVETABLE_SIZE` samples; at most `MAX_WT_FRAMES` are kept.
+    pub fn from_frames(frames: Vec<Vec<f32>>) -> Self {
+        let frames: Vec<Frame> = frames
+            .into_iter()
+            .take(MAX_WT_FRAMES)
+            .map(|raw| {
+                debug_assert_eq!(raw.len(), WAVETABLE_SIZE);
+                let mips = build_mips(&raw);
+                Frame { raw, mips }
+            })
+            .collect();
+        debug_assert!(!frames.is_empty());
+        Self { frames }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Raw (un-band-limited) samples of one frame.
+    pub fn raw_frame(&self, frame: usize) -> Option<&[f32]> {
+        self.frames.get(frame).map(|f| f.raw.as_slice())
+    }
+
+    /// First frame; the single-cycle view used by `get_wavetable`.
+    pub fn raw(&self) -> &[f32] {
+        &self.frames[0].raw
+    }
+
+    /// Pick the richest level whose top harmonic stays below Nyquist at `freq`.
+    #[inline]
+    pub fn level_for(freq: f32, sr: f32) -> usize {
+        let max_harm = (0.5 * sr / freq.abs().max(1e-3)) as usize;
+        let mut level = 0;
+        while level + 1 < MIP_LEVELS && ((WAVETABLE_SIZE / 2) >> level) > max_harm {
+            level += 1;
+        }
+        level
+    }
+
+    /// `phase` in cycles (0..1); reads the first frame.
+    #[inline]
+    pub fn sample(&self, phase: f32, freq: f32, sr: f32) -> f32 {
+        self.sample_at(0.0, phase, freq, sr)
+    }
+
+    /// `position` 0..1 scans the frames, crossfading neighbours.
+    #[inline]
+    pub fn sample_at(&self, position: f32, phase: f32, freq: f32, sr: f32) -> f32 {
+        let level = Self::level_for(freq, sr);
+        let last = self.frames.len() - 1;
+        if last == 0 {
+            return read_linear(&self.frames[0].mips[level], phase);
+        }
+        let x = position.clamp(0.0, 1.0) * last as f32;
+        let f0 = (x as usize).min(last);
+        let f1 = (f0 + 1).min(last);
+        let frac = x - f0 as f32;
+        let a = read_linear(&self.frames[f0].mips[level], phase);
+        if frac <= 0.0 || f0 == f1 {
+            return a;
+        }
+        let b = read_linear(&self.frames[f1].mips[level], phase);
+        a + (b - a) * frac
+    }
+}
+
+/// Linearly resample an arbitrary-length single cycle to `WAVETABLE_SIZE`.
+pub fn resample_cycle(src: &[f32]) -> Vec<f32> {
+    let len = src.len();
+    let mut out = vec![0.0f32; WAVETABLE_SIZE];
+    if len == 0 {
+        return out;
+    }
+    for (i, o) in out.iter_mut().enumerate() {
+        let x = (i as f32) / (WAVETABLE_SIZE as f32) * (len as f32);
+        let i0 = x.floor() as usize % len;
+        let i1 = (i0 + 1) % len;
+        let frac = x - x.floor();
+        *o = src[i0] * (1.0 - frac) + src[i1] * frac;
+    }
+    out
+}
+
+#[inline]
+pub fn read_linear(table: &[f32], phase: f32) -> f32 {
+    let len = table.len();
+    let x = (phase - phase.floor()) * len as f32;
+    let i0 = x as usize % len;
+    let i1 = (i0 + 1) % len;
+    let frac = x - x.floor();
+    table[i0] * (1.0 - frac) + table[i1] * frac
+}
+
+fn build_mips(raw: &[f32]) -> Vec<Vec<f32>> {
+    let n = raw.len();
+    let mut re = raw.to_vec();
+    let mut im = vec![0.0f32; n];
+    fft(&mut re, &mut im, false);
+
+    (0..MIP_LEVELS)
+        .map(|level| {
+            let len = (n >> level).max(MIN_LEVEL_LEN);
+            // stay strictly below the level table's own Nyquist bin
+            let max_harm = ((n / 2) >> level).min(len / 2 - 1);
+            let scale = len as f32 / n as f32;
+            let mut lre = vec![0.0f32; len];
+            let mut lim = vec![0.0f32; len];
+            lre[0] = re[0] * scale;
+            for h in 1..=max_harm {
+                lre[h] = re[h] * scale;
+                lim[h] = im[h] * scale;
+                lre[len - h] = re[h] * scale;
+                lim[len - h] = -im[h] * scale;
+            }
+            fft(&mut lre, &mut lim, true);
+            lre
+        })
+        .collect()
+}
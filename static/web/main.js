@@ -9,12 +9,37 @@ const BUFFER_SIZE = 1024;
 // ---------- tiny no-op synth (UI-safe fallback)
 const noopSynth = {
   render_audio: (n) => new Float32Array(n),
+  render_audio_stereo: (n) => new Float32Array(n * 2),
   set_parameter: () => {},
+  set_parameter_normalized: () => {},
+  get_parameter: () => 0,
+  format_parameter: () => "",
+  list_parameters: () => [],
+  set_tempo: () => {},
+  set_transport: () => {},
   set_wavetable: () => {},
+  load_wavetable_wav: () => 0,
+  set_lfo_points: () => 0,
+  get_lfo_points: () => new Float32Array(0),
+  get_wavetable: () => null,
   note_on: () => {},
   note_off: () => {},
+  process_midi: () => {},
+  load_scala: () => {},
+  set_reference_pitch: () => {},
+  reset_tuning: () => {},
+  schedule_note_on: () => {},
+  schedule_note_off: () => {},
+  schedule_parameter: () => {},
+  schedule_midi: () => {},
+  clear_scheduled: () => {},
   export_preset: () => "{}",
-  import_preset: () => true,
+  import_preset: () => {},
+  add_mod_slot: () => 0,
+  remove_mod_slot: () => false,
+  set_mod_slot_amount: () => {},
+  list_mod_slots: () => [],
+  list_mod_targets: () => ({ sources: [], destinations: [] }),
 };
 
 let audioCtx = null;
@@ -61,6 +86,12 @@ function setWasmState(state, msg) {
 
 const clamp = (v, a, b) => Math.max(a, Math.min(b, v));
 
+// tempo sync: while on, the rate/time knobs pick a note division (index order matches the engine)
+const SYNC_DIVISIONS = ['8/1','4/1','2/1','1/1','1/2d','1/2','1/2t','1/4d','1/4','1/4t',
+                        '1/8d','1/8','1/8t','1/16d','1/16','1/16t','1/32d','1/32','1/32t','1/64'];
+const syncState = { lfo: false, delay: false };
+const divisionFromKnob = (abs) => Math.round(abs * (SYNC_DIVISIONS.length - 1));
+
 // ---------- Analyser/recorder chain (FX hard-bypassed here; real FX happen in WASM)
 function createEffectsChain(ctx) {
   const input = ctx.createGain();
@@ -152,14 +183,22 @@ export async function startSynth() {
 
   synth = (wasmOk && SynthesizerCtor) ? new SynthesizerCtor(audioCtx.sampleRate) : noopSynth;
 
+  window.refreshModSlots?.();
+
   fxNodes = createEffectsChain(audioCtx);
 
-  // connect script processor
-  scriptNode = audioCtx.createScriptProcessor(BUFFER_SIZE, 0, 1);
+  // connect script processor (stereo; engine returns planar L then R)
+  scriptNode = audioCtx.createScriptProcessor(BUFFER_SIZE, 0, 2);
   scriptNode.onaudioprocess = (evt) => {
-    const out = evt.outputBuffer.getChannelData(0);
-    try { out.set(synth.render_audio(out.length)); }
-    catch (err) { out.fill(0); }
+    const outL = evt.outputBuffer.getChannelData(0);
+    const outR = evt.outputBuffer.getChannelData(1);
+    const n = outL.length;
+    try {
+      const buf = synth.render_audio_stereo(n);
+      outL.set(buf.subarray(0, n));
+      outR.set(buf.subarray(n, 2 * n));
+    }
+    catch (err) { outL.fill(0); outR.fill(0); }
   };
 
   scriptNode.connect(fxNodes.inputNode);
@@ -188,6 +227,8 @@ window.addEventListener("DOMContentLoaded", async () => {
   wireModMatrix();
   wireKeyboard();
   setupQwertyKeys();        // computer keyboard input (Z/S/X/D/…)
+  setupWebMidi();           // hardware controllers, raw bytes straight to the engine
+  wireTuning();
   wirePresets();
   setupWavetableEditor();
   setupSpectrum();
@@ -220,7 +261,7 @@ function wireLfoButtons() {
       parent?.querySelectorAll('.lfo-waveform-button').forEach(b=>b.classList.remove('active'));
       btn.classList.add('active');
       const wf = (btn.getAttribute('data-lfo-waveform')||'sine').toLowerCase();
-      const map = { sine:0, triangle:1, saw:2, square:3, random:4 };
+      const map = { sine:0, triangle:1, saw:2, square:3, random:4, smooth:5, curve:6 };
       const idx = map[wf] ?? 0;
       try { synth.set_parameter?.('lfo0_waveform', idx); } catch {}
     });
@@ -236,12 +277,27 @@ function wireLfoButtons() {
 }
 
 function wireFilterButtons() {
+  $$('[data-filter-model]').forEach(btn=>{
+    btn.addEventListener('click', ()=>{
+      const parent = btn.closest('.waveform-display') || btn.parentElement;
+      parent?.querySelectorAll('[data-filter-model]').forEach(b=>b.classList.remove('active'));
+      btn.classList.add('active');
+      const model = (btn.getAttribute('data-filter-model')||'svf').toLowerCase();
+      const map = { svf:0, ladder:1, diode:2, comb:3 };
+      const idx = map[model] ?? 0;
+      try { synth.set_parameter?.('filter_model', idx); } catch {}
+    });
+  });
+
   $$('[data-filter]').forEach(btn=>{
     btn.addEventListener('click', ()=>{
       const parent = btn.closest('.waveform-display') || btn.parentElement;
       parent?.querySelectorAll('[data-filter]').forEach(b=>b.classList.remove('active'));
       btn.classList.add('active');
-      // Map to engine param here if supported: synth.set_parameter('filter_type', id)
+      const ft = (btn.getAttribute('data-filter')||'lowpass').toLowerCase();
+      const map = { lowpass:0, highpass:1, bandpass:2, notch:3, peak:4, allpass:5, morph:6 };
+      const idx = map[ft] ?? 0;
+      try { synth.set_parameter?.('filter_type', idx); } catch {}
     });
   });
 }
@@ -299,6 +355,31 @@ function wireKnobs() {
         } else if (param === 'volume' || param === 'gain') {
           show(abs.toFixed(2));
           try { synth.set_parameter?.(`osc${oi}_gain`, abs); } catch {}
+        } else if (param === 'coarse') {
+          const st = Math.round(abs * 96 - 48);
+          show(st > 0 ? `+${st}` : String(st));
+          try { synth.set_parameter?.(`osc${oi}_coarse`, st); } catch {}
+        } else if (param === 'fm' || param === 'pm' || param === 'am' || param === 'ring') {
+          show(abs.toFixed(2));
+          try { synth.set_parameter?.(`osc${oi}_${param}`, abs); } catch {}
+        } else if (param === 'unison') {
+          const n = Math.round(1 + abs * 15);
+          show(String(n));
+          try { synth.set_parameter?.(`osc${oi}_unison`, n); } catch {}
+        } else if (param === 'uspread') {
+          const cents = abs * 100;
+          show(cents.toFixed(1));
+          try { synth.set_parameter?.(`osc${oi}_unison_detune`, cents); } catch {}
+        } else if (param === 'ublend') {
+          show(abs.toFixed(2));
+          try { synth.set_parameter?.(`osc${oi}_unison_blend`, abs); } catch {}
+        } else if (param === 'wtpos') {
+          show(abs.toFixed(2));
+          try { synth.set_parameter?.(`osc${oi}_wt_position`, abs); } catch {}
+        } else if (param === 'pan') {
+          const p = abs * 2 - 1;
+          show(Math.abs(p) < 0.005 ? 'C' : (p < 0 ? `L${Math.round(-p * 100)}` : `R${Math.round(p * 100)}`));
+          try { synth.set_parameter?.(`osc${oi}_pan`, p); } catch {}
         }
         return;
       }
@@ -310,6 +391,13 @@ function wireKnobs() {
       } else if (param === 'resonance') {
         show(abs.toFixed(2));
         try { synth.set_parameter?.('filter_resonance', abs); } catch {}
+      } else if (param === 'drive') {
+        show(abs.toFixed(2));
+        try { synth.set_parameter?.('filter_drive', abs); } catch {}
+      } else if (param === 'filterEnvAmount') {
+        const oct = abs * 16 - 8;
+        show(`${oct >= 0 ? '+' : ''}${oct.toFixed(2)} oct`);
+        try { synth.set_parameter?.('filter_env_amount', oct); } catch {}
       } else if (param === 'attack') {
         const s = abs * 2.0; show(`${Math.round(s * 1000)}ms`);
         try { synth.set_parameter?.('env_attack', s); } catch {}
@@ -322,12 +410,24 @@ function wireKnobs() {
       } else if (param === 'release') {
         const s = abs * 2.0; show(`${Math.round(s * 1000)}ms`);
         try { synth.set_parameter?.('env_release', s); } catch {}
+      } else if (param === 'lfoRate' && syncState.lfo) {
+        const d = divisionFromKnob(abs); show(SYNC_DIVISIONS[d]);
+        try { synth.set_parameter?.('lfo0_division', d); } catch {}
       } else if (param === 'lfoRate') {
         const hz = abs * 10.0; show(`${hz.toFixed(2)} Hz`);
         try { synth.set_parameter?.('lfo0_rate', hz); } catch {}
       } else if (param === 'lfoAmount') {
         show(abs.toFixed(2));
         try { synth.set_parameter?.('lfo0_amount', abs); } catch {}
+      } else if (param === 'lfoPhase') {
+        show(abs.toFixed(2));
+        try { synth.set_parameter?.('lfo0_phase', abs); } catch {}
+      } else if (param === 'lfoFade') {
+        const s = abs * 4.0; show(`${Math.round(s * 1000)}ms`);
+        try { synth.set_parameter?.('lfo0_fade', s); } catch {}
+      } else if (param === 'delayTime' && syncState.delay) {
+        const d = divisionFromKnob(abs); show(SYNC_DIVISIONS[SYNC_DIVISIONS.length - 1 - d]);
+        try { synth.set_parameter?.('fx_delay_division', SYNC_DIVISIONS.length - 1 - d); } catch {}
       } else if (param === 'delayTime') {
         const t = abs * 2.0; show(abs.toFixed(2));
         try { synth.set_parameter?.('fx_delay_time', t); } catch {}
@@ -400,27 +500,95 @@ function wireToggles() {
         if (param === 'delayEnabled')  try { synth.set_parameter?.('fx_delay_wet',  this.checked ? 0.35 : 0.0); } catch {}
         if (param === 'filterEnv')     try { synth.set_parameter?.('filter_env', this.checked ? 1.0 : 0.0); } catch {}
         if (param === 'lfoRetrigger')  try { synth.set_parameter?.('lfo0_retrigger', this.checked ? 1.0 : 0.0); } catch {}
+        if (param === 'lfoPerVoice')   try { synth.set_parameter?.('lfo0_per_voice', this.checked ? 1.0 : 0.0); } catch {}
+        if (param === 'lfoOneShot')    try { synth.set_parameter?.('lfo0_one_shot', this.checked ? 1.0 : 0.0); } catch {}
+        if (param === 'lfoSync')       { syncState.lfo = this.checked;   try { synth.set_parameter?.('lfo0_sync', this.checked ? 1.0 : 0.0); } catch {} }
+        if (param === 'delaySync')     { syncState.delay = this.checked; try { synth.set_parameter?.('fx_delay_sync', this.checked ? 1.0 : 0.0); } catch {} }
       }
     });
   });
 }
 
 function wireModMatrix() {
-  $$('.modulation-matrix input[type="range"]').forEach(r=>{
+  // table labels -> engine names ("LFO 1" is lfo0; "Env 1" is env1, env0 being the amp envelope)
+  const sourceName = (label) => {
+    const l = label.trim().toLowerCase();
+    let m;
+    if ((m = l.match(/^lfo (\d+)/))) return `lfo${parseInt(m[1], 10) - 1}`;
+    if ((m = l.match(/^env (\d+)/))) return `env${parseInt(m[1], 10)}`;
+    if ((m = l.match(/^macro (\d+)/))) return `macro${parseInt(m[1], 10) - 1}`;
+    return l.replace(/\s+/g, '_');
+  };
+  const destName = (label) => {
+    const l = label.trim().toLowerCase();
+    const m = l.match(/^osc (\d+) (.+)$/);
+    if (m) return `osc${parseInt(m[1], 10) - 1}_${m[2].replace(/\s+/g, '_')}`;
+    if (l === 'filter cutoff') return 'filter_cutoff';
+    return l.replace(/\s+/g, '_');
+  };
+
+  const ths = Array.from(document.querySelectorAll('.modulation-matrix thead th')).map(th=>th.textContent);
+  $$('.modulation-matrix tbody input[type="range"]').forEach(r=>{
     r.addEventListener('input', ()=>{
       const tr = r.closest('tr'); if(!tr) return;
-      const src = tr.children[0]?.textContent?.trim().toLowerCase() || '';
-      const ths = Array.from(document.querySelectorAll('.modulation-matrix thead th')).map(th=>th.textContent.trim());
-      const td = r.closest('td'); const idx = Array.prototype.indexOf.call(td.parentElement.children, td);
-      const tgt = (ths[idx] || '').toLowerCase();
+      const td = r.closest('td'); const idx = Array.prototype.indexOf.call(tr.children, td);
+      const source = sourceName(tr.children[0]?.textContent || '');
+      const destination = destName(ths[idx] || '');
       const v = parseFloat(r.value);
       try {
-        if (src.includes('lfo 1') && tgt.includes('filter')) synth.set_parameter?.('mod_lfo0_to_cutoff', v);
-        if (src.includes('lfo 2') && tgt.includes('filter')) synth.set_parameter?.('mod_lfo1_to_cutoff', v);
-        if (src.includes('env 1') && tgt.includes('filter')) synth.set_parameter?.('mod_env_to_cutoff', v);
-      } catch {}
+        const slot = Array.from(synth.list_mod_slots?.() || []).find(s=>s.source===source && s.destination===destination);
+        if (slot) synth.set_mod_slot_amount?.(slot.index, v);
+        else synth.add_mod_slot?.(source, destination, v, source.startsWith('lfo'), '');
+        refreshSlotList();
+      } catch (e) { console.warn('mod matrix:', e?.message ?? e); }
     });
   });
+
+  // full slot list under the quick table
+  const host = document.querySelector('.modulation-matrix'); if(!host) return;
+  const list = document.createElement('div'); list.className = 'mod-slot-list';
+  const adder = document.createElement('div'); adder.className = 'mod-slot-add';
+  adder.style.display='flex'; adder.style.gap='6px'; adder.style.marginTop='8px';
+  const srcSel = document.createElement('select');
+  const dstSel = document.createElement('select');
+  const auxSel = document.createElement('select');
+  const addBtn = document.createElement('button'); addBtn.textContent = 'Add Slot';
+  adder.append(srcSel, dstSel, auxSel, addBtn);
+  host.append(adder, list);
+
+  const fill = (sel, names, blank) => {
+    sel.innerHTML = blank ? `<option value="">${blank}</option>` : '';
+    names.forEach(n=>{ const o=document.createElement('option'); o.value=n; o.textContent=n; sel.appendChild(o); });
+  };
+
+  addBtn.onclick = ()=>{
+    try {
+      synth.add_mod_slot?.(srcSel.value, dstSel.value, 0.5, srcSel.value.startsWith('lfo'), auxSel.value);
+      refreshSlotList();
+    } catch (e) { alert(`Could not add slot: ${e?.message ?? e}`); }
+  };
+
+  function refreshSlotList(){
+    if (!dstSel.options.length) {
+      const targets = synth.list_mod_targets?.() || { sources: [], destinations: [] };
+      fill(srcSel, targets.sources);
+      fill(dstSel, targets.destinations);
+      fill(auxSel, targets.sources, '(no aux)');
+    }
+    list.innerHTML = '';
+    Array.from(synth.list_mod_slots?.() || []).forEach(slot=>{
+      const row = document.createElement('div'); row.style.display='flex'; row.style.gap='6px'; row.style.alignItems='center';
+      const label = document.createElement('span');
+      label.textContent = `${slot.index}: ${slot.source} → ${slot.destination}${slot.aux ? ` × ${slot.aux}` : ''}`;
+      const amt = document.createElement('input'); amt.type='range'; amt.min='-1'; amt.max='1'; amt.step='0.01'; amt.value=String(slot.amount);
+      amt.oninput = ()=>{ try { synth.set_mod_slot_amount?.(slot.index, parseFloat(amt.value)); } catch {} };
+      const del = document.createElement('button'); del.textContent='✕';
+      del.onclick = ()=>{ try { synth.remove_mod_slot?.(slot.index); } catch {} refreshSlotList(); };
+      row.append(label, amt, del); list.appendChild(row);
+    });
+  }
+  window.refreshModSlots = refreshSlotList;
+  refreshSlotList();
 }
 
 function wireKeyboard() {
@@ -471,8 +639,14 @@ function wirePresets() {
   loadBtn?.addEventListener('click', ()=>{
     const name = presetSelect?.value; if(!name) return alert('Pick a preset');
     const raw = localStorage.getItem(`preset_${name}`); if(!raw) return alert('Empty preset');
-    const ok = synth.import_preset?.(raw);
-    alert(ok? `Loaded: ${name}` : 'Import failed');
+    try {
+      synth.import_preset?.(raw);
+    } catch (err) {
+      const fields = (err?.fields ?? []).map((f) => `\n  ${f.field}: ${f.reason}`).join('');
+      return alert(`Import failed: ${err?.message ?? err}${fields}`);
+    }
+    window.refreshModSlots?.();
+    alert(`Loaded: ${name}`);
   });
 }
 
@@ -505,7 +679,8 @@ function setupWavetableEditor(){
   const clrBtn  = mkBtn('Clear');
   const saveBtn = mkBtn('Save Slot');
   const loadSel = document.createElement('select'); loadSel.innerHTML='<option value="">--Load Slot--</option>';
-  panel.append(freeBtn, addBtn, normBtn, clrBtn, saveBtn, loadSel);
+  const wavInput = document.createElement('input'); wavInput.type='file'; wavInput.accept='.wav,audio/wav';
+  panel.append(freeBtn, addBtn, normBtn, clrBtn, saveBtn, loadSel, wavInput);
   canvas.parentElement.insertBefore(panel, canvas.nextSibling);
 
   const preview = document.createElement('canvas');
@@ -527,6 +702,17 @@ function setupWavetableEditor(){
   refreshSlots();
   loadSel.onchange=()=>{ const v=loadSel.value; if(v==='')return; const raw=localStorage.getItem(`wavetable_slot_${v}`); if(!raw) return alert('Empty'); table=Float32Array.from(JSON.parse(raw)); render(); push(); };
 
+  wavInput.onchange=async()=>{
+    const file=wavInput.files?.[0]; if(!file) return;
+    try {
+      const frames = synth.load_wavetable_wav(0, new Uint8Array(await file.arrayBuffer()));
+      synth.set_parameter?.('osc0_waveform', 5);
+      const first = synth.get_wavetable?.(0); if(first){ table=Float32Array.from(first); render(); drawFFTPreview(table, pctx, preview.width, preview.height); }
+      console.log(`Loaded ${frames} wavetable frame(s) from ${file.name}`);
+    } catch (e) { alert(`Could not load ${file.name}: ${e?.message ?? e}`); }
+    wavInput.value='';
+  };
+
   // harmonics
   const H=32; const row=document.createElement('div'); row.style.display='flex'; row.style.flexWrap='wrap'; row.style.gap='6px';
   harmonicPanel.appendChild(row);
@@ -733,6 +919,39 @@ function setupQwertyKeys() {
   });
 }
 
+// ---------- WebMIDI: every input's raw messages go to the engine's parser
+function setupWebMidi() {
+  if (!navigator.requestMIDIAccess) return;
+  // SysEx carries MIDI Tuning Standard messages; fall back if the browser refuses it
+  navigator.requestMIDIAccess({ sysex: true }).catch(() => navigator.requestMIDIAccess()).then((access) => {
+    const attach = (input) => {
+      input.onmidimessage = (e) => {
+        try { synth.process_midi?.(e.data); } catch {}
+        if (audioCtx && audioCtx.state !== "running") audioCtx.resume().catch(()=>{});
+      };
+    };
+    access.inputs.forEach(attach);
+    access.onstatechange = (e) => { if (e.port.type === 'input' && e.port.state === 'connected') attach(e.port); };
+  }).catch(() => {});
+}
+
+// ---------- Tuning: Scala scale (+ key map) files
+function wireTuning() {
+  const input = document.getElementById('tuningFiles');
+  document.getElementById('resetTuning')?.addEventListener('click', () => synth.reset_tuning?.());
+  if (!input) return;
+  input.onchange = async () => {
+    const files = Array.from(input.files || []);
+    const scl = files.find(f => /\.scl$/i.test(f.name));
+    const kbm = files.find(f => /\.kbm$/i.test(f.name));
+    if (!scl) { alert('Pick a .scl file (and optionally a .kbm)'); input.value = ''; return; }
+    try {
+      synth.load_scala(await scl.text(), kbm ? await kbm.text() : '');
+    } catch (e) { alert(`Could not load ${scl.name}: ${e?.message ?? e}`); }
+    input.value = '';
+  };
+}
+
 // ---------- Download link inline button style (no external CSS)
 function styleAsButton(el) {
   Object.assign(el.style, {
@@ -0,0 +1,90 @@
+// Pitch bend, sustain / sostenuto pedals and aftertouch.
+use serum_wasm_backend::modulation::{ModDest, ModSlot, ModSource};
+use serum_wasm_backend::Engine;
+
+const SR: f32 = 44_100.0;
+
+fn dry_engine() -> Engine {
+    let mut e = Engine::new(SR);
+    e.set_parameter("fx_delay_wet", 0.0);
+    e.set_parameter("fx_reverb_wet", 0.0);
+    e.set_parameter("env_release", 0.01);
+    e
+}
+
+fn render(e: &mut Engine, frames: usize) -> Vec<f32> {
+    let mut out = vec![0.0; frames];
+    e.render(&mut out);
+    out
+}
+
+/// Rising zero crossings per second of a one-second render.
+fn pitch(e: &mut Engine) -> usize {
+    render(e, SR as usize).windows(2).filter(|w| w[0] <= 0.0 && w[1] > 0.0).count()
+}
+
+#[test]
+fn pitch_bend_follows_the_patch_range() {
+    let sine = |range: f32, bend: &[u8]| {
+        let mut e = dry_engine();
+        e.set_parameter("osc0_waveform", 0.0);
+        e.set_parameter("osc1_gain", 0.0);
+        e.set_parameter("filter_cutoff", 20_000.0);
+        e.set_parameter("pitch_bend_range", range);
+        e.process_midi(bend);
+        e.note_on(69, 1.0);
+        pitch(&mut e)
+    };
+    let centre = sine(2.0, &[0xE0, 0x00, 0x40]);
+    assert!((438..=442).contains(&centre), "{centre}");
+    let up_octave = sine(12.0, &[0xE0, 0x7F, 0x7F]);
+    assert!((876..=884).contains(&up_octave), "{up_octave}");
+    let down_tone = sine(2.0, &[0xE0, 0x00, 0x00]);
+    assert!((390..=394).contains(&down_tone), "{down_tone}"); // 440 * 2^(-2/12) = 392
+}
+
+#[test]
+fn sustain_pedal_holds_released_notes() {
+    let mut e = dry_engine();
+    e.note_on(60, 1.0);
+    e.process_midi(&[0xB0, 64, 127]);
+    e.note_off(60);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 1, "held by the pedal");
+    e.process_midi(&[0xB0, 64, 0]);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 0);
+}
+
+#[test]
+fn sostenuto_only_holds_notes_down_when_pressed() {
+    let mut e = dry_engine();
+    e.note_on(60, 1.0);
+    e.set_parameter("sostenuto_pedal", 1.0);
+    e.note_on(64, 1.0);
+    e.note_off(60);
+    e.note_off(64);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 1, "only the latched note rings on");
+    e.set_parameter("sostenuto_pedal", 0.0);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 0);
+}
+
+#[test]
+fn aftertouch_and_wheel_drive_mod_slots() {
+    // each source fully closes the amp when at maximum
+    let level = |source: ModSource, midi: &[u8]| {
+        let mut e = dry_engine();
+        e.clear_mod_slots();
+        e.add_mod_slot(ModSlot::new(source, ModDest::Amp, -1.0)).unwrap();
+        e.note_on(60, 1.0);
+        e.note_on(67, 1.0);
+        e.process_midi(midi);
+        render(&mut e, 4410)[2205..].iter().map(|s| s.abs()).fold(0.0, f32::max)
+    };
+    assert!(level(ModSource::ModWheel, &[0xB0, 1, 127]) < 1e-4);
+    assert!(level(ModSource::Aftertouch, &[0xD0, 127]) < 1e-4);
+    assert!(level(ModSource::PolyAftertouch, &[0xA0, 60, 127, 67, 127]) < 1e-4);
+    assert!(level(ModSource::PolyAftertouch, &[0xA0, 60, 127]) > 0.01, "67 has no pressure");
+}
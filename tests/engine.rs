@@ -0,0 +1,205 @@
+// Native regression tests for the DSP core (no browser / wasm runtime needed).
+use serum_wasm_backend::Engine;
+
+const SR: f32 = 48_000.0;
+
+fn render_note(seed: u64, frames: usize) -> Vec<f32> {
+    let mut e = Engine::with_seed(SR, seed);
+    e.set_parameter("osc1_waveform", 4.0); // noise, exercises the RNG
+    e.note_on(60, 1.0);
+    let mut out = vec![0.0; frames];
+    e.render(&mut out);
+    out
+}
+
+#[test]
+fn renders_audio_natively() {
+    let out = render_note(1, 4096);
+    assert!(out.iter().all(|s| s.is_finite()));
+    assert!(out.iter().any(|s| s.abs() > 1e-3));
+}
+
+#[test]
+fn same_seed_renders_identically() {
+    assert_eq!(render_note(7, 2048), render_note(7, 2048));
+    assert_ne!(render_note(7, 2048), render_note(8, 2048));
+}
+
+#[test]
+fn released_voices_are_retired() {
+    let mut e = Engine::new(SR);
+    e.set_parameter("env_release", 0.01);
+    e.note_on(64, 0.8);
+    let mut buf = vec![0.0; 512];
+    e.render(&mut buf);
+    assert_eq!(e.active_voices(), 1);
+    e.note_off(64);
+    e.render(&mut buf);
+    e.render(&mut buf);
+    assert_eq!(e.active_voices(), 0);
+}
+
+#[test]
+fn hard_panned_oscillators_stay_on_their_side() {
+    let mut e = Engine::new(SR);
+    e.set_parameter("fx_delay_wet", 0.0);
+    e.set_parameter("fx_reverb_wet", 0.0);
+    e.set_parameter("osc0_pan", -1.0);
+    e.set_parameter("osc1_gain", 0.0);
+    e.note_on(60, 1.0);
+    let (mut l, mut r) = (vec![0.0; 2048], vec![0.0; 2048]);
+    e.render_stereo(&mut l, &mut r);
+    assert!(l.iter().any(|s| s.abs() > 1e-3));
+    assert!(r.iter().all(|s| s.abs() < 1e-6));
+}
+
+#[test]
+fn every_filter_model_stays_bounded_at_extremes() {
+    for model in 0..4 {
+        for &(cutoff, res) in &[(20.0, 1.2), (2_000.0, 1.2), (23_000.0, 1.2), (8_000.0, 0.0)] {
+            let mut e = Engine::new(SR);
+            e.set_parameter("filter_model", model as f32);
+            e.set_parameter("filter_drive", 1.0);
+            e.set_parameter("filter_cutoff", cutoff);
+            e.set_parameter("filter_resonance", res);
+            e.note_on(36, 1.0);
+            e.note_on(84, 1.0);
+            let mut out = vec![0.0; 8192];
+            e.render(&mut out);
+            assert!(
+                out.iter().all(|s| s.is_finite() && s.abs() <= 2.0),
+                "model {model} cutoff {cutoff} res {res}"
+            );
+        }
+    }
+}
+
+fn dry_stereo(setup: impl Fn(&mut Engine), frames: usize) -> (Vec<f32>, Vec<f32>) {
+    let mut e = Engine::with_seed(SR, 3);
+    e.set_parameter("fx_delay_wet", 0.0);
+    e.set_parameter("fx_reverb_wet", 0.0);
+    e.set_parameter("filter_cutoff", 20_000.0);
+    e.set_parameter("filter_env", 0.0);
+    e.set_parameter("osc1_gain", 0.0);
+    setup(&mut e);
+    e.note_on(48, 0.5);
+    let (mut l, mut r) = (vec![0.0; frames], vec![0.0; frames]);
+    e.render_stereo(&mut l, &mut r);
+    (l, r)
+}
+
+fn rms(x: &[f32]) -> f32 {
+    (x.iter().map(|s| s * s).sum::<f32>() / x.len() as f32).sqrt()
+}
+
+#[test]
+fn unison_spreads_in_stereo_at_roughly_constant_level() {
+    let (l1, r1) = dry_stereo(|_| {}, 16_384);
+    assert_eq!(l1, r1);
+
+    let (l7, r7) = dry_stereo(
+        |e| {
+            e.set_parameter("osc0_unison", 7.0);
+            e.set_parameter("osc0_unison_detune", 30.0);
+            e.set_parameter("osc0_unison_curve", 1.0);
+        },
+        16_384,
+    );
+    assert!(l7.iter().zip(&r7).any(|(a, b)| (a - b).abs() > 1e-3));
+    let ratio_db = 20.0 * (rms(&l7[4096..]) / rms(&l1[4096..])).log10();
+    assert!(ratio_db.abs() < 4.0, "unison level moved by {ratio_db} dB");
+
+    // zero width collapses the stack back to mono
+    let (l, r) = dry_stereo(
+        |e| {
+            e.set_parameter("osc0_unison", 7.0);
+            e.set_parameter("osc0_unison_width", 0.0);
+        },
+        4096,
+    );
+    assert_eq!(l, r);
+}
+
+#[test]
+fn unison_and_pan_changes_reach_a_sounding_note() {
+    let mut e = Engine::with_seed(SR, 3);
+    for (k, v) in [("fx_delay_wet", 0.0), ("fx_reverb_wet", 0.0), ("filter_env", 0.0), ("osc1_gain", 0.0)] {
+        e.set_parameter(k, v);
+    }
+    e.note_on(48, 0.5);
+    let (mut l, mut r) = (vec![0.0; 4096], vec![0.0; 4096]);
+    e.render_stereo(&mut l, &mut r);
+    assert_eq!(l, r);
+
+    // a unison change mid-note spreads the stack at once
+    e.set_parameter("osc0_unison", 7.0);
+    e.render_stereo(&mut l, &mut r);
+    assert!(l.iter().zip(&r).any(|(a, b)| (a - b).abs() > 1e-3));
+
+    // a pan ramp lands exactly on its target
+    e.set_parameter("osc0_unison", 1.0);
+    e.set_parameter("osc0_pan", -1.0);
+    e.render_stereo(&mut l, &mut r);
+    e.render_stereo(&mut l, &mut r);
+    assert!(l.iter().any(|s| s.abs() > 1e-3));
+    assert!(r.iter().all(|s| s.abs() < 1e-6));
+}
+
+/// OSC B alone (A still runs as the modulator), 440 Hz at 44 kHz: A's period is exactly 100 samples.
+fn osc_b_only<R>(setup: impl Fn(&mut Engine) -> R) -> Vec<f32> {
+    let mut e = Engine::new(44_000.0);
+    for (name, v) in [
+        ("fx_delay_wet", 0.0),
+        ("fx_reverb_wet", 0.0),
+        ("filter_cutoff", 21_000.0),
+        ("filter_env", 0.0),
+        ("env_attack", 0.0001),
+        ("env_sustain", 1.0),
+        ("osc0_gain", 0.0),
+        ("osc0_unison_phase_rand", 0.0),
+        ("osc1_unison_phase_rand", 0.0),
+    ] {
+        e.set_parameter(name, v);
+    }
+    setup(&mut e);
+    e.note_on(69, 0.5);
+    let mut out = vec![0.0; 8192];
+    e.render(&mut out);
+    out
+}
+
+fn max_period_error(x: &[f32], period: usize) -> f32 {
+    x[4096..].iter().zip(&x[4096 + period..]).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+}
+
+#[test]
+fn hard_sync_locks_osc_b_to_osc_a_period() {
+    let free = osc_b_only(|e| e.set_parameter("osc1_coarse", 7.0));
+    let synced = osc_b_only(|e| {
+        e.set_parameter("osc1_coarse", 7.0);
+        e.set_parameter("osc1_sync", 1.0);
+    });
+    assert!(max_period_error(&free, 100) > 0.1);
+    assert!(max_period_error(&synced, 100) < 0.02, "{}", max_period_error(&synced, 100));
+}
+
+#[test]
+fn ring_mod_of_identical_sines_is_non_negative() {
+    let out = osc_b_only(|e| {
+        e.set_parameter("osc0_waveform", 0.0);
+        e.set_parameter("osc1_waveform", 0.0);
+        e.set_parameter("osc1_ring", 1.0);
+    });
+    assert!(out[2048..].iter().all(|s| *s > -1e-3));
+    assert!(out[2048..].iter().any(|s| *s > 0.1));
+}
+
+#[test]
+fn fm_pm_and_am_change_osc_b_but_stay_bounded() {
+    let dry = osc_b_only(|_| {});
+    for name in ["osc1_fm", "osc1_pm", "osc1_am"] {
+        let wet = osc_b_only(|e| e.set_parameter(name, 1.0));
+        assert!(wet.iter().all(|s| s.is_finite() && s.abs() <= 2.0), "{name}");
+        assert!(wet.iter().zip(&dry).any(|(a, b)| (a - b).abs() > 1e-2), "{name} had no effect");
+    }
+}
@@ -0,0 +1,76 @@
+// DAHDSR envelope stages and curvature.
+use serum_wasm_backend::envelope::{ADSRParams, AdsrState, PerVoiceADSR};
+use serum_wasm_backend::modulation::{ModDest, ModSlot, ModSource};
+use serum_wasm_backend::Engine;
+
+const DT: f32 = 0.001; // 1 kHz control rate keeps the step counts readable
+
+fn run(env: &mut PerVoiceADSR, steps: usize) -> Vec<f32> {
+    (0..steps).map(|_| env.tick(DT)).collect()
+}
+
+#[test]
+fn delay_and_hold_stages_bracket_the_attack() {
+    let p = ADSRParams { delay: 0.010, attack: 0.010, hold: 0.010, decay: 0.010, sustain: 0.5, ..Default::default() };
+    let mut env = PerVoiceADSR::new(&p);
+    let out = run(&mut env, 60);
+    assert!(out[..9].iter().all(|&l| l == 0.0), "silent through the delay");
+    assert!(out[14] > 0.3 && out[14] < 0.7, "half way up the attack");
+    assert!(out[20..29].iter().all(|&l| l == 1.0), "held at the peak");
+    assert!(out[35] < 1.0 && out[35] > 0.5, "decaying");
+    assert_eq!(out[59], 0.5);
+    assert_eq!(env.state, AdsrState::Sustain);
+}
+
+#[test]
+fn curvature_bends_segments_without_changing_their_length() {
+    let level_at = |curve: f32, step: usize| {
+        let p = ADSRParams { attack: 0.0001, decay: 0.0001, sustain: 1.0, release: 0.100, release_curve: curve, ..Default::default() };
+        let mut env = PerVoiceADSR::new(&p);
+        run(&mut env, 5);
+        env.note_off();
+        let out = run(&mut env, 110);
+        (out[step], env.state)
+    };
+    let (linear, _) = level_at(0.0, 49);
+    let (expo, state) = level_at(1.0, 49);
+    let (slow, _) = level_at(-1.0, 49);
+    assert!((linear - 0.5).abs() < 0.02);
+    assert!(expo < 0.05, "exponential release falls fast: {expo}");
+    assert!(slow > 0.95, "negative curve holds up: {slow}");
+    assert_eq!(state, AdsrState::Idle);
+    for curve in [-1.0, 0.0, 1.0] {
+        assert!(level_at(curve, 97).0 > 0.0, "release ends early at curve {curve}");
+        assert_eq!(level_at(curve, 100).0, 0.0);
+    }
+}
+
+#[test]
+fn note_off_during_delay_ends_silently() {
+    let p = ADSRParams { delay: 0.5, ..Default::default() };
+    let mut env = PerVoiceADSR::new(&p);
+    run(&mut env, 10);
+    env.note_off();
+    let out = run(&mut env, 400);
+    assert!(out.iter().all(|&l| l == 0.0));
+    assert_eq!(env.state, AdsrState::Idle);
+}
+
+#[test]
+fn mod_envelope_sweeps_the_filter_per_note() {
+    let mut e = Engine::with_seed(48_000.0, 2);
+    e.set_parameter("filter_cutoff", 40.0);
+    e.set_parameter("fx_delay_wet", 0.0);
+    e.set_parameter("fx_reverb_wet", 0.0);
+    for (name, v) in [("env2_attack", 0.001), ("env2_decay", 0.05), ("env2_sustain", 0.0), ("env2_decay_curve", 0.5)] {
+        e.set_parameter(name, v);
+    }
+    e.add_mod_slot(ModSlot::new(ModSource::Env2, ModDest::FilterCutoff, 1.0)).unwrap();
+    e.note_on(48, 1.0);
+    let mut out = vec![0.0; 48_000 / 4];
+    e.render(&mut out);
+    let energy = |range: std::ops::Range<usize>| out[range].iter().map(|s| s * s).sum::<f32>();
+    // open while the envelope is up, closed once it has decayed to zero
+    let (open, closed) = (energy(0..2400), energy(9600..12_000));
+    assert!(open > 10.0 * closed, "{open} vs {closed}");
+}
@@ -0,0 +1,133 @@
+// Sample-accurate scheduling of notes, parameters and MIDI inside a render block.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use serum_wasm_backend::events::{EventKind, EventQueue, EVENT_CAPACITY};
+use serum_wasm_backend::Engine;
+
+/// Counts this thread's heap allocations, so parallel tests don't interfere.
+struct Counting;
+thread_local! {
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+}
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+#[global_allocator]
+static COUNTING: Counting = Counting;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCS.with(Cell::get);
+    f();
+    ALLOCS.with(Cell::get) - before
+}
+
+const SR: f32 = 44_100.0;
+
+fn dry_engine() -> Engine {
+    let mut e = Engine::new(SR);
+    e.set_parameter("fx_delay_wet", 0.0);
+    e.set_parameter("fx_reverb_wet", 0.0);
+    e
+}
+
+fn first_sound(out: &[f32]) -> Option<usize> {
+    out.iter().position(|s| s.abs() > 0.0)
+}
+
+#[test]
+fn queue_orders_by_frame_then_insertion() {
+    let mut q = EventQueue::default();
+    q.push(10, EventKind::NoteOff { note: 1 });
+    q.push(5, EventKind::NoteOn { note: 2, velocity: 1.0 });
+    q.push(10, EventKind::NoteOff { note: 3 });
+    assert_eq!(q.pop_due(4), None);
+    assert_eq!(q.pop_due(5), Some(EventKind::NoteOn { note: 2, velocity: 1.0 }));
+    q.advance(8);
+    assert_eq!(q.pop_due(2), Some(EventKind::NoteOff { note: 1 }));
+    assert_eq!(q.pop_due(2), Some(EventKind::NoteOff { note: 3 }));
+    assert!(q.is_empty());
+}
+
+#[test]
+fn note_starts_on_its_frame() {
+    let mut e = dry_engine();
+    e.schedule(300, EventKind::NoteOn { note: 60, velocity: 1.0 });
+    let mut out = vec![0.0; 1024];
+    e.render(&mut out);
+    assert_eq!(first_sound(&out), Some(300));
+}
+
+#[test]
+fn events_past_the_block_carry_over() {
+    let mut e = dry_engine();
+    e.schedule(1500, EventKind::midi(&[0x90, 60, 100]).unwrap());
+    let (mut l, mut r) = (vec![0.0; 1024], vec![0.0; 1024]);
+    e.render_stereo(&mut l, &mut r);
+    assert_eq!(first_sound(&l), None);
+    assert_eq!(e.scheduled_count(), 1);
+    e.render_stereo(&mut l, &mut r);
+    assert_eq!(first_sound(&l), Some(1500 - 1024));
+    assert_eq!(e.scheduled_count(), 0);
+}
+
+#[test]
+fn parameter_changes_land_mid_block() {
+    let mut e = dry_engine();
+    e.set_smoothing("master_gain", 0.0); // land exactly, no ramp
+    e.note_on(60, 1.0);
+    e.schedule(700, EventKind::param("master_gain", 0.0).unwrap());
+    let mut out = vec![0.0; 1024];
+    e.render(&mut out);
+    assert!(out[600..700].iter().any(|s| s.abs() > 1e-3));
+    assert!(out[700..].iter().all(|&s| s == 0.0));
+
+    e.schedule(10, EventKind::NoteOff { note: 60 });
+    e.clear_scheduled();
+    assert_eq!(e.scheduled_count(), 0);
+}
+
+#[test]
+fn payloads_resolve_up_front() {
+    assert!(EventKind::param("no_such_param", 0.0).is_none());
+    // an alias resolves to its parameter's id
+    assert_eq!(EventKind::param("osc0_volume", 0.5), EventKind::param("osc0_gain", 0.5));
+    assert_eq!(EventKind::midi(&[0xC0, 5]), Some(EventKind::Midi { bytes: [0xC0, 5, 0], len: 2 }));
+    assert!(EventKind::midi(&[]).is_none() && EventKind::midi(&[0x90, 60, 100, 0]).is_none());
+
+    // a note-on split across two events on the same frame still plays there
+    let mut e = dry_engine();
+    e.schedule(200, EventKind::midi(&[0x90, 60]).unwrap());
+    e.schedule(200, EventKind::midi(&[100]).unwrap());
+    let mut out = vec![0.0; 1024];
+    e.render(&mut out);
+    assert_eq!(first_sound(&out), Some(200));
+}
+
+#[test]
+fn scheduling_and_applying_events_does_not_allocate() {
+    let mut e = dry_engine();
+    let mut out = vec![0.0; 1024];
+    e.render(&mut out);
+    let n = allocations(|| {
+        for i in 0..EVENT_CAPACITY {
+            let frame = (i * 7919) % 1024;
+            let event = match i % 4 {
+                0 => EventKind::NoteOn { note: 48 + (i % 24) as u8, velocity: 1.0 },
+                1 => EventKind::NoteOff { note: 48 + (i % 24) as u8 },
+                2 => EventKind::param("filter_cutoff", 200.0 + i as f32).unwrap(),
+                _ => EventKind::midi(&[0xB0, 1, (i % 128) as u8]).unwrap(),
+            };
+            e.schedule(frame, event);
+        }
+        e.render(&mut out);
+    });
+    assert_eq!(n, 0);
+    assert_eq!(e.scheduled_count(), 0);
+}
@@ -0,0 +1,264 @@
+// Per-voice filter: SVF responses per type, envelope sweeps per note, key tracking,
+// resonance and stability.
+use serum_wasm_backend::filter::{FilterType, StateVarFilter};
+use serum_wasm_backend::util::midi_to_freq;
+use serum_wasm_backend::Engine;
+
+const SR: f32 = 48_000.0;
+
+/// One saw (or sine) on OSC A through the voice filter, no FX or LFO on the cutoff.
+fn dry_engine(waveform: f32) -> Engine {
+    let mut e = Engine::with_seed(SR, 3);
+    for (k, v) in [
+        ("fx_delay_wet", 0.0),
+        ("fx_reverb_wet", 0.0),
+        ("osc0_waveform", waveform),
+        ("osc1_gain", 0.0),
+        ("filter_resonance", 0.0),
+        ("mod_lfo0_to_cutoff", 0.0),
+        ("env_attack", 0.001),
+        ("env_sustain", 1.0),
+        ("env_release", 2.0),
+    ] {
+        e.set_parameter(k, v);
+    }
+    e
+}
+
+fn render(e: &mut Engine, frames: usize) -> Vec<f32> {
+    let mut out = vec![0.0; frames];
+    e.render(&mut out);
+    out
+}
+
+fn rms(x: &[f32]) -> f32 {
+    (x.iter().map(|s| s * s).sum::<f32>() / x.len() as f32).sqrt()
+}
+
+/// High-frequency share of a window: slope energy relative to level.
+fn brightness(x: &[f32]) -> f32 {
+    let slope: Vec<f32> = x.windows(2).map(|w| w[1] - w[0]).collect();
+    rms(&slope) / rms(x)
+}
+
+/// Steady-state gain of `f` for a sine at `hz`.
+fn sine_gain(f: &mut StateVarFilter, hz: f32) -> f32 {
+    let sine = |i: usize| (2.0 * std::f32::consts::PI * hz * i as f32 / SR).sin();
+    let out: Vec<f32> = (0..SR as usize).map(|i| f.process(sine(i))).collect();
+    let input: Vec<f32> = (0..SR as usize).map(sine).collect();
+    rms(&out[24_000..]) / rms(&input[24_000..])
+}
+
+#[test]
+fn staggered_staccato_notes_each_get_their_own_sweep() {
+    let run = |amount: f32| {
+        let mut e = dry_engine(1.0);
+        e.set_parameter("filter_cutoff", 150.0);
+        e.set_parameter("filter_env_amount", amount);
+        e.set_parameter("filter_env_decay", 0.05);
+        e.set_parameter("filter_env_release", 0.05);
+        e.note_on(48, 1.0);
+        let mut out = render(&mut e, 2400);
+        e.note_off(48);
+        out.extend(render(&mut e, 12_000 - 2400));
+        e.note_on(55, 1.0);
+        out.extend(render(&mut e, 2400));
+        e.note_off(55);
+        out.extend(render(&mut e, 4800));
+        out
+    };
+    let out = run(5.0);
+    let first = brightness(&out[240..1200]);
+    let before_second = brightness(&out[10_800..12_000]);
+    let second = brightness(&out[12_240..13_200]);
+    // the first note has settled dark by the time the second one opens up again
+    assert!(first > 3.0 * before_second, "{first} {before_second}");
+    assert!(second > 3.0 * before_second, "{second} {before_second}");
+
+    // the default amount is 0: neither onset is brighter than the tail
+    let flat = run(0.0);
+    let tail = brightness(&flat[10_800..12_000]);
+    for onset in [&flat[240..1200], &flat[12_240..13_200]] {
+        assert!(brightness(onset) < 1.5 * tail, "{} {tail}", brightness(onset));
+    }
+}
+
+#[test]
+fn key_tracking_raises_the_cutoff_with_pitch() {
+    // low vs high sine level through a 100 Hz lowpass
+    let level_ratio = |track: f32| {
+        let level = |note: u8| {
+            let mut e = dry_engine(0.0);
+            e.set_parameter("filter_cutoff", 100.0);
+            e.set_parameter("filter_keytrack", track);
+            e.note_on(note, 1.0);
+            rms(&render(&mut e, 9600)[4800..])
+        };
+        level(84) / level(36)
+    };
+    let (fixed, tracked) = (level_ratio(0.0), level_ratio(1.0));
+    assert!(fixed < 0.1, "{fixed}");
+    assert!(tracked > 0.5 && tracked < 2.0, "{tracked}");
+}
+
+#[test]
+fn key_tracking_follows_the_tuning() {
+    // key 84 retuned down to key 36's pitch tracks like key 36, not four octaves up
+    let level = |note: u8, table: &[f32]| {
+        let mut e = dry_engine(0.0);
+        e.set_tuning_table(table, 440.0);
+        e.set_parameter("filter_cutoff", 100.0);
+        e.set_parameter("filter_keytrack", 1.0);
+        e.note_on(note, 1.0);
+        rms(&render(&mut e, 9600)[4800..])
+    };
+    let mut table: Vec<f32> = (0..128).map(midi_to_freq).collect();
+    table[84] = table[36];
+    let (low, retuned) = (level(36, &table), level(84, &table));
+    assert!((retuned / low - 1.0).abs() < 0.01, "{low} {retuned}");
+
+    // the pivot moves with the tuning too: key 60 a fifth up still sits on the cutoff
+    table[60] *= 1.5;
+    let fresh: Vec<f32> = (0..128).map(midi_to_freq).collect();
+    let (moved, plain) = (level(36, &table), level(36, &fresh));
+    assert!(moved < 0.9 * plain, "{moved} {plain}");
+}
+
+#[test]
+fn resonance_sets_the_gain_at_the_cutoff() {
+    // lower resonance values ring more: 0 is Q 2, 1.2 and up bottom out at Q 0.55
+    for (res, q) in [(0.0, 2.0), (0.6, 1.1), (1.2, 0.55), (2.0, 0.55)] {
+        let mut f = StateVarFilter::new(1000.0, res, SR);
+        let g = sine_gain(&mut f, 1000.0);
+        assert!((g / q - 1.0).abs() < 0.03, "resonance {res}: gain {g}, want {q}");
+    }
+}
+
+#[test]
+fn the_most_resonant_setting_rings_out_instead_of_self_oscillating() {
+    let mut f = StateVarFilter::new(1000.0, 0.0, SR);
+    let ring: Vec<f32> = (0..SR as usize).map(|i| f.process(if i == 0 { 1.0 } else { 0.0 })).collect();
+    assert!(ring[..480].iter().any(|s| s.abs() > 0.01));
+    assert!(ring[4800..].iter().all(|s| s.abs() < 1e-6));
+}
+
+#[test]
+fn stays_stable_with_the_cutoff_at_sr_0_49() {
+    for res in [0.0, 0.6, 1.2, 2.0] {
+        // the engine clamps anything higher to sr * 0.49
+        let mut e = dry_engine(1.0);
+        e.set_parameter("osc1_gain", 0.5);
+        e.set_parameter("osc1_waveform", 4.0);
+        e.set_parameter("filter_cutoff", 1.0e6);
+        e.set_parameter("filter_resonance", res);
+        e.note_on(96, 1.0);
+        let out = render(&mut e, SR as usize);
+        assert!(out.iter().all(|s| s.is_finite() && s.abs() < 4.0), "resonance {res}");
+
+        // and a full-scale step straight into the filter settles
+        let mut f = StateVarFilter::new(SR * 0.49, res, SR);
+        let step: Vec<f32> = (0..4800).map(|_| f.process(1.0)).collect();
+        assert!(step.iter().all(|s| s.is_finite() && s.abs() < 4.0), "resonance {res}");
+        assert!((step[4799] - 1.0).abs() < 1e-3, "resonance {res}: {}", step[4799]);
+    }
+}
+
+/// `sine_gain` for an SVF of `mode` at a Butterworth-ish Q (resonance 0.862 is Q 0.707).
+fn svf_gain(mode: FilterType, morph: f32, hz: f32) -> f32 {
+    let mut f = StateVarFilter::new(1000.0, 0.862, SR);
+    f.mode = mode;
+    f.morph = morph;
+    sine_gain(&mut f, hz)
+}
+
+#[test]
+fn svf_types_have_their_textbook_responses() {
+    let db = |g: f32| 20.0 * g.log10();
+    let near = |g: f32, want_db: f32, tol: f32| (db(g) - want_db).abs() <= tol;
+    // (type, morph, dB at cutoff/10, cutoff, cutoff*10)
+    let cases = [
+        (FilterType::Lowpass, 0.0, 0.0, -3.0, -40.0),
+        (FilterType::Highpass, 0.0, -40.0, -3.0, 0.0),
+        (FilterType::Bandpass, 0.0, -20.0, -3.0, -20.0),
+        (FilterType::Peak, 0.0, 0.0, 3.0, 0.0),
+        (FilterType::Morph, 0.0, 0.0, -3.0, -40.0),
+        (FilterType::Morph, 0.5, -20.0, -3.0, -20.0),
+        (FilterType::Morph, 1.0, -40.0, -3.0, 0.0),
+    ];
+    for (mode, morph, lo, mid, hi) in cases {
+        for (hz, want) in [(100.0, lo), (1000.0, mid), (10_000.0, hi)] {
+            let g = svf_gain(mode, morph, hz);
+            // the bilinear transform squeezes the top decade, so allow more there
+            let tol = if hz > 5000.0 { 6.0 } else { 1.0 };
+            assert!(near(g, want, tol), "type {} morph {morph} at {hz} Hz: {:.1} dB, want {want}", mode.to_index(), db(g));
+        }
+    }
+
+    // notch: deep at the cutoff, flat a decade away
+    assert!(db(svf_gain(FilterType::Notch, 0.0, 1000.0)) < -40.0);
+    for hz in [100.0, 10_000.0] {
+        assert!(near(svf_gain(FilterType::Notch, 0.0, hz), 0.0, 0.5), "{hz}");
+    }
+    // allpass: unity everywhere
+    for hz in [50.0, 100.0, 1000.0, 10_000.0, 20_000.0] {
+        assert!(near(svf_gain(FilterType::Allpass, 0.0, hz), 0.0, 0.1), "{hz}");
+    }
+}
+
+#[test]
+fn every_type_stays_stable_at_sr_0_49_and_rings_out() {
+    let types = [
+        FilterType::Lowpass,
+        FilterType::Highpass,
+        FilterType::Bandpass,
+        FilterType::Notch,
+        FilterType::Peak,
+        FilterType::Allpass,
+        FilterType::Morph,
+    ];
+    for mode in types {
+        for res in [0.0, 1.2] {
+            for cutoff in [1000.0, SR * 0.49] {
+                let mut f = StateVarFilter::new(cutoff, res, SR);
+                f.mode = mode;
+                f.morph = 0.3;
+                // an impulse, then silence: output stays bounded and dies away
+                let out: Vec<f32> = (0..9600).map(|i| f.process(if i == 0 { 1.0 } else { 0.0 })).collect();
+                let what = format!("type {} resonance {res} cutoff {cutoff}", mode.to_index());
+                assert!(out.iter().all(|s| s.is_finite() && s.abs() < 4.0), "{what}");
+                assert!(out[4800..].iter().all(|s| s.abs() < 1e-6), "{what}");
+            }
+        }
+    }
+}
+
+#[test]
+fn filter_type_survives_a_preset_round_trip() {
+    for index in 0..7 {
+        let mut a = Engine::new(SR);
+        assert!(a.set_parameter("filter_type", index as f32));
+        a.set_parameter("filter_morph", 0.25);
+        let mut b = Engine::new(SR);
+        b.import_preset(&a.export_preset()).unwrap();
+        assert_eq!(b.get_parameter("filter_type"), Some(index as f32));
+        assert_eq!(b.get_parameter("filter_morph"), Some(0.25));
+    }
+}
+
+#[test]
+fn a_recycled_comb_slot_starts_silent() {
+    let mut e = dry_engine(1.0);
+    e.set_parameter("filter_model", 3.0);
+    e.set_parameter("filter_resonance", 1.2);
+    e.set_parameter("env_release", 0.01);
+    e.note_on(45, 1.0);
+    render(&mut e, 4800);
+    e.note_off(45);
+    render(&mut e, 4800);
+    assert_eq!(e.active_voices(), 0);
+
+    // the next note gets the same slot; with the oscillators muted nothing may ring on
+    e.set_parameter("osc0_gain", 0.0);
+    e.note_on(45, 1.0);
+    assert!(render(&mut e, 4800).iter().all(|&s| s == 0.0));
+}
@@ -0,0 +1,162 @@
+// LFO shapes, drawn curves, start phase, fade-in, one-shot and per-voice mode.
+use serum_wasm_backend::lfo::{CurvePoint, LfoCurve, LfoShape, LFO};
+use serum_wasm_backend::modulation::{ModDest, ModSlot, ModSource};
+use serum_wasm_backend::Engine;
+
+const DT: f32 = 0.001;
+
+fn lfo(shape: LfoShape) -> LFO {
+    LFO { rate: 1.0, amount: 1.0, shape, ..Default::default() }
+}
+
+fn run(l: &mut LFO, steps: usize) -> Vec<f32> {
+    (0..steps)
+        .map(|_| {
+            l.tick(DT);
+            l.value()
+        })
+        .collect()
+}
+
+#[test]
+fn every_shape_stays_finite_and_in_range() {
+    for shape in [
+        LfoShape::Sine,
+        LfoShape::Triangle,
+        LfoShape::Saw,
+        LfoShape::Square,
+        LfoShape::SampleHold,
+        LfoShape::SmoothRandom,
+    ] {
+        let out = run(&mut lfo(shape), 3000);
+        assert!(out.iter().all(|v| v.is_finite() && v.abs() <= 1.0), "{shape:?} out of range");
+        let (lo, hi) = out.iter().fold((1.0f32, -1.0f32), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        assert!(hi - lo > 0.5, "{shape:?} barely moves: {lo}..{hi}");
+    }
+}
+
+#[test]
+fn periodic_shapes_hit_their_landmarks() {
+    let at = |shape, phase: f32| {
+        let mut l = LFO { start_phase: phase, ..lfo(shape) };
+        l.retrigger();
+        l.value()
+    };
+    assert!(at(LfoShape::Sine, 0.25) > 0.999);
+    assert!(at(LfoShape::Triangle, 0.0).abs() < 1e-6);
+    assert!(at(LfoShape::Triangle, 0.25) > 0.999);
+    assert!(at(LfoShape::Triangle, 0.75) < -0.999);
+    assert!(at(LfoShape::Saw, 0.0) < -0.999);
+    assert_eq!(at(LfoShape::Square, 0.2), 1.0);
+    assert_eq!(at(LfoShape::Square, 0.7), -1.0);
+}
+
+#[test]
+fn sample_and_hold_steps_once_per_cycle_and_smooth_random_glides() {
+    let held = run(&mut LFO { rate: 10.0, ..lfo(LfoShape::SampleHold) }, 1000);
+    let steps = held.windows(2).filter(|w| w[0] != w[1]).count();
+    assert!((8..=10).contains(&steps), "{steps} level changes in 10 cycles");
+
+    let smooth = run(&mut LFO { rate: 10.0, ..lfo(LfoShape::SmoothRandom) }, 1000);
+    let max_jump = smooth.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f32::max);
+    assert!(max_jump < 0.05, "smooth random jumps by {max_jump}");
+}
+
+#[test]
+fn fade_in_ramps_from_silence() {
+    let mut l = LFO { fade_in: 0.5, ..lfo(LfoShape::Square) };
+    l.retrigger();
+    let out = run(&mut l, 1000);
+    assert!(out[9].abs() < 0.05);
+    assert!((out[249] - 0.5).abs() < 0.01, "half way through the fade: {}", out[249]);
+    assert!((out[499] - 1.0).abs() < 1e-3);
+}
+
+#[test]
+fn per_voice_lfo_starts_at_its_phase_on_every_note() {
+    // square at phase 0.5 is -1, which fully closes the amp
+    let level = |per_voice: bool| {
+        let mut e = Engine::new(44_100.0);
+        e.clear_mod_slots();
+        e.add_mod_slot(ModSlot::new(ModSource::Lfo0, ModDest::Amp, 1.0)).unwrap();
+        for (k, v) in [("waveform", 3.0), ("rate", 1.0), ("amount", 1.0), ("phase", 0.5)] {
+            e.set_parameter(&format!("lfo0_{k}"), v);
+        }
+        e.set_parameter("lfo0_per_voice", if per_voice { 1.0 } else { 0.0 });
+        let mut pre = vec![0.0; 11_025];
+        e.render(&mut pre); // the global LFO runs on from phase 0
+        e.note_on(60, 1.0);
+        let mut out = vec![0.0; 4410];
+        e.render(&mut out);
+        out.iter().map(|s| s.abs()).fold(0.0, f32::max)
+    };
+    assert!(level(true) < 1e-4, "per-voice LFO should start at -1");
+    assert!(level(false) > 0.05, "global LFO has moved on");
+}
+
+#[test]
+fn curve_passes_through_its_points_and_bends_between_them() {
+    let pt = |x, y, curve| CurvePoint { x, y, curve };
+    let c = LfoCurve::new(&[pt(1.0, -1.0, 0.0), pt(0.0, -1.0, 0.0), pt(0.5, 1.0, 0.0)]).unwrap();
+    assert_eq!(c.points()[1].x, 0.5, "points are sorted by phase");
+    assert_eq!(c.value(0.0), -1.0);
+    assert_eq!(c.value(0.5), 1.0);
+    assert!(c.value(0.25).abs() < 1e-6, "linear segment");
+
+    let bent = LfoCurve::new(&[pt(0.0, -1.0, 1.0), pt(1.0, 1.0, 0.0)]).unwrap();
+    assert!(bent.value(0.25) > 0.5, "positive curve rises fast: {}", bent.value(0.25));
+
+    // vertical step: two points at the same phase
+    let step = LfoCurve::from_flat(&[0.0, 1.0, 0.0, 0.5, 1.0, 0.0, 0.5, -1.0, 0.0, 1.0, -1.0, 0.0]).unwrap();
+    assert_eq!(step.value(0.49), 1.0);
+    assert_eq!(step.value(0.51), -1.0);
+
+    assert!(LfoCurve::from_flat(&[0.5, 0.5]).is_none());
+}
+
+#[test]
+fn default_curve_matches_the_triangle() {
+    let mut tri = lfo(LfoShape::Triangle);
+    let mut custom = lfo(LfoShape::Custom);
+    for (a, b) in run(&mut tri, 2000).into_iter().zip(run(&mut custom, 2000)) {
+        assert!((a - b).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn one_shot_runs_once_and_holds_the_last_point() {
+    let mut l = LFO { one_shot: true, ..lfo(LfoShape::Custom) };
+    l.curve = LfoCurve::from_flat(&[0.0, 0.0, 0.0, 0.1, 1.0, 0.0, 0.6, 0.2, 0.0, 1.0, -0.5, 0.0]).unwrap();
+    l.retrigger();
+    let out = run(&mut l, 3000);
+    assert!((out[99] - 1.0).abs() < 0.01, "peak of the first stage: {}", out[99]);
+    assert!(out[1500..].iter().all(|&v| v == -0.5), "holds the end level");
+}
+
+#[test]
+fn one_shot_curve_restarts_on_every_note() {
+    // shut for a quarter second, open, then fade shut: a per-note envelope on the amp
+    let mut e = Engine::new(44_100.0);
+    e.clear_mod_slots();
+    e.add_mod_slot(ModSlot::new(ModSource::Lfo0, ModDest::Amp, 1.0)).unwrap();
+    assert_eq!(e.set_lfo_points(0, &[0.0, -1.0, 0.0, 0.5, -1.0, 0.0, 0.5, 0.0, 0.0, 1.0, -1.0, 0.0]), 4);
+    for (k, v) in [("waveform", 6.0), ("rate", 2.0), ("amount", 1.0), ("one_shot", 1.0)] {
+        e.set_parameter(&format!("lfo0_{k}"), v);
+    }
+    e.set_parameter("fx_delay_wet", 0.0);
+    e.set_parameter("fx_reverb_wet", 0.0);
+    let peak = |e: &mut Engine, frames| {
+        let mut out = vec![0.0; frames];
+        e.render(&mut out);
+        out.iter().map(|s| s.abs()).fold(0.0, f32::max)
+    };
+    e.note_on(60, 1.0);
+    assert!(peak(&mut e, 8820) < 1e-4, "first stage holds the amp shut");
+    assert!(peak(&mut e, 8820) > 0.05, "second stage opens it");
+    e.render(&mut vec![0.0; 8820]);
+    assert!(peak(&mut e, 4410) < 1e-3, "held at the end level");
+    e.note_off(60);
+    e.note_on(64, 1.0);
+    assert!(peak(&mut e, 8000) < 1e-3, "restarted at the first point");
+    assert_eq!(e.lfo_points(0).map(<[_]>::len), Some(4));
+}
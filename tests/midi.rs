@@ -0,0 +1,75 @@
+// Raw MIDI parsing and how the engine acts on it.
+use serum_wasm_backend::midi::{MidiMessage, MidiParser};
+use serum_wasm_backend::Engine;
+
+fn parse(bytes: &[u8]) -> Vec<MidiMessage> {
+    MidiParser::new().parse(bytes).collect()
+}
+
+#[test]
+fn decodes_channel_messages() {
+    use MidiMessage::*;
+    assert_eq!(
+        parse(&[0x93, 60, 100, 0x83, 60, 64, 0xB1, 1, 127, 0xC2, 5, 0xD0, 80, 0xA4, 61, 30, 0xE0, 0x00, 0x40, 0xE0, 0x7F, 0x7F, 0xE0, 0, 0]),
+        [
+            NoteOn { channel: 3, note: 60, velocity: 100 },
+            NoteOff { channel: 3, note: 60, velocity: 64 },
+            ControlChange { channel: 1, controller: 1, value: 127 },
+            ProgramChange { channel: 2, program: 5 },
+            ChannelPressure { channel: 0, pressure: 80 },
+            PolyPressure { channel: 4, note: 61, pressure: 30 },
+            PitchBend { channel: 0, value: 0 },
+            PitchBend { channel: 0, value: 8191 },
+            PitchBend { channel: 0, value: -8192 },
+        ]
+    );
+}
+
+#[test]
+fn running_status_and_zero_velocity_note_off() {
+    use MidiMessage::*;
+    assert_eq!(
+        parse(&[0x90, 60, 100, 64, 90, 60, 0]),
+        [
+            NoteOn { channel: 0, note: 60, velocity: 100 },
+            NoteOn { channel: 0, note: 64, velocity: 90 },
+            NoteOff { channel: 0, note: 60, velocity: 0 },
+        ]
+    );
+}
+
+#[test]
+fn realtime_sysex_and_stray_bytes_are_skipped() {
+    use MidiMessage::*;
+    // stray data before any status, clock inside a message, a SysEx dump, then
+    // data bytes after the SysEx with no status of their own
+    assert_eq!(
+        parse(&[12, 34, 0x90, 60, 0xF8, 100, 0xF0, 0x7E, 0x01, 0x02, 0xF7, 61, 100, 0xC0, 7]),
+        [NoteOn { channel: 0, note: 60, velocity: 100 }, ProgramChange { channel: 0, program: 7 }]
+    );
+}
+
+#[test]
+fn messages_may_span_calls() {
+    let mut p = MidiParser::new();
+    assert_eq!(p.parse(&[0x90, 60]).count(), 0);
+    assert_eq!(p.parse(&[100]).collect::<Vec<_>>(), [MidiMessage::NoteOn { channel: 0, note: 60, velocity: 100 }]);
+}
+
+#[test]
+fn engine_plays_and_releases_notes_from_bytes() {
+    let mut e = Engine::new(44_100.0);
+    e.process_midi(&[0x90, 60, 127, 64, 127]);
+    assert_eq!(e.active_voices(), 2);
+    e.process_midi(&[0xE0, 0x00, 0x60, 0xC0, 9]);
+    assert_eq!(e.pitch_bend(), 0.5);
+    assert_eq!(e.program(), 9);
+
+    e.process_midi(&[0x90, 60, 0, 0x80, 64, 0]);
+    let mut out = vec![0.0; 44_100];
+    e.render(&mut out);
+    assert_eq!(e.active_voices(), 0, "both notes released and finished");
+
+    e.process_midi(&[0x90, 60, 127, 0xB0, 120, 0]);
+    assert_eq!(e.active_voices(), 0, "all sound off");
+}
@@ -0,0 +1,112 @@
+// Slot-based modulation matrix.
+use serum_wasm_backend::modulation::{ModDest, ModError, ModMatrix, ModSlot, ModSource, MOD_SLOTS};
+use serum_wasm_backend::Engine;
+
+fn sources(pairs: &[(ModSource, f32)]) -> [f32; ModSource::COUNT] {
+    let mut src = [0.0; ModSource::COUNT];
+    for &(s, v) in pairs {
+        src[s as usize] = v;
+    }
+    src
+}
+
+#[test]
+fn polarity_and_aux_scale_the_source() {
+    let mut m = ModMatrix::default();
+    m.clear();
+    // unipolar velocity read as bipolar: 0.25 -> -0.5
+    m.add(ModSlot { bipolar: true, ..ModSlot::new(ModSource::Velocity, ModDest::Amp, 1.0) }).unwrap();
+    // bipolar LFO read as unipolar and scaled by the mod wheel: (-1 -> 0) ... (0 -> 0.5) * 0.5
+    m.add(ModSlot {
+        bipolar: false,
+        aux: Some(ModSource::ModWheel),
+        ..ModSlot::new(ModSource::Lfo0, ModDest::FilterDrive, 1.0)
+    })
+    .unwrap();
+
+    let src = sources(&[(ModSource::Velocity, 0.25), (ModSource::Lfo0, 0.0), (ModSource::ModWheel, 0.5)]);
+    let d = m.evaluate(&src, false);
+    assert!((d[ModDest::Amp.index()] + 0.5).abs() < 1e-6);
+    assert!((d[ModDest::FilterDrive.index()] - 0.25).abs() < 1e-6);
+}
+
+#[test]
+fn global_destinations_only_take_global_sources() {
+    let mut m = ModMatrix::default();
+    m.clear();
+    m.add(ModSlot::new(ModSource::Macro0, ModDest::Lfo0Rate, 0.5)).unwrap();
+    m.add(ModSlot::new(ModSource::Velocity, ModDest::MasterGain, 1.0)).unwrap();
+    m.add(ModSlot::new(ModSource::Macro0, ModDest::FilterCutoff, 1.0)).unwrap();
+
+    let src = sources(&[(ModSource::Macro0, 1.0), (ModSource::Velocity, 1.0)]);
+    let g = m.evaluate(&src, true);
+    assert_eq!(g[ModDest::Lfo0Rate.index()], 0.5 * ModDest::Lfo0Rate.depth());
+    assert_eq!(g[ModDest::MasterGain.index()], 0.0);
+    assert_eq!(g[ModDest::FilterCutoff.index()], 0.0);
+
+    let v = m.evaluate(&src, false);
+    assert_eq!(v[ModDest::FilterCutoff.index()], ModDest::FilterCutoff.depth());
+    assert_eq!(v[ModDest::Lfo0Rate.index()], 0.0);
+}
+
+#[test]
+fn slots_fill_up_and_free() {
+    let mut m = ModMatrix::default();
+    m.clear();
+    for i in 0..MOD_SLOTS {
+        assert_eq!(m.add(ModSlot::new(ModSource::Random, ModDest::VoicePan, 0.1)), Ok(i));
+    }
+    assert_eq!(m.add(ModSlot::new(ModSource::Random, ModDest::VoicePan, 0.1)), Err(ModError::MatrixFull));
+    assert!(m.remove(5));
+    assert!(!m.remove(5));
+    assert_eq!(m.add(ModSlot::new(ModSource::Key, ModDest::Osc0Pitch, 0.1)), Ok(5));
+    assert_eq!(m.iter().count(), MOD_SLOTS);
+}
+
+#[test]
+fn names_parse_and_report_unknowns() {
+    let slot = ModSlot::parse("env1", "filter_cutoff", 0.5, false, "velocity").unwrap();
+    assert_eq!((slot.source, slot.dest, slot.aux), (ModSource::Env1, ModDest::FilterCutoff, Some(ModSource::Velocity)));
+    assert_eq!(
+        ModSlot::parse("lfo9", "filter_cutoff", 0.5, false, "").unwrap_err().code(),
+        "unknown_source"
+    );
+    assert_eq!(
+        ModSlot::parse("lfo0", "nowhere", 0.5, false, "").unwrap_err(),
+        ModError::UnknownDestination("nowhere".into())
+    );
+    for s in ModSource::ALL {
+        assert_eq!(ModSource::from_name(s.name()), Some(s));
+    }
+    for d in ModDest::ALL {
+        assert_eq!(ModDest::from_name(d.name()), Some(d));
+    }
+}
+
+#[test]
+fn legacy_route_names_edit_matching_slots() {
+    let mut m = ModMatrix::default();
+    m.set_by_name("mod_lfo0_to_cutoff", -0.4);
+    assert_eq!(m.iter().count(), 1);
+    assert_eq!(m.get(0).unwrap().amount, -0.4);
+    m.set_by_name("mod_env_to_cutoff", 0.6);
+    let i = m.find(ModSource::Env1, ModDest::FilterCutoff).unwrap();
+    m.set_by_name(&format!("mod_slot{i}_amount"), 0.2);
+    assert_eq!(m.get(i).unwrap().amount, 0.2);
+}
+
+#[test]
+fn macro_on_cutoff_changes_the_sound() {
+    let render = |macro_value: f32| {
+        let mut e = Engine::with_seed(48_000.0, 1);
+        e.set_parameter("filter_cutoff", 40.0);
+        e.add_mod_slot(ModSlot::new(ModSource::Macro0, ModDest::FilterCutoff, 1.0)).unwrap();
+        e.set_parameter("macro0", macro_value);
+        e.note_on(48, 1.0);
+        let mut out = vec![0.0; 4096];
+        e.render(&mut out);
+        out.iter().skip(1024).map(|s| s * s).sum::<f32>()
+    };
+    let (hi, lo) = (render(1.0), render(0.0));
+    assert!(hi > 4.0 * lo, "{hi} {lo}");
+}
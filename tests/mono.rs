@@ -0,0 +1,168 @@
+// Mono / legato note priority, the held-note stack and portamento.
+use serum_wasm_backend::mono::{MonoSettings, NotePriority, NoteStack};
+use serum_wasm_backend::Engine;
+
+const SR: f32 = 44_100.0;
+
+fn engine(mode: f32) -> Engine {
+    let mut e = Engine::new(SR);
+    e.set_parameter("fx_delay_wet", 0.0);
+    e.set_parameter("fx_reverb_wet", 0.0);
+    e.set_parameter("env_release", 0.01);
+    e.set_parameter("osc0_waveform", 0.0);
+    e.set_parameter("osc1_gain", 0.0);
+    e.set_parameter("filter_cutoff", 20_000.0);
+    e.set_parameter("voice_mode", mode);
+    e
+}
+
+fn render(e: &mut Engine, frames: usize) -> Vec<f32> {
+    let mut out = vec![0.0; frames];
+    e.render(&mut out);
+    out
+}
+
+fn crossings(out: &[f32]) -> usize {
+    out.windows(2).filter(|w| w[0] <= 0.0 && w[1] > 0.0).count()
+}
+
+fn peak(out: &[f32]) -> f32 {
+    out.iter().map(|s| s.abs()).fold(0.0, f32::max)
+}
+
+#[test]
+fn note_stack_picks_by_priority() {
+    let mut s = NoteStack::default();
+    for n in [60, 67, 55, 64] {
+        s.push(n);
+    }
+    assert_eq!(s.pick(NotePriority::Last), Some(64));
+    assert_eq!(s.pick(NotePriority::Low), Some(55));
+    assert_eq!(s.pick(NotePriority::High), Some(67));
+    s.push(60);
+    assert_eq!(s.pick(NotePriority::Last), Some(60), "re-pressed moves to the top");
+    assert!(s.remove(60) && !s.remove(60));
+    assert_eq!(s.pick(NotePriority::Last), Some(64));
+}
+
+#[test]
+fn mono_returns_to_the_previous_held_note() {
+    let mut e = engine(1.0);
+    e.note_on(60, 1.0);
+    e.note_on(64, 1.0);
+    render(&mut e, 441);
+    assert_eq!(e.active_notes(), [64]);
+    e.note_off(64);
+    render(&mut e, 441);
+    assert_eq!(e.active_notes(), [60]);
+    e.note_off(60);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 0);
+}
+
+#[test]
+fn low_note_priority_ignores_higher_keys() {
+    let mut e = engine(1.0);
+    e.set_parameter("note_priority", 1.0);
+    e.note_on(60, 1.0);
+    e.note_on(64, 1.0);
+    assert_eq!(e.active_notes(), [60]);
+    e.note_on(55, 1.0);
+    assert_eq!(e.active_notes(), [55]);
+    e.note_off(64);
+    e.note_off(55);
+    assert_eq!(e.active_notes(), [60]);
+}
+
+#[test]
+fn changing_mode_or_priority_keeps_the_held_keys() {
+    // mono, then low priority: the low key takes over and both still release
+    let mut e = engine(1.0);
+    e.note_on(60, 1.0);
+    e.note_on(64, 1.0);
+    e.set_parameter("note_priority", 1.0);
+    assert_eq!(e.active_notes(), [60]);
+    e.note_off(60);
+    assert_eq!(e.active_notes(), [64]);
+    e.note_off(64);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 0);
+
+    // poly chord into mono keeps just the picked note
+    let mut e = engine(0.0);
+    e.note_on(60, 1.0);
+    e.note_on(64, 1.0);
+    e.set_parameter("voice_mode", 1.0);
+    assert_eq!(e.active_notes(), [64]);
+    e.note_off(64);
+    assert_eq!(e.active_notes(), [60]);
+    e.note_off(60);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 0);
+
+    // and back to poly: the sounding key releases as usual
+    let mut e = engine(2.0);
+    e.note_on(60, 1.0);
+    e.note_on(64, 1.0);
+    e.set_parameter("voice_mode", 0.0);
+    e.note_off(64);
+    e.note_off(60);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 0);
+}
+
+#[test]
+fn legato_keeps_the_envelope_running() {
+    // slow attack: a retrigger starts again from silence
+    let after_second_note = |mode| {
+        let mut e = engine(mode);
+        e.set_parameter("env_attack", 1.0);
+        e.note_on(60, 1.0);
+        render(&mut e, 22_050);
+        e.note_on(64, 1.0);
+        peak(&render(&mut e, 882)[441..])
+    };
+    assert!(after_second_note(2.0) > 0.2, "legato carries on");
+    assert!(after_second_note(1.0) < 0.05, "mono retriggers");
+}
+
+#[test]
+fn glide_curves_and_modes() {
+    let settings = |k: &[(&str, f32)]| {
+        let mut m = MonoSettings::default();
+        m.set_by_name("glide_time", 0.1);
+        for &(n, v) in k {
+            m.set_by_name(n, v);
+        }
+        m
+    };
+    let after = |m: MonoSettings, semis: f32, secs: f32| {
+        let mut g = m.glide(semis, true).unwrap();
+        for _ in 0..(secs * SR) as usize {
+            g.tick(1.0 / SR);
+        }
+        g.offset
+    };
+    assert!((after(settings(&[]), 12.0, 0.05) - 6.0).abs() < 0.05, "linear is half way");
+    assert!(after(settings(&[("glide_curve", 1.0)]), 12.0, 0.05) < 2.0, "exponential front-loads");
+    assert!((after(settings(&[("glide_mode", 1.0)]), -24.0, 0.1) + 12.0).abs() < 0.05, "rate is per octave");
+    assert_eq!(after(settings(&[]), 12.0, 0.2), 0.0);
+
+    let legato_only = settings(&[("glide_legato", 1.0)]);
+    assert!(legato_only.glide(12.0, false).is_none());
+    assert!(legato_only.glide(12.0, true).is_some());
+    assert!(MonoSettings::default().glide(12.0, true).is_none(), "no glide time, no glide");
+}
+
+#[test]
+fn portamento_slides_between_overlapping_notes() {
+    let mut e = engine(2.0);
+    e.set_parameter("glide_time", 0.2);
+    e.note_on(57, 1.0);
+    render(&mut e, 4410);
+    e.note_on(69, 1.0);
+    let sliding = crossings(&render(&mut e, 8820));
+    assert!((50..=80).contains(&sliding), "220 -> 440 Hz over 0.2 s: {sliding}");
+    let arrived = crossings(&render(&mut e, 8820));
+    assert!((87..=89).contains(&arrived), "{arrived}");
+}
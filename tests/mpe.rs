@@ -0,0 +1,141 @@
+// MPE: zone configuration, per-note bend / pressure / timbre on member channels.
+use serum_wasm_backend::modulation::{ModDest, ModSlot, ModSource};
+use serum_wasm_backend::mpe::MpeZone;
+use serum_wasm_backend::Engine;
+
+const SR: f32 = 44_100.0;
+
+/// RPN `lsb` (msb 0) set to `value` on `channel`.
+fn rpn(channel: u8, lsb: u8, value: u8) -> [u8; 9] {
+    [0xB0 | channel, 101, 0, 0xB0 | channel, 100, lsb, 0xB0 | channel, 6, value]
+}
+
+fn mpe_engine() -> Engine {
+    let mut e = Engine::new(SR);
+    e.set_parameter("fx_delay_wet", 0.0);
+    e.set_parameter("fx_reverb_wet", 0.0);
+    e.set_parameter("env_release", 0.01);
+    e.set_parameter("osc0_waveform", 0.0);
+    e.set_parameter("osc1_gain", 0.0);
+    e.set_parameter("filter_cutoff", 20_000.0);
+    e.process_midi(&rpn(0, 6, 15));
+    e
+}
+
+fn render(e: &mut Engine, frames: usize) -> Vec<f32> {
+    let mut out = vec![0.0; frames];
+    e.render(&mut out);
+    out
+}
+
+fn pitch(e: &mut Engine) -> usize {
+    render(e, SR as usize).windows(2).filter(|w| w[0] <= 0.0 && w[1] > 0.0).count()
+}
+
+#[test]
+fn configuration_message_sets_up_the_zone() {
+    let mut e = Engine::new(SR);
+    assert_eq!(e.mpe().zone, MpeZone::Off);
+    e.process_midi(&rpn(0, 6, 15));
+    assert_eq!(e.mpe().zone, MpeZone::Lower);
+    assert!(e.mpe().is_member(1) && e.mpe().is_member(15) && !e.mpe().is_member(0));
+    assert_eq!(e.mpe().bend_range, 48.0);
+
+    e.process_midi(&rpn(15, 6, 4));
+    assert_eq!(e.mpe().zone, MpeZone::Upper);
+    assert!(e.mpe().is_member(11) && e.mpe().is_member(14) && !e.mpe().is_member(10) && !e.mpe().is_member(15));
+
+    e.process_midi(&rpn(0, 6, 0));
+    assert_eq!(e.mpe().zone, MpeZone::Off);
+}
+
+#[test]
+fn per_note_bend_only_moves_its_own_channel() {
+    let mut e = mpe_engine();
+    e.process_midi(&rpn(1, 0, 12));
+    // bend sent before the note still applies to it
+    e.process_midi(&[0xE1, 0x7F, 0x7F, 0x91, 69, 100]);
+    let bent = pitch(&mut e);
+    assert!((876..=884).contains(&bent), "{bent}");
+
+    e.process_midi(&[0x81, 69, 0, 0x92, 69, 100]);
+    render(&mut e, 4410);
+    let plain = pitch(&mut e);
+    assert!((438..=442).contains(&plain), "{plain}");
+}
+
+#[test]
+fn same_note_on_two_channels_releases_separately() {
+    let mut e = mpe_engine();
+    e.process_midi(&[0x91, 60, 100, 0x92, 60, 100, 0x81, 60, 0]);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 1);
+    e.process_midi(&[0x82, 60, 0]);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 0);
+}
+
+#[test]
+fn master_and_member_channel_notes_release_separately() {
+    let mut e = mpe_engine();
+    // master channel 1 and member channel 2 both hold the key
+    e.process_midi(&[0x90, 60, 100, 0x91, 60, 100, 0x80, 60, 0]);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 1);
+    // a channel with neither note releases nothing
+    e.process_midi(&[0x83, 60, 0]);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 1);
+    e.process_midi(&[0x81, 60, 0]);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 0);
+}
+
+#[test]
+fn member_channel_pedals_and_channel_mode_messages() {
+    // sustain on a member channel holds the zone, like on the master channel
+    let mut e = mpe_engine();
+    e.process_midi(&[0xB1, 64, 127, 0x91, 60, 100, 0x92, 64, 100, 0x81, 60, 0, 0x82, 64, 0]);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 2);
+    e.process_midi(&[0xB2, 64, 0]);
+    render(&mut e, 4410);
+    assert_eq!(e.active_voices(), 0);
+
+    // all notes off / all sound off on a member channel only reach its own notes
+    for cc in [123, 120] {
+        let mut e = mpe_engine();
+        e.process_midi(&[0xB0, 64, 127, 0x91, 60, 100, 0x92, 64, 100, 0x90, 67, 100]);
+        e.process_midi(&[0xB1, cc, 0]);
+        render(&mut e, 4410);
+        assert_eq!(e.active_voices(), 2, "cc {cc}");
+    }
+}
+
+#[test]
+fn pressure_and_timbre_are_per_voice_sources() {
+    for (source, msg) in [(ModSource::Timbre, [0xB1, 74, 127]), (ModSource::PolyAftertouch, [0xD1, 127, 0xF8])] {
+        let mut e = mpe_engine();
+        e.clear_mod_slots();
+        e.add_mod_slot(ModSlot { bipolar: false, ..ModSlot::new(source, ModDest::Amp, -1.0) }).unwrap();
+        e.process_midi(&[0xB1, 74, 0, 0xB2, 74, 0, 0x91, 60, 100]);
+        e.process_midi(&msg);
+        let silent = render(&mut e, 4410)[2205..].iter().map(|s| s.abs()).fold(0.0, f32::max);
+        assert!(silent < 1e-4, "{source:?} at full closes the amp");
+        e.process_midi(&[0x92, 64, 100]);
+        let other = render(&mut e, 4410)[2205..].iter().map(|s| s.abs()).fold(0.0, f32::max);
+        assert!(other > 0.01, "{source:?} on channel 2 is untouched");
+    }
+}
+
+#[test]
+fn member_bend_range_is_clamped_and_survives_a_preset() {
+    let mut e = mpe_engine();
+    e.process_midi(&rpn(1, 0, 127));
+    assert_eq!(e.mpe().bend_range, 96.0);
+
+    let mut back = Engine::new(SR);
+    back.import_preset(&e.export_preset()).unwrap();
+    assert_eq!(back.mpe().bend_range, 96.0);
+    assert_eq!(back.export_preset(), e.export_preset());
+}
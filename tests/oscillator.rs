@@ -0,0 +1,88 @@
+// Alias measurements for the band-limited oscillators.
+use serum_wasm_backend::fft::fft;
+use serum_wasm_backend::oscillator;
+use serum_wasm_backend::wavetable::Wavetable;
+use serum_wasm_backend::WAVETABLE_SIZE;
+
+const SR: f32 = 48_000.0;
+const N: usize = 16_384;
+
+/// Power that folded back below Nyquist (anything off the true harmonic series),
+/// relative to total power, in dB.
+fn alias_db(signal: &[f32], f0: f32) -> f32 {
+    let mut re: Vec<f32> = signal
+        .iter()
+        .enumerate()
+        .map(|(i, s)| s * 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / N as f32).cos()))
+        .collect();
+    let mut im = vec![0.0; N];
+    fft(&mut re, &mut im, false);
+
+    let bin_hz = SR / N as f32;
+    let harmonics: Vec<f32> = (1..).map(|k| k as f32 * f0).take_while(|f| *f < SR / 2.0).collect();
+    let (mut total, mut alias) = (0.0f64, 0.0f64);
+    for b in 1..N / 2 {
+        let p = (re[b] * re[b] + im[b] * im[b]) as f64;
+        total += p;
+        let f = b as f32 * bin_hz;
+        if !harmonics.iter().any(|h| (f - h).abs() <= 4.0 * bin_hz) {
+            alias += p;
+        }
+    }
+    10.0 * (alias / total).log10() as f32
+}
+
+fn render(f0: f32, osc: impl Fn(f32, f32) -> f32) -> Vec<f32> {
+    let dt = f0 / SR;
+    (0..N).map(|i| osc((i as f32 * dt).fract(), dt)).collect()
+}
+
+fn naive_saw(t: f32, _dt: f32) -> f32 {
+    2.0 * t - 1.0
+}
+
+#[test]
+fn blep_saw_folds_far_less_than_naive() {
+    let f0 = 3_520.0;
+    let naive = alias_db(&render(f0, naive_saw), f0);
+    let blep = alias_db(&render(f0, oscillator::saw), f0);
+    assert!(blep < -20.0, "blep saw alias {blep:.1} dB");
+    assert!(blep < naive - 10.0, "naive {naive:.1} dB vs blep {blep:.1} dB");
+}
+
+#[test]
+fn blep_square_and_triangle_are_clean() {
+    let f0 = 2_960.0;
+    let naive_tri = |t: f32, _dt: f32| 1.0 - 4.0 * ((t + 0.25).fract() - 0.5).abs();
+    let sq = alias_db(&render(f0, oscillator::square), f0);
+    let tri = alias_db(&render(f0, oscillator::triangle), f0);
+    let tri_naive = alias_db(&render(f0, naive_tri), f0);
+    assert!(sq < -24.0, "square alias {sq:.1} dB");
+    assert!(tri < -40.0, "triangle alias {tri:.1} dB");
+    assert!(tri < tri_naive - 6.0, "naive {tri_naive:.1} dB vs blamp {tri:.1} dB");
+}
+
+#[test]
+fn wavetable_mips_remove_folding() {
+    let raw: Vec<f32> = (0..WAVETABLE_SIZE)
+        .map(|i| 2.0 * (i as f32 / WAVETABLE_SIZE as f32) - 1.0)
+        .collect();
+    let wt = Wavetable::new(raw);
+    let f0 = 3_520.0;
+    let out = render(f0, |t, _| wt.sample(t, f0, SR));
+    let db = alias_db(&out, f0);
+    assert!(db < -40.0, "wavetable alias {db:.1} dB");
+}
+
+#[test]
+fn wt_position_crossfades_between_frames() {
+    let sine: Vec<f32> = (0..WAVETABLE_SIZE)
+        .map(|i| (2.0 * std::f32::consts::PI * i as f32 / WAVETABLE_SIZE as f32).sin())
+        .collect();
+    let wt = Wavetable::from_frames(vec![sine, vec![0.0; WAVETABLE_SIZE]]);
+    assert_eq!(wt.frame_count(), 2);
+    let peak = |pos: f32| wt.sample_at(pos, 0.25, 110.0, SR);
+    assert!((peak(0.0) - 1.0).abs() < 1e-3);
+    assert!((peak(0.5) - 0.5).abs() < 1e-3);
+    assert!(peak(1.0).abs() < 1e-3);
+}
@@ -0,0 +1,191 @@
+// Parameter registry: ids, ranges, tapers, formatting, and get/set by name.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use serum_wasm_backend::params::{self, Kind, Taper};
+use serum_wasm_backend::Engine;
+
+/// Counts this thread's heap allocations, so parallel tests don't interfere.
+struct Counting;
+thread_local! {
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+}
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+#[global_allocator]
+static COUNTING: Counting = Counting;
+
+const SR: f32 = 48_000.0;
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() <= 1e-4 * b.abs().max(1.0)
+}
+
+#[test]
+fn ids_and_names_are_unique_and_stable() {
+    let all = params::all();
+    let mut ids: Vec<u32> = all.iter().map(|p| p.id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), all.len());
+    let mut names: Vec<&str> = all.iter().map(|p| p.name.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+    assert_eq!(names.len(), all.len());
+
+    // these ids are a contract with saved automation; never renumber them
+    for (id, name) in [(100, "osc0_waveform"), (201, "osc1_gain"), (604, "filter_cutoff"), (700, "lfo0_rate"), (900, "master_gain"), (1300, "mod_slot0_amount")] {
+        assert_eq!(params::find(name).map(|p| p.id), Some(id), "{name}");
+        assert_eq!(params::by_id(id).map(|p| p.name.as_str()), Some(name));
+    }
+    for p in all {
+        assert_eq!(params::by_id(p.id).map(|q| q.name.as_str()), Some(p.name.as_str()));
+    }
+    for id in [0, 99, 112, 509, 1299, u32::MAX] {
+        assert!(params::by_id(id).is_none(), "{id}");
+    }
+}
+
+#[test]
+fn defaults_match_a_new_engine_and_sit_in_range() {
+    let e = Engine::new(SR);
+    for p in params::all() {
+        assert!(p.min <= p.default && p.default <= p.max, "{} default {} outside {}..{}", p.name, p.default, p.min, p.max);
+        assert_eq!(e.get_parameter(&p.name), Some(p.default), "{}", p.name);
+        if p.kind == Kind::Choice {
+            assert_eq!(p.choices.len() as f32, p.max + 1.0, "{}", p.name);
+        }
+    }
+}
+
+#[test]
+fn every_parameter_reads_back_what_was_set() {
+    // at 1.0 only the LFO phase wraps (to 0); every other top end is reachable at any rate
+    for sr in [44_100.0, SR] {
+        for n in [0.0, 0.3, 0.8, 0.99, 1.0] {
+            let mut e = Engine::new(sr);
+            // slot amounts only stick to slots that exist
+            for p in params::all().iter().filter(|p| !p.name.starts_with("mod_slot")) {
+                let v = if n == 1.0 && p.name.ends_with("_phase") { p.min } else { p.denormalize(n) };
+                assert!(e.set_parameter(&p.name, v), "{}", p.name);
+                let got = e.get_parameter(&p.name).unwrap();
+                assert!(close(got, v), "{} at {n} ({sr} Hz): set {v}, got {got}", p.name);
+            }
+        }
+    }
+
+    // the cutoff keeps what was set; only the filter itself stops short of Nyquist
+    let mut e = Engine::new(44_100.0);
+    assert!(e.set_parameter_normalized("filter_cutoff", 1.0));
+    assert_eq!(e.get_parameter("filter_cutoff"), Some(22_000.0));
+    let mut back = Engine::new(44_100.0);
+    back.import_preset(&e.export_preset()).unwrap();
+    assert_eq!(back.get_parameter("filter_cutoff"), Some(22_000.0));
+}
+
+#[test]
+fn unknown_names_are_rejected() {
+    let mut e = Engine::new(SR);
+    let before = e.get_parameter("filter_cutoff");
+    assert!(!e.set_parameter("filter_cutof", 100.0));
+    assert!(!e.set_parameter_normalized("filter_cutof", 0.5));
+    assert_eq!(e.get_parameter("filter_cutoff"), before);
+    assert_eq!(e.get_parameter("filter_cutof"), None);
+    assert_eq!(e.get_parameter("osc2_gain"), None);
+    assert_eq!(e.get_parameter("mod_slot99_amount"), None);
+    assert!(params::find("macro4").is_none());
+}
+
+#[test]
+fn aliases_resolve_to_the_canonical_parameter() {
+    let mut e = Engine::new(SR);
+    assert!(e.set_parameter("osc1_volume", 0.25));
+    assert_eq!(e.get_parameter("osc1_gain"), Some(0.25));
+    assert!(e.set_parameter("env1_attack", 0.5));
+    assert_eq!(e.get_parameter("filter_env_attack"), Some(0.5));
+    assert!(e.set_parameter("osc0_unison_voices", 5.0));
+    assert_eq!(e.get_parameter("osc0_unison"), Some(5.0));
+    assert!(e.set_parameter("mod_env_to_cutoff", 0.4));
+    assert_eq!(e.get_parameter("mod_env1_to_cutoff"), Some(0.4));
+    assert_eq!(params::find("env1_decay").map(|p| p.name.as_str()), Some("filter_env_decay"));
+
+    // every mod envelope 1 setting and route keeps its old name
+    for p in params::all() {
+        let old = match (p.name.strip_prefix("filter_env_"), p.name.strip_prefix("mod_env1_to_")) {
+            (Some(key), _) => format!("env1_{key}"),
+            (_, Some(dest)) => format!("mod_env_to_{dest}"),
+            _ => continue,
+        };
+        assert_eq!(params::find(&old).map(|a| a.id), Some(p.id), "{old}");
+    }
+
+    // lookups, aliases included, don't allocate
+    let before = ALLOCS.with(Cell::get);
+    for name in ["filter_cutoff", "osc0_volume", "env1_release", "mod_env_to_amp"] {
+        assert!(e.set_parameter(name, 0.3));
+        assert!(e.get_parameter(name).is_some());
+    }
+    assert_eq!(ALLOCS.with(Cell::get) - before, 0);
+}
+
+#[test]
+fn values_are_clamped_and_stepped() {
+    let mut e = Engine::new(SR);
+    e.set_parameter("master_gain", 9.0);
+    assert_eq!(e.get_parameter("master_gain"), Some(2.0));
+    e.set_parameter("osc0_coarse", 7.4);
+    assert_eq!(e.get_parameter("osc0_coarse"), Some(7.0));
+    e.set_parameter("filter_type", 42.0);
+    assert_eq!(e.get_parameter("filter_type"), Some(6.0));
+    e.set_parameter("voice_retrigger", 0.2);
+    assert_eq!(e.get_parameter("voice_retrigger"), Some(0.0));
+}
+
+#[test]
+fn tapers_map_knob_travel() {
+    let cutoff = params::find("filter_cutoff").unwrap();
+    assert_eq!(cutoff.taper, Taper::Exponential);
+    assert!(close(cutoff.denormalize(0.5), (20.0f32 * 22_000.0).sqrt()));
+    assert!(close(cutoff.normalize(cutoff.denormalize(0.37)), 0.37));
+    assert_eq!(cutoff.denormalize(-1.0), 20.0);
+
+    let attack = params::find("env_attack").unwrap();
+    assert!(matches!(attack.taper, Taper::Skew(s) if s > 1.0));
+    // the low end gets most of the travel
+    assert!(attack.denormalize(0.5) < 0.2 * attack.max);
+    assert!(close(attack.normalize(attack.denormalize(0.6)), 0.6));
+
+    let pan = params::find("osc0_pan").unwrap();
+    assert_eq!(pan.denormalize(0.5), 0.0);
+
+    let mut e = Engine::new(SR);
+    assert!(e.set_parameter_normalized("filter_cutoff", 1.0));
+    assert_eq!(e.get_parameter("filter_cutoff"), Some(22_000.0));
+    assert!(e.set_parameter_normalized("lfo0_division", 0.0));
+    assert_eq!(e.get_parameter("lfo0_division"), Some(0.0));
+}
+
+#[test]
+fn display_formatting() {
+    let fmt = |name: &str, v: f32| params::find(name).unwrap().format(v);
+    assert_eq!(fmt("filter_cutoff", 1200.0), "1.20 kHz");
+    assert_eq!(fmt("filter_cutoff", 440.0), "440 Hz");
+    assert_eq!(fmt("env_release", 0.035), "35 ms");
+    assert_eq!(fmt("env_release", 2.5), "2.50 s");
+    assert_eq!(fmt("osc0_pan", -0.4), "L40");
+    assert_eq!(fmt("osc0_pan", 0.0), "C");
+    assert_eq!(fmt("fx_delay_wet", 0.35), "35%");
+    assert_eq!(fmt("osc1_coarse", 7.0), "7");
+    assert_eq!(fmt("osc0_detune", -12.5), "-12.5 ct");
+    assert_eq!(fmt("lfo0_division", 10.0), "1/8d");
+    assert_eq!(fmt("osc0_waveform", 5.0), "Wavetable");
+    assert_eq!(fmt("lfo0_sync", 1.0), "On");
+    assert_eq!(fmt("tempo", 128.0), "128.0 BPM");
+}
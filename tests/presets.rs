@@ -0,0 +1,296 @@
+// Presets: versioned JSON that round-trips the whole patch, v1 migration, validation errors.
+use serum_wasm_backend::json::{self, Json};
+use serum_wasm_backend::modulation::{ModDest, ModSlot, ModSource};
+use serum_wasm_backend::params;
+use serum_wasm_backend::preset::{PresetError, PRESET_VERSION};
+use serum_wasm_backend::rng::Rng;
+use serum_wasm_backend::Engine;
+
+const SR: f32 = 48_000.0;
+
+/// Every preset setting away from its default.
+fn busy_engine() -> Engine {
+    let mut e = Engine::with_seed(SR, 7);
+    for p in params::all().iter().filter(|p| p.in_preset()) {
+        assert!(e.set_parameter(&p.name, p.denormalize(0.37)), "{}", p.name);
+    }
+    e.set_parameter("voice_mode", 0.0);
+    e.set_parameter("env_delay", 0.0);
+    e.set_parameter("osc0_gain", 0.1f32.sqrt());
+    e.clear_mod_slots();
+    e.add_mod_slot(ModSlot::new(ModSource::Lfo1, ModDest::Amp, -0.123_456_79)).unwrap();
+    e.add_mod_slot(ModSlot::new(ModSource::Env2, ModDest::Osc1WtPosition, 0.5)).unwrap();
+    e.add_mod_slot(ModSlot::parse("velocity", "filter_cutoff", 0.7, true, "mod_wheel").unwrap()).unwrap();
+    e.remove_mod_slot(1);
+    assert_eq!(e.set_lfo_points(1, &[0.0, 0.2, 0.5, 0.3, 1.0, -0.4, 1.0, 0.2, 0.0]), 3);
+    e.load_scala("! test.scl\n5-EDO\n5\n240.\n480.\n720.\n960.\n2/1\n", None).unwrap();
+    e.set_reference_pitch(432.0);
+    e.set_smoothing("filter_cutoff", 0.137);
+    let frames: Vec<f32> = (0..3 * 2048).map(|i| ((i as f32) * 0.001_234_5).sin() * 0.987_654_3).collect();
+    assert_eq!(e.set_wavetable_frames(1, &frames, 2048), 3);
+    e
+}
+
+fn render(e: &mut Engine) -> Vec<f32> {
+    e.note_on(60, 0.8);
+    e.note_on(67, 0.6);
+    let mut out = vec![0.0; 4096];
+    e.render(&mut out);
+    out
+}
+
+fn import_err(e: &mut Engine, text: &str) -> PresetError {
+    e.import_preset(text).expect_err("import should fail")
+}
+
+#[test]
+fn export_import_round_trips_bit_exactly() {
+    let mut a = busy_engine();
+    let text = a.export_preset();
+    let mut b = Engine::with_seed(SR, 7);
+    b.import_preset(&text).unwrap();
+    assert_eq!(b.export_preset(), text);
+
+    for p in params::all().iter().filter(|p| p.in_preset()) {
+        assert_eq!(b.get_parameter(&p.name).map(f32::to_bits), a.get_parameter(&p.name).map(f32::to_bits), "{}", p.name);
+    }
+    let slots: Vec<_> = b.mod_matrix().iter().map(|(i, s)| (i, *s)).collect();
+    assert_eq!(slots, a.mod_matrix().iter().map(|(i, s)| (i, *s)).collect::<Vec<_>>());
+    assert_eq!(slots.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 2]);
+    assert_eq!(b.tuning().table(), a.tuning().table());
+    assert_eq!(b.lfo_points(1), a.lfo_points(1));
+    assert_eq!(b.smoothing("filter_cutoff"), Some(0.137));
+    // all frames, full length (the old format kept only the first 256 samples)
+    assert_eq!(b.wavetable_frame_count(1), 3);
+    assert_eq!(b.wavetable(1).unwrap().len(), 2048);
+    assert_eq!(b.wavetable(1), a.wavetable(1));
+
+    let (ra, rb) = (render(&mut a), render(&mut b));
+    assert!(ra.iter().any(|&s| s != 0.0));
+    assert!(ra.iter().zip(&rb).all(|(x, y)| x.to_bits() == y.to_bits()));
+}
+
+/// A random MIDI message of the kinds that reach patch state: RPNs, CCs, MTS.
+fn random_midi(rng: &mut Rng) -> Vec<u8> {
+    let mut byte = |n: u64| (rng.next_u64() % n) as u8;
+    let channel = byte(16);
+    match byte(6) {
+        // bend range (any channel, master or member) or MPE configuration
+        0 => {
+            let lsb = if byte(2) == 0 { 0 } else { 6 };
+            vec![0xB0 | channel, 101, 0, 0xB0 | channel, 100, lsb, 0xB0 | channel, 6, byte(128)]
+        }
+        1 => vec![0xB0 | channel, byte(128), byte(128)],
+        2 => vec![0xF0, 0x7F, 0x7F, 0x08, 0x02, 0x00, 0x01, byte(128), byte(128), byte(128), byte(128), 0xF7],
+        3 => {
+            let mut dump = vec![0xF0, 0x7E, 0x7F, 0x08, 0x01, 0x00];
+            dump.extend_from_slice(b"random tuning   ");
+            for _ in 0..128 {
+                dump.extend_from_slice(&[byte(128), byte(128), byte(128)]);
+            }
+            dump.extend_from_slice(&[0x00, 0xF7]);
+            dump
+        }
+        4 => vec![0x90 | channel, byte(128), 1 + byte(127)],
+        _ => vec![0xE0 | channel, byte(128), byte(128), 0xD0 | channel, byte(128), 0xC0 | channel, byte(128)],
+    }
+}
+
+#[test]
+fn whatever_midi_did_to_the_patch_round_trips() {
+    let mut rng = Rng::new(25);
+    for run in 0..64 {
+        let mut a = Engine::new(SR);
+        for _ in 0..40 {
+            a.process_midi(&random_midi(&mut rng));
+        }
+        let text = a.export_preset();
+        let mut b = Engine::new(SR);
+        if let Err(err) = b.import_preset(&text) {
+            panic!("run {run}: {err}");
+        }
+        assert_eq!(b.export_preset(), text, "run {run}");
+    }
+}
+
+#[test]
+fn exports_are_versioned_and_skip_performance_state() {
+    let mut e = Engine::new(SR);
+    e.set_parameter("mod_wheel", 0.8);
+    e.set_parameter("tempo", 140.0);
+    let doc = json::parse(&e.export_preset()).unwrap();
+    assert_eq!(doc.get("version").and_then(Json::as_f32), Some(PRESET_VERSION as f32));
+    let params = doc.get("params").unwrap();
+    for name in ["lfo0_rate", "lfo1_amount", "voice_retrigger", "fx_delay_time", "filter_env", "mpe_zone", "macro3"] {
+        assert!(params.get(name).is_some(), "{name}");
+    }
+    for name in ["mod_wheel", "tempo", "sustain_pedal", "mod_slot0_amount", "mod_lfo0_to_amp"] {
+        assert!(params.get(name).is_none(), "{name}");
+    }
+}
+
+#[test]
+fn missing_fields_take_new_engine_values() {
+    let mut e = busy_engine();
+    e.import_preset(r#"{"version": 2, "params": {"filter_cutoff": 1234.5}}"#).unwrap();
+    let mut fresh = Engine::new(SR);
+    fresh.set_parameter("filter_cutoff", 1234.5);
+    assert_eq!(e.export_preset(), fresh.export_preset());
+    assert_eq!(e.tuning().freq(69), 440.0);
+    assert_eq!(e.wavetable_frame_count(1), 1);
+}
+
+#[test]
+fn invalid_fields_are_all_reported_and_nothing_changes() {
+    let mut e = busy_engine();
+    let before = e.export_preset();
+    let err = import_err(
+        &mut e,
+        r#"{
+            "version": 2,
+            "params": {"filter_cutoff": 5, "osc0_gain": "loud", "osc0_coarse": 2.5, "voice_retrigger": 0.5,
+                       "filter_cutof": 100, "mod_wheel": 1},
+            "mod_slots": [
+                {"index": 0, "source": "lfo0", "destination": "amp", "amount": 0.5, "bipolar": true, "aux": ""},
+                {"index": 0, "source": "lfo9", "destination": "amp", "amount": 2, "bipolar": true}
+            ],
+            "tuning": [440],
+            "smoothing": {"tempo": 0.1},
+            "wavetables": [{"frame_len": 2048, "data": "AAAA"}, {"frame_len": 2048, "data": "not base64!"}],
+            "colour": "red"
+        }"#,
+    );
+    assert_eq!(err.code(), "invalid_fields");
+    let fields: Vec<&str> = err.fields().iter().map(|f| f.field.as_str()).collect();
+    assert_eq!(
+        fields,
+        [
+            "params.filter_cutoff",
+            "params.osc0_gain",
+            "params.osc0_coarse",
+            "params.voice_retrigger",
+            "params.filter_cutof",
+            "params.mod_wheel",
+            "mod_slots[1].index",
+            "mod_slots[1].amount",
+            "tuning",
+            "smoothing.tempo",
+            "wavetables[0].data",
+            "wavetables[1].data",
+            "colour",
+        ]
+    );
+    assert_eq!(err.fields()[0].reason, "expected 20..22000");
+    assert_eq!(err.fields()[4].reason, "unknown parameter");
+    assert!(err.to_string().contains("params.osc0_coarse: expected a whole number"));
+    assert_eq!(e.export_preset(), before);
+
+    // a slot whose names don't resolve points at the name
+    let err = import_err(
+        &mut e,
+        r#"{"version": 2, "mod_slots": [{"index": 3, "source": "lfo0", "destination": "amp", "amount": 0.5, "bipolar": false, "aux": "nope"}]}"#,
+    );
+    assert_eq!(err.fields()[0].field, "mod_slots[0].aux");
+}
+
+#[test]
+fn unusable_documents_are_rejected() {
+    let mut e = Engine::new(SR);
+    assert_eq!(import_err(&mut e, r#"{"version": 2,"#).code(), "syntax");
+    assert_eq!(import_err(&mut e, "[1, 2]").code(), "not_an_object");
+    let err = import_err(&mut e, r#"{"version": 3}"#);
+    assert_eq!(err, PresetError::UnsupportedVersion(3));
+    assert_eq!(import_err(&mut e, r#"{"version": "2"}"#).fields()[0].field, "version");
+}
+
+#[test]
+fn version_1_presets_are_migrated() {
+    // what the old UI saved: flat keys, fixed routes, 256-sample tables as index-keyed objects
+    let mut cycle = String::from("{");
+    for i in 0..256 {
+        let s = if i < 128 { 1.0 } else { -1.0 };
+        cycle += &format!("{}\"{i}\":{s}", if i > 0 { "," } else { "" });
+    }
+    cycle += "}";
+    let v1 = format!(
+        r#"{{"master_gain": 0.5, "osc1_volume": 0.3, "osc0_coarse": 99, "env1_attack": 0.25,
+            "filter_cutoff": 900, "mod_lfo0_to_amp": 0.4, "mod_env_to_wtpos": 0.2,
+            "lfo1_points": [0, 0, 0, 1, 1, 0], "wavetables": [{cycle}]}}"#
+    );
+    let mut e = busy_engine();
+    e.import_preset(&v1).unwrap();
+    assert_eq!(e.get_parameter("master_gain"), Some(0.5));
+    assert_eq!(e.get_parameter("osc1_gain"), Some(0.3));
+    assert_eq!(e.get_parameter("osc0_coarse"), Some(48.0));
+    assert_eq!(e.get_parameter("filter_env_attack"), Some(0.25));
+    assert_eq!(e.get_parameter("filter_cutoff"), Some(900.0));
+    // left out, so back to defaults
+    assert_eq!(e.get_parameter("lfo0_rate"), params::find("lfo0_rate").map(|p| p.default));
+
+    // fixed routes become slots after the historical default one
+    let slots: Vec<_> = e.mod_matrix().iter().map(|(_, s)| (s.source, s.dest, s.amount)).collect();
+    assert_eq!(
+        slots,
+        [
+            (ModSource::Lfo0, ModDest::FilterCutoff, 0.3),
+            (ModSource::Lfo0, ModDest::Amp, 0.4),
+            (ModSource::Env1, ModDest::Osc0WtPosition, 0.2),
+            (ModSource::Env1, ModDest::Osc1WtPosition, 0.2),
+        ]
+    );
+    assert_eq!(e.lfo_points(1).map(<[_]>::len), Some(2));
+
+    let table = e.wavetable(0).unwrap();
+    assert_eq!(table.len(), 2048);
+    assert_eq!(e.wavetable_frame_count(0), 1);
+    assert!(table[100] > 0.99 && table[1500] < -0.99);
+
+    // re-exported as the current version
+    let doc = json::parse(&e.export_preset()).unwrap();
+    assert_eq!(doc.get("version").and_then(Json::as_f32), Some(PRESET_VERSION as f32));
+
+    // bad v1 values are reported under the v2 layout
+    let err = import_err(&mut e, r#"{"osc0_gain": "x", "bogus": 1}"#);
+    let fields: Vec<&str> = err.fields().iter().map(|f| f.field.as_str()).collect();
+    assert_eq!(fields, ["params.osc0_gain", "params.bogus"]);
+}
+
+#[test]
+fn json_numbers_and_strings_round_trip() {
+    for v in [0.1f32, -0.0, 1.0e-38, f32::MAX, f32::MIN_POSITIVE, 16_777_217.0, 0.333_333_34] {
+        let back = json::parse(&Json::number(v).to_string()).unwrap().as_f32().unwrap();
+        assert_eq!(back.to_bits(), v.to_bits(), "{v}");
+    }
+    let s = Json::String("tab\t \"q\" \\ \u{1} é 🎹".into());
+    assert_eq!(json::parse(&s.to_string()).unwrap(), s);
+    assert_eq!(json::parse(r#""\ud83c\udfb9\u00e9""#).unwrap(), Json::String("🎹é".into()));
+    for bad in ["", "01", "1.", "-", "[1,]", "{\"a\" 1}", "\"\\ud83c\"", "\"\\u12g4\"", "tru", "[1] x"] {
+        assert!(json::parse(bad).is_err(), "{bad:?}");
+    }
+    let deep = "[".repeat(200) + &"]".repeat(200);
+    assert!(json::parse(&deep).is_err());
+}
+
+#[test]
+fn non_finite_values_still_export_valid_json() {
+    for v in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        assert_eq!(Json::number(v), Json::Null);
+    }
+    let mut e = Engine::new(SR);
+    e.set_parameter("master_gain", f32::NAN);
+    e.set_parameter("filter_cutoff", f32::INFINITY);
+    e.set_smoothing("filter_cutoff", f32::NAN);
+    e.clear_mod_slots();
+    e.add_mod_slot(ModSlot::new(ModSource::Lfo0, ModDest::Amp, f32::NAN)).unwrap();
+    let text = e.export_preset();
+    json::parse(&text).unwrap();
+
+    // and it loads back: NaN falls back to the default, infinity to the range limit
+    let fresh = Engine::new(SR);
+    let mut b = Engine::new(SR);
+    b.import_preset(&text).unwrap();
+    assert_eq!(b.get_parameter("master_gain"), fresh.get_parameter("master_gain"));
+    assert_eq!(b.get_parameter("filter_cutoff"), Some(params::find("filter_cutoff").unwrap().max));
+    assert_eq!(b.smoothing("filter_cutoff"), fresh.smoothing("filter_cutoff"));
+    assert_eq!(b.get_parameter("mod_slot0_amount"), Some(0.0));
+}
@@ -0,0 +1,129 @@
+// Parameter smoothing: jumps in gain / cutoff ramp instead of stepping.
+use serum_wasm_backend::smooth::{Smoothed, Smoother, DEFAULT_RAMP};
+use serum_wasm_backend::Engine;
+
+const SR: f32 = 48_000.0;
+
+/// A steady sine through a wide-open filter, no FX.
+fn sine_engine() -> Engine {
+    let mut e = Engine::new(SR);
+    for (k, v) in [
+        ("fx_delay_wet", 0.0),
+        ("fx_reverb_wet", 0.0),
+        ("osc0_waveform", 0.0),
+        ("osc1_gain", 0.0),
+        ("filter_env", 0.0),
+        ("filter_cutoff", 20_000.0),
+        ("env_attack", 0.001),
+        ("env_sustain", 1.0),
+    ] {
+        e.set_parameter(k, v);
+    }
+    e
+}
+
+fn render(e: &mut Engine, frames: usize) -> Vec<f32> {
+    let mut out = vec![0.0; frames];
+    e.render(&mut out);
+    out
+}
+
+/// Largest sample-to-sample step.
+fn max_step(out: &[f32]) -> f32 {
+    out.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f32::max)
+}
+
+/// Max step across a jump of `name` from `from` to `to`, and the steady max step at `to`.
+fn jump(name: &str, from: f32, to: f32, ramp: Option<f32>) -> (f32, f32) {
+    let mut e = sine_engine();
+    if let Some(t) = ramp {
+        assert!(e.set_smoothing(name, t));
+    }
+    e.set_parameter(name, from);
+    e.note_on(60, 1.0);
+    let before = render(&mut e, 4800);
+    e.set_parameter(name, to);
+    let across = render(&mut e, 4800);
+    let steady = render(&mut e, 4800);
+    let last = before[before.len() - 1];
+    (max_step(&across).max((across[0] - last).abs()), max_step(&steady))
+}
+
+#[test]
+fn ramps_are_linear_and_configurable() {
+    let mut values = [0.5; Smoothed::COUNT];
+    values[Smoothed::FilterCutoff as usize] = 100.0;
+    let mut s = Smoother::new(1000.0, values);
+    assert!(s.is_settled());
+    assert_eq!(s.time(Smoothed::MasterGain), DEFAULT_RAMP);
+    s.set_time(Smoothed::MasterGain, 0.01); // 10 samples at 1 kHz
+
+    let mut targets = values;
+    targets[Smoothed::MasterGain as usize] = 1.5;
+    targets[Smoothed::FilterCutoff as usize] = 6400.0;
+    let first = s.tick(&targets)[Smoothed::MasterGain as usize];
+    assert!((first - 0.6).abs() < 1e-5);
+    for _ in 0..4 {
+        s.tick(&targets);
+    }
+    assert!((s.value(Smoothed::MasterGain) - 1.0).abs() < 1e-5);
+    for _ in 0..5 {
+        s.tick(&targets);
+    }
+    assert_eq!(s.value(Smoothed::MasterGain), 1.5);
+    // the cutoff ramps in octaves: half its 20 samples in, it's 3 of 6 octaves up
+    assert!((s.value(Smoothed::FilterCutoff) - 800.0).abs() < 1.0);
+    for _ in 0..10 {
+        s.tick(&targets);
+    }
+    assert_eq!(s.value(Smoothed::FilterCutoff), 6400.0);
+    assert!(s.is_settled());
+
+    // a target moving mid-ramp restarts from where the value is
+    targets[Smoothed::MasterGain as usize] = 0.5;
+    s.tick(&targets);
+    targets[Smoothed::MasterGain as usize] = 2.0;
+    s.tick(&targets);
+    assert!(s.value(Smoothed::MasterGain) > 1.4 && s.value(Smoothed::MasterGain) < 1.5);
+}
+
+#[test]
+fn master_gain_jump_has_no_step() {
+    let (across, steady) = jump("master_gain", 0.1, 1.0, None);
+    assert!(across <= steady * 1.05, "{across} vs {steady}");
+    // with smoothing off the same jump clicks
+    let (across, steady) = jump("master_gain", 0.1, 1.0, Some(0.0));
+    assert!(across > steady * 2.0, "{across} vs {steady}");
+}
+
+#[test]
+fn oscillator_gain_jump_has_no_step() {
+    let (across, steady) = jump("osc0_gain", 0.05, 0.9, None);
+    assert!(across <= steady * 1.05, "{across} vs {steady}");
+}
+
+#[test]
+fn cutoff_jump_has_no_step() {
+    // the sweep passes the resonant peak over the note, so allow some overshoot
+    let (across, steady) = jump("filter_cutoff", 80.0, 16_000.0, None);
+    assert!(across <= steady * 1.5, "{across} vs {steady}");
+    let (across, steady) = jump("filter_cutoff", 80.0, 16_000.0, Some(0.0));
+    assert!(across > steady * 5.0, "{across} vs {steady}");
+}
+
+#[test]
+fn smoothing_names_and_first_note() {
+    let mut e = Engine::new(SR);
+    assert!(!e.set_smoothing("osc0_waveform", 0.1));
+    assert!(e.set_smoothing("osc1_volume", 0.25));
+    assert_eq!(e.smoothing("osc1_gain"), Some(0.25));
+    assert!(e.set_smoothing("all", 0.05));
+    assert_eq!(e.smoothing("fx_reverb_wet"), Some(0.05));
+    assert_eq!(e.smoothing("tempo"), None);
+
+    // settings made while silent apply in full from the first note
+    let mut e = sine_engine();
+    e.set_parameter("osc0_gain", 0.0);
+    e.note_on(60, 1.0);
+    assert!(render(&mut e, 512).iter().all(|&s| s == 0.0));
+}
@@ -0,0 +1,88 @@
+// Tempo sync: note divisions, synced LFO rates, song-position following and delay time.
+use serum_wasm_backend::delay::SimpleDelay;
+use serum_wasm_backend::lfo::{LfoShape, LFO};
+use serum_wasm_backend::modulation::{ModDest, ModSlot, ModSource};
+use serum_wasm_backend::tempo::SyncDivision;
+use serum_wasm_backend::Engine;
+
+const SR: f32 = 44_100.0;
+
+fn div(name: &str) -> SyncDivision {
+    SyncDivision::from_name(name).unwrap()
+}
+
+#[test]
+fn divisions_cover_straight_dotted_and_triplet_lengths() {
+    assert_eq!(div("1/4").seconds(120.0), 0.5);
+    assert_eq!(div("1/1").beats(), 4.0);
+    assert_eq!(div("1/8d").beats(), 0.75);
+    assert!((div("1/16t").beats() * 6.0 - 1.0).abs() < 1e-6);
+    assert_eq!(SyncDivision::default(), div("1/4"));
+    for d in SyncDivision::all() {
+        assert_eq!(SyncDivision::from_f32(d.to_index() as f32), d);
+    }
+    assert_eq!(SyncDivision::from_f32(99.0).name(), "1/64");
+}
+
+#[test]
+fn synced_lfo_runs_at_the_division() {
+    let mut l = LFO { amount: 1.0, shape: LfoShape::Saw, sync: true, division: div("1/8"), tempo: 150.0, ..Default::default() };
+    assert_eq!(l.hz(), 5.0);
+    l.rate = 0.1; // ignored while synced
+    let wraps = (0..SR as usize + 100)
+        .map(|_| {
+            let before = l.state.phase;
+            l.tick(1.0 / SR);
+            l.state.phase < before
+        })
+        .filter(|&w| w)
+        .count();
+    assert_eq!(wraps, 5);
+}
+
+#[test]
+fn synced_lfo_follows_the_song_position() {
+    // square over a bar at 120 BPM gates the amp: open for beats 0..2, shut for 2..4
+    let level_at = |beats: f64| {
+        let mut e = Engine::new(SR);
+        e.clear_mod_slots();
+        e.add_mod_slot(ModSlot::new(ModSource::Lfo0, ModDest::Amp, 1.0)).unwrap();
+        for (k, v) in [("waveform", 3.0), ("amount", 1.0), ("sync", 1.0), ("division", div("1/1").to_index() as f32)] {
+            e.set_parameter(&format!("lfo0_{k}"), v);
+        }
+        e.set_parameter("fx_delay_wet", 0.0);
+        e.set_parameter("fx_reverb_wet", 0.0);
+        e.set_transport(true, beats);
+        e.note_on(60, 1.0);
+        let mut out = vec![0.0; 4410];
+        e.render(&mut out);
+        out.iter().map(|s| s.abs()).fold(0.0, f32::max)
+    };
+    assert!(level_at(0.0) > 0.05);
+    assert!(level_at(2.0) < 1e-4, "half way through the bar the square is low");
+    assert!(level_at(6.0) < 1e-4, "same place in the next bar");
+
+    let mut e = Engine::new(SR);
+    e.set_tempo(90.0);
+    e.set_transport(true, 8.0);
+    e.render(&mut vec![0.0; SR as usize]);
+    assert!((e.song_position() - 9.5).abs() < 1e-6, "{}", e.song_position());
+    e.set_transport(false, 1.0);
+    e.render(&mut vec![0.0; 100]);
+    assert_eq!(e.song_position(), 1.0, "stopped transport holds");
+}
+
+#[test]
+fn synced_delay_repeats_on_the_division() {
+    let mut d = SimpleDelay::new(SR, 0.3, 0.0);
+    d.wet = 1.0;
+    d.sync = true;
+    d.division = div("1/8");
+    d.set_tempo(120.0);
+    assert_eq!(d.effective_time(), 0.25);
+    assert_eq!(d.time(), 0.3, "free time is kept for when sync is off");
+
+    let out: Vec<f32> = (0..SR as usize / 2).map(|i| d.process_stereo(if i == 0 { 1.0 } else { 0.0 }, 0.0).0).collect();
+    let peak = out.iter().enumerate().max_by(|a, b| a.1.abs().total_cmp(&b.1.abs())).unwrap().0;
+    assert_eq!(peak, (0.25 * SR) as usize);
+}
@@ -0,0 +1,164 @@
+// Microtuning: Scala .scl/.kbm parsing, MTS SysEx and voices following the table.
+use serum_wasm_backend::tuning::{Keymap, Scale, Tuning, TuningError};
+use serum_wasm_backend::util::midi_to_freq;
+use serum_wasm_backend::Engine;
+
+const SR: f32 = 44_100.0;
+
+const PENTATONIC: &str = "! slendro.scl
+!
+Just pentatonic
+ 5
+!
+ 9/8
+ 5/4
+ 3/2 fifth
+ 5/3
+ 2
+";
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() <= b * 1e-4
+}
+
+fn crossings(e: &mut Engine, frames: usize) -> usize {
+    let mut out = vec![0.0; frames];
+    e.render(&mut out);
+    out.windows(2).filter(|w| w[0] <= 0.0 && w[1] > 0.0).count()
+}
+
+#[test]
+fn default_tuning_is_twelve_tet() {
+    let t = Tuning::default();
+    for n in 0..128u8 {
+        assert!(close(t.freq(n), midi_to_freq(n)), "key {n}");
+    }
+    assert_eq!(t.reference_pitch(), 440.0);
+}
+
+#[test]
+fn scl_parses_ratios_cents_and_labels() {
+    let s = Scale::parse(PENTATONIC).unwrap();
+    assert_eq!(s.description, "Just pentatonic");
+    assert_eq!(s.cents.len(), 5);
+    assert!((s.cents[2] - 701.955).abs() < 1e-2);
+    assert!((s.cents[4] - 1200.0).abs() < 1e-3);
+
+    // comments may be indented, even between the degrees
+    let indented = Scale::parse("  ! indented.scl\nindented\n 2\n\t! the fifth\n 3/2\n   !\n 2/1\n").unwrap();
+    assert_eq!(indented.description, "indented");
+    assert_eq!(indented.cents.len(), 2);
+    assert!((indented.cents[0] - 701.955).abs() < 1e-2);
+
+    let cents = Scale::parse("quarter tones\n2\n50.0\n1200.\n").unwrap();
+    assert_eq!(cents.cents, vec![50.0, 1200.0]);
+    assert!((cents.degree_cents(-1) + 1150.0).abs() < 1e-3);
+}
+
+#[test]
+fn scl_errors() {
+    assert_eq!(Scale::parse("empty\n0\n"), Err(TuningError::EmptyScale));
+    let err = Scale::parse("short\n3\n100.0\n200.0\n").unwrap_err();
+    assert_eq!(err, TuningError::Truncated { expected: 3, found: 2 });
+    let err = Scale::parse("bad\n1\nfoo\n").unwrap_err();
+    assert_eq!(err.code(), "bad_line");
+    assert!(err.to_string().contains("line 3"));
+}
+
+#[test]
+fn scale_with_default_keymap_keeps_a440() {
+    let t = Tuning::from_scala(&Scale::parse(PENTATONIC).unwrap(), &Keymap::default());
+    // 69 is 9 degrees above middle C: one period plus 4 steps
+    assert!(close(t.freq(69), 440.0));
+    let c = 440.0 / (2.0 * 5.0 / 3.0);
+    assert!(close(t.freq(60), c));
+    assert!(close(t.freq(62), c * 5.0 / 4.0));
+    assert!(close(t.freq(65), c * 2.0));
+}
+
+#[test]
+fn kbm_maps_keys_and_leaves_gaps_silent() {
+    // white keys only, degree 0 on C4, C4 = 261.6256 Hz
+    let kbm = "! white keys\n12\n0\n127\n60\n60\n261.6256\n7\n0\n x\n1\nx\n2\n3\nx\n4\nx\n5\nx\n6\n";
+    let km = Keymap::parse(kbm).unwrap();
+    assert_eq!(km.map.len(), 12);
+    assert_eq!(km.map[1], None);
+    let t = Tuning::from_scala(&Scale::equal(7), &km);
+    assert!(close(t.freq(60), 261.6256));
+    assert_eq!(t.freq(61), 0.0);
+    let step = 2f32.powf(1.0 / 7.0);
+    assert!(close(t.freq(62), 261.6256 * step));
+    assert!(close(t.freq(72), 261.6256 * 2.0));
+    assert!(close(t.freq(59), 261.6256 / step));
+
+    assert_eq!(Keymap::parse("0\n0\n127\n60\n").unwrap_err().code(), "truncated");
+    assert_eq!(Keymap::parse("0\n100\n10\n60\n69\n440\n0\n").unwrap_err().code(), "bad_keymap");
+    assert_eq!(Keymap::parse("0\n0\n127\n60\n69\nloud\n0\n").unwrap_err().code(), "bad_line");
+}
+
+#[test]
+fn reference_pitch_rescales_the_table() {
+    let mut e = Engine::new(SR);
+    e.set_reference_pitch(432.0);
+    assert!(close(e.tuning().freq(69), 432.0));
+    assert!(close(e.tuning().freq(57), 216.0));
+    e.set_parameter("reference_pitch", 440.0);
+    assert!(close(e.tuning().freq(60), midi_to_freq(60)));
+}
+
+#[test]
+fn mts_single_note_and_bulk_dump() {
+    let mut e = Engine::new(SR);
+    // key 60 -> 69 + 1/2 semitone; key 61 "no change"
+    e.process_midi(&[0xF0, 0x7F, 0x7F, 0x08, 0x02, 0x00, 0x02, 60, 69, 0x40, 0x00, 61, 0x7F, 0x7F, 0x7F, 0xF7]);
+    assert!(close(e.tuning().freq(60), 440.0 * 2f32.powf(0.5 / 12.0)));
+    assert!(close(e.tuning().freq(61), midi_to_freq(61)));
+
+    // bulk dump: every key one semitone down
+    let mut dump = vec![0xF0, 0x7E, 0x7F, 0x08, 0x01, 0x00];
+    dump.extend_from_slice(b"down a semitone ");
+    for key in 0..128u8 {
+        dump.extend_from_slice(&[key.saturating_sub(1), 0, 0]);
+    }
+    dump.extend_from_slice(&[0x00, 0xF7]);
+    // split across calls, like a MIDI input delivering it in chunks
+    let (a, b) = dump.split_at(100);
+    e.process_midi(a);
+    e.process_midi(b);
+    assert!(close(e.tuning().freq(70), 440.0));
+    assert!(close(e.tuning().freq(1), midi_to_freq(0)));
+
+    // a SysEx broken by a status byte is dropped
+    e.reset_tuning();
+    e.process_midi(&[0xF0, 0x7F, 0x7F, 0x08, 0x02, 0x00, 0x01, 60, 72, 0x90, 0xF7]);
+    assert!(close(e.tuning().freq(60), midi_to_freq(60)));
+}
+
+#[test]
+fn voices_play_the_tuning_table() {
+    let mut e = Engine::new(SR);
+    for (k, v) in [("fx_delay_wet", 0.0), ("fx_reverb_wet", 0.0), ("osc0_waveform", 0.0), ("osc1_gain", 0.0), ("filter_cutoff", 20_000.0)] {
+        e.set_parameter(k, v);
+    }
+    e.load_scala("octaves\n1\n2/1\n", None).unwrap();
+    // every key in 1-step-per-octave tuning: 61 is an octave above 60, 69 stays 440
+    assert!(close(e.tuning().freq(70), 880.0));
+    e.note_on(70, 1.0);
+    let n = crossings(&mut e, SR as usize);
+    assert!((n as i32 - 880).abs() <= 2, "{n}");
+
+    // a failed load leaves the tuning alone
+    assert!(e.load_scala("bad\n1\nnope\n", None).is_err());
+    assert!(close(e.tuning().freq(70), 880.0));
+}
+
+#[test]
+fn unmapped_keys_do_not_sound() {
+    let mut e = Engine::new(SR);
+    e.load_scala("12\n12\n100.\n200.\n300.\n400.\n500.\n600.\n700.\n800.\n900.\n1000.\n1100.\n2/1\n", Some("0\n48\n72\n60\n69\n440\n0\n"))
+        .unwrap();
+    e.note_on(40, 1.0);
+    assert_eq!(e.active_voices(), 0);
+    e.note_on(60, 1.0);
+    assert_eq!(e.active_voices(), 1);
+}
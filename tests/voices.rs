@@ -0,0 +1,141 @@
+// Polyphony limits, stealing policies, same-note retrigger, the steal declick and the allocation-free voice pool.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use serum_wasm_backend::Engine;
+
+/// Counts this thread's heap allocations, so parallel tests don't interfere.
+struct Counting;
+thread_local! {
+    static ALLOCS: Cell<usize> = const { Cell::new(0) };
+}
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+#[global_allocator]
+static COUNTING: Counting = Counting;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCS.with(Cell::get);
+    f();
+    ALLOCS.with(Cell::get) - before
+}
+
+const SR: f32 = 44_100.0;
+
+fn engine(polyphony: f32, policy: f32) -> Engine {
+    let mut e = Engine::new(SR);
+    e.set_parameter("fx_delay_wet", 0.0);
+    e.set_parameter("fx_reverb_wet", 0.0);
+    e.set_parameter("polyphony", polyphony);
+    e.set_parameter("voice_steal", policy);
+    e
+}
+
+fn play(e: &mut Engine, notes: &[(u8, f32)]) {
+    let mut buf = vec![0.0; 441];
+    for &(n, vel) in notes {
+        e.note_on(n, vel);
+        e.render(&mut buf);
+    }
+}
+
+#[test]
+fn polyphony_caps_the_voice_count() {
+    let mut e = engine(4.0, 0.0);
+    play(&mut e, &[(60, 1.0), (62, 1.0), (64, 1.0), (65, 1.0), (67, 1.0), (69, 1.0)]);
+    assert_eq!(e.active_voices(), 4);
+    assert_eq!(e.active_notes(), [64, 65, 67, 69], "oldest went first");
+}
+
+#[test]
+fn each_policy_picks_its_victim() {
+    let played = [(60, 1.0), (72, 0.2), (48, 1.0)];
+    let survivors = |policy| {
+        let mut e = engine(3.0, policy);
+        play(&mut e, &played);
+        play(&mut e, &[(55, 1.0)]);
+        let mut notes = e.active_notes();
+        notes.sort();
+        notes
+    };
+    assert_eq!(survivors(0.0), [48, 55, 72], "oldest");
+    assert_eq!(survivors(1.0), [48, 55, 60], "quietest");
+    assert_eq!(survivors(2.0), [55, 60, 72], "lowest");
+    assert_eq!(survivors(3.0), [48, 55, 60], "highest");
+}
+
+#[test]
+fn released_voices_are_stolen_before_held_ones() {
+    let mut e = engine(2.0, 0.0);
+    e.set_parameter("env_release", 2.0);
+    play(&mut e, &[(60, 1.0), (64, 1.0)]);
+    e.note_off(64);
+    play(&mut e, &[(67, 1.0)]);
+    assert_eq!(e.active_notes(), [60, 67]);
+}
+
+#[test]
+fn same_note_retriggers_instead_of_stacking() {
+    let mut e = engine(8.0, 0.0);
+    play(&mut e, &[(60, 1.0), (60, 1.0), (60, 1.0)]);
+    assert_eq!(e.active_voices(), 1);
+    e.set_parameter("voice_retrigger", 0.0);
+    play(&mut e, &[(60, 1.0)]);
+    assert_eq!(e.active_voices(), 2);
+}
+
+#[test]
+fn stolen_voices_fade_instead_of_clicking() {
+    let mut e = engine(1.0, 0.0);
+    e.set_parameter("osc0_waveform", 0.0);
+    e.set_parameter("osc1_gain", 0.0);
+    e.set_parameter("filter_cutoff", 20_000.0);
+    e.note_on(60, 1.0);
+    let mut out = vec![0.0; 4410];
+    e.render(&mut out);
+    let peak = out.iter().map(|s| s.abs()).fold(0.0, f32::max);
+    e.note_on(67, 1.0);
+    let mut last = *out.last().unwrap();
+    e.render(&mut out);
+    let max_step = out.iter().map(|&s| {
+        let d = (s - last).abs();
+        last = s;
+        d
+    }).fold(0.0, f32::max);
+    assert!(max_step < peak * 0.2, "step {max_step} vs peak {peak}");
+    assert_eq!(e.active_notes(), [67]);
+}
+
+#[test]
+fn note_on_steals_and_render_never_allocate() {
+    let mut e = engine(4.0, 1.0);
+    // comb is the model with buffers to lose
+    e.set_parameter("filter_model", 3.0);
+    let mut buf = vec![0.0; 64];
+    let n = allocations(|| {
+        // far more notes than the pool holds, unrendered, so stolen voices pile up and get cut
+        for i in 0..200u32 {
+            e.note_on(36 + (i % 48) as u8, 1.0);
+        }
+        e.render(&mut buf);
+        for i in 0..48 {
+            e.note_off(36 + i);
+        }
+        e.process_midi(&[0xB0, 120, 0]);
+        e.note_on(60, 1.0);
+        e.set_parameter("voice_mode", 1.0);
+        for note in [62, 64, 62] {
+            e.note_on(note, 1.0);
+            e.render(&mut buf);
+        }
+    });
+    assert_eq!(n, 0);
+    assert!(e.active_voices() > 0);
+}
@@ -0,0 +1,104 @@
+// Wavetable .wav import.
+use serum_wasm_backend::wav::{parse_wavetable, WavError};
+use serum_wasm_backend::Engine;
+
+fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut c = id.to_vec();
+    c.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    c.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+        c.push(0);
+    }
+    c
+}
+
+fn wav(format: u16, bits: u16, channels: u16, clm: Option<&str>, data: &[u8]) -> Vec<u8> {
+    let mut fmt = Vec::new();
+    fmt.extend_from_slice(&format.to_le_bytes());
+    fmt.extend_from_slice(&channels.to_le_bytes());
+    fmt.extend_from_slice(&44_100u32.to_le_bytes());
+    let block = channels * bits / 8;
+    fmt.extend_from_slice(&(44_100 * block as u32).to_le_bytes());
+    fmt.extend_from_slice(&block.to_le_bytes());
+    fmt.extend_from_slice(&bits.to_le_bytes());
+
+    let mut body = b"WAVE".to_vec();
+    body.extend(chunk(b"fmt ", &fmt));
+    if let Some(text) = clm {
+        body.extend(chunk(b"clm ", text.as_bytes()));
+    }
+    body.extend(chunk(b"data", data));
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend(body);
+    out
+}
+
+const CLM_4: &str = "<!>4    01000000 wavetable (www.xferrecords.com)";
+
+#[test]
+fn reads_16_bit_pcm_frames_from_clm() {
+    let data: Vec<u8> = [0i16, 16_384, -16_384, 32_767, 1, 2, 3, 4]
+        .iter()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    let wt = parse_wavetable(&wav(1, 16, 1, Some(CLM_4), &data)).unwrap();
+    assert_eq!(wt.frame_size, 4);
+    assert_eq!(wt.frame_count(), 2);
+    assert_eq!(wt.samples[1], 0.5);
+    assert_eq!(wt.samples[2], -0.5);
+}
+
+#[test]
+fn reads_24_bit_pcm_and_float_and_takes_first_channel() {
+    let pcm24: Vec<u8> = [0x40_0000i32, -0x40_0000, 0, 0]
+        .iter()
+        .flat_map(|s| s.to_le_bytes()[..3].to_vec())
+        .collect();
+    let wt = parse_wavetable(&wav(1, 24, 1, Some(CLM_4), &pcm24)).unwrap();
+    assert_eq!(&wt.samples[..2], &[0.5, -0.5]);
+
+    // stereo float: right channel is ignored
+    let float: Vec<u8> = [0.25f32, 9.0, -0.75, 9.0, 0.0, 9.0, 1.0, 9.0]
+        .iter()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    let wt = parse_wavetable(&wav(3, 32, 2, Some(CLM_4), &float)).unwrap();
+    assert_eq!(wt.samples, vec![0.25, -0.75, 0.0, 1.0]);
+}
+
+#[test]
+fn defaults_to_2048_sample_frames_without_clm() {
+    let data = vec![0u8; 2048 * 2 * 3];
+    let wt = parse_wavetable(&wav(1, 16, 1, None, &data)).unwrap();
+    assert_eq!((wt.frame_size, wt.frame_count()), (2048, 3));
+}
+
+#[test]
+fn malformed_files_report_structured_errors() {
+    assert_eq!(parse_wavetable(b"nope").err(), Some(WavError::NotRiff));
+
+    let mut cut = wav(1, 16, 1, Some(CLM_4), &[0; 16]);
+    cut.truncate(cut.len() - 4);
+    assert_eq!(parse_wavetable(&cut).err(), Some(WavError::Truncated));
+
+    let e = parse_wavetable(&wav(1, 8, 1, Some(CLM_4), &[0; 8])).unwrap_err();
+    assert_eq!(e.code(), "unsupported_bit_depth");
+
+    let e = parse_wavetable(&wav(1, 16, 1, Some("garbage"), &[0; 16])).unwrap_err();
+    assert_eq!(e.code(), "bad_clm_chunk");
+
+    let e = parse_wavetable(&wav(1, 16, 1, Some(CLM_4), &[0; 4])).unwrap_err();
+    assert_eq!(e, WavError::NoCompleteFrame { samples: 2, frame_size: 4 });
+}
+
+#[test]
+fn engine_loads_every_frame() {
+    let data: Vec<u8> = (0..2048 * 5).flat_map(|i| ((i % 100) as i16).to_le_bytes()).collect();
+    let mut e = Engine::new(48_000.0);
+    let frames = e
+        .load_wavetable_wav(1, &wav(1, 16, 1, Some("<!>2048 01000000 wavetable"), &data))
+        .unwrap();
+    assert_eq!(frames, 5);
+    assert_eq!(e.wavetable_frame_count(1), 5);
+}